@@ -15,14 +15,18 @@ pub enum AcpError {
     ParseRule(#[from] rule::RuleError),
 }
 
+/// Rules of the access control policy and the action applied to traffic matching none of them
 #[derive(Debug)]
-pub struct Acp(Vec<Rule>);
+pub struct Acp {
+    rules: Vec<Rule>,
+    default_action: Option<String>,
+}
 
 impl Deref for Acp {
     type Target = Vec<Rule>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.rules
     }
 }
 
@@ -30,17 +34,109 @@ impl TryFrom<Vec<String>> for Acp {
     type Error = AcpError;
 
     fn try_from(lines: Vec<String>) -> Result<Self, Self::Error> {
-        let mut reader = Reader::from(lines);
+        let (acp, _) = Acp::from_lines_with_progress(lines, false, |_, _| {})?;
+        Ok(acp)
+    }
+}
 
-        let mut rules = vec![];
+impl Acp {
+    /// Parses the rules like try_from, but a rule failing to parse is skipped instead of
+    /// failing the whole policy. Errors of the skipped rules are returned in the file order
+    pub fn from_lines_skipping_errors(lines: Vec<String>) -> (Self, Vec<rule::RuleError>) {
+        Acp::from_lines_with_progress(lines, true, |_, _| {})
+            .expect("Logic error: rule errors are skipped, parsing the policy can't fail")
+    }
+
+    /// Parses the rules one by one, progress is called with the number of parsed rule blocks
+    /// and the number of all of them after every block, e.g. to draw a progress bar.
+    /// With skip_errors a rule failing to parse is skipped and its error returned,
+    /// otherwise the first error fails the whole policy
+    pub fn from_lines_with_progress(
+        lines: Vec<String>,
+        skip_errors: bool,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<(Self, Vec<rule::RuleError>), AcpError> {
+        let (lines, default_action) = split_default_action(lines);
+        let rule_lines = rule_lines(lines);
+        let total = rule_lines.len();
 
-        while let Some(rule_lines) = reader.next_rule() {
-            let rule = Rule::try_from(rule_lines)?;
-            rules.push(rule);
+        let mut rules = vec![];
+        let mut errors = vec![];
+
+        for (idx, rule_lines) in rule_lines.into_iter().enumerate() {
+            match Rule::try_from(rule_lines) {
+                Ok(rule) => rules.push(rule),
+                Err(error) if skip_errors => errors.push(error),
+                Err(error) => return Err(error.into()),
+            }
+            progress(idx + 1, total);
         }
 
-        Ok(Self(rules))
+        let acp = Self {
+            rules,
+            default_action,
+        };
+        Ok((acp, errors))
+    }
+}
+
+/// Lines of every rule of the policy, not parsed yet. The default action block is left out
+pub fn split_rules(lines: Vec<String>) -> Vec<Vec<String>> {
+    let (lines, _) = split_default_action(lines);
+    rule_lines(lines)
+}
+
+fn rule_lines(lines: Vec<String>) -> Vec<Vec<String>> {
+    let mut reader = Reader::from(lines);
+
+    let mut rules = vec![];
+    while let Some(rule_lines) = reader.next_rule() {
+        rules.push(rule_lines);
     }
+
+    rules
+}
+
+/// The default action is set in the policy header above the rules:
+/// =========[ Some_Cloud-Policy_1 ]==========
+/// Default Action            : Block
+///
+/// Some dumps have a default action block following the last rule instead, it is not a rule
+/// itself, so the rules end there:
+/// ===============[ Default Action ]================
+/// Default Action            : Block all traffic
+fn split_default_action(mut lines: Vec<String>) -> (Vec<String>, Option<String>) {
+    let first_rule = lines
+        .iter()
+        .position(|line| line.contains("[ Rule: "))
+        .unwrap_or(lines.len());
+    let header_action = default_action_in(&lines[..first_rule]);
+
+    let Some(block_start) = lines[first_rule..]
+        .iter()
+        .position(|line| line.contains("[ Default Action ]"))
+        .map(|idx| first_rule + idx)
+    else {
+        return (lines, header_action);
+    };
+
+    let block_action = default_action_in(&lines[block_start..]);
+    lines.truncate(block_start);
+
+    (lines, block_action.or(header_action))
+}
+
+fn default_action_in(lines: &[String]) -> Option<String> {
+    lines
+        .iter()
+        .find_map(|line| default_action_value(line))
+        .filter(|action| !action.is_empty())
+}
+
+/// "Default Action : Access Control: Block All Traffic" -> "Access Control: Block All Traffic"
+fn default_action_value(line: &str) -> Option<String> {
+    let (title, action) = line.split_once(':')?;
+    (title.trim() == "Default Action").then(|| action.trim().to_string())
 }
 
 impl Acp {
@@ -63,4 +159,97 @@ impl Acp {
     pub fn rule_by_idx(&self, idx: usize) -> Option<&Rule> {
         self.get(idx)
     }
+
+    /// Action of the policy for traffic not matched by any rule, None if the dump has none
+    pub fn default_action(&self) -> Option<&str> {
+        self.default_action.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn acp_from(content: &str) -> Acp {
+        Acp::try_from(content.lines().map(String::from).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn test_default_action() {
+        let acp = acp_from(
+            "----------[ Rule: Web ]-----------
+    Action                : Allow
+    Destination Networks  : 10.0.0.0/24
+===============[ Default Action ]================
+Default Action            : Block all traffic
+Logging Configuration
+  DC                      : Disabled",
+        );
+
+        assert_eq!(acp.rule_count(), 1);
+        assert_eq!(acp.default_action(), Some("Block all traffic"));
+        assert_eq!(acp.rule_by_name("Web").unwrap().get_action(), Some("Allow"));
+        assert_eq!(acp.capacity(), 1);
+    }
+
+    #[test]
+    fn test_no_default_action() {
+        let acp = acp_from(
+            "----------[ Rule: Web ]-----------
+    Destination Networks  : 10.0.0.0/24",
+        );
+
+        assert_eq!(acp.rule_count(), 1);
+        assert_eq!(acp.default_action(), None);
+
+        let acp = acp_from("Default Action : Access Control: Block All Traffic");
+        assert!(acp.is_empty());
+        assert_eq!(
+            acp.default_action(),
+            Some("Access Control: Block All Traffic")
+        );
+    }
+
+    #[test]
+    fn test_default_action_in_policy_header() {
+        let acp = acp_from(
+            "=========[ Some_Cloud-Policy_1 ]==========
+Description               :  
+Default Action            : Block
+Logging Configuration
+    DC                    : Disabled
+----------[ Rule: Web ]-----------
+    Action                : Allow
+    Destination Networks  : 10.0.0.0/24
+----------[ Rule: Dns ]-----------
+    Destination Networks  : 10.0.1.0/24",
+        );
+
+        assert_eq!(acp.rule_count(), 2);
+        assert_eq!(acp.default_action(), Some("Block"));
+        assert_eq!(acp.capacity(), 2);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let lines: Vec<String> = "----------[ Rule: Bad-1 ]-----------
+    Source Networks       : 10.0.0.0/33
+----------[ Rule: Web ]-----------
+    Destination Networks  : 10.0.0.0/24
+----------[ Rule: Bad-2 ]-----------
+    Destination Ports     : HTTP (protocol 6, port 80-)"
+            .lines()
+            .map(String::from)
+            .collect();
+
+        let error = Acp::try_from(lines.clone()).unwrap_err();
+        assert!(error.to_string().contains("10.0.0.0/33"), "{}", error);
+
+        let (acp, errors) = Acp::from_lines_skipping_errors(lines);
+        assert_eq!(acp.rule_count(), 1);
+        assert!(acp.rule_by_name("Web").is_some());
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].to_string().contains("10.0.0.0/33"));
+        assert!(errors[1].to_string().contains("Invalid end port"));
+    }
 }
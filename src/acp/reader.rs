@@ -9,7 +9,8 @@
 ///
 /// # Examples
 ///
-/// ```rust
+/// ```ignore
+/// // Reader is private to the acp module, so the example is not compiled as a doc-test
 /// let mut reader = Reader { lines: vec![
 ///     "Some text".to_string(),
 ///     "Rule: First Rule".to_string(),
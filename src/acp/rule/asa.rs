@@ -0,0 +1,309 @@
+use super::network_object::IPv4;
+use super::protocol_object::protocol_list_optimized::ProtocolListOptimized;
+use super::{restricting, Protocol, Rule};
+
+/// ACL name FTD uses for the access control policy
+pub const DEFAULT_ACL_NAME: &str = "CSM_FW_ACL_";
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum AsaError {
+    #[error("Rule {rule} can't be exported to ASA: {reason}")]
+    Unsupported { rule: String, reason: String },
+}
+
+/// Renders the optimized rule as ASA extended access-list lines, one ACE per line:
+///  access-list CSM_FW_ACL_ extended permit tcp 10.0.0.0 255.255.255.0 host 10.1.1.1 eq 443
+///
+/// Supported: Allow, Trust and Block actions, host and subnet networks (a missing section is "any"),
+/// TCP and UDP ports (missing ports or "protocol ip" are "ip") and time ranges.
+/// Not supported: application filtering, ICMP and other L3 protocols,
+/// Monitor and Interactive Block actions, negated ports that can't be cut out of the port ranges
+/// (the optimized rule would match the negated traffic too).
+/// As in ace_count, with both source and destination ports set only protocols present on both
+/// sides produce lines.
+pub fn to_asa_acl(rule: &Rule, acl_name: &str) -> Result<Vec<String>, AsaError> {
+    let unsupported = |reason: String| AsaError::Unsupported {
+        rule: rule.get_name().to_string(),
+        reason,
+    };
+
+    if rule.is_application_filtered() {
+        return Err(unsupported("application filtering".to_string()));
+    }
+
+    let action = match rule.get_action() {
+        Some("Allow") | Some("Trust") => "permit",
+        Some("Block") | Some("Block with reset") => "deny",
+        Some(action) => return Err(unsupported(format!("action {}", action))),
+        None => return Err(unsupported("missing action".to_string())),
+    };
+
+    if [&rule.src_protocols, &rule.dst_protocols]
+        .into_iter()
+        .flatten()
+        .any(|protocols| protocols.has_unapplied_exclusions())
+    {
+        return Err(unsupported("negated ports".to_string()));
+    }
+
+    let (src_protocols_opt, dst_protocols_opt) = rule.optimized_protocols();
+    let services = services(
+        restricting(src_protocols_opt),
+        restricting(dst_protocols_opt),
+    )
+    .map_err(unsupported)?;
+
+    let (src_networks_opt, dst_networks_opt) = rule.get_optimized_networks();
+    let src_networks = src_networks_opt.map_or(vec!["any".to_string()], |n| addresses(&n.cidrs()));
+    let dst_networks = dst_networks_opt.map_or(vec!["any".to_string()], |n| addresses(&n.cidrs()));
+
+    let time_range = rule
+        .get_time_range()
+        .map_or(String::new(), |t| format!(" time-range {}", t));
+
+    let mut lines = vec![];
+    for src in &src_networks {
+        for dst in &dst_networks {
+            for service in &services {
+                lines.push(format!(
+                    "access-list {} extended {} {} {}{} {}{}{}",
+                    acl_name,
+                    action,
+                    service.protocol,
+                    src,
+                    service.src_ports,
+                    dst,
+                    service.dst_ports,
+                    time_range
+                ));
+            }
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Protocol keyword with the source and destination port operators of a single ACE
+struct Service {
+    protocol: &'static str,
+    src_ports: String,
+    dst_ports: String,
+}
+
+fn services(
+    src_ports: Option<&Vec<ProtocolListOptimized>>,
+    dst_ports: Option<&Vec<ProtocolListOptimized>>,
+) -> Result<Vec<Service>, String> {
+    let service = |protocol,
+                   src: Option<&ProtocolListOptimized>,
+                   dst: Option<&ProtocolListOptimized>| Service {
+        protocol,
+        src_ports: src.map_or(String::new(), port_operator),
+        dst_ports: dst.map_or(String::new(), port_operator),
+    };
+
+    match (src_ports, dst_ports) {
+        (None, None) => Ok(vec![service("ip", None, None)]),
+        (Some(src), None) => src
+            .iter()
+            .map(|s| Ok(service(protocol_keyword(s)?, Some(s), None)))
+            .collect(),
+        (None, Some(dst)) => dst
+            .iter()
+            .map(|d| Ok(service(protocol_keyword(d)?, None, Some(d))))
+            .collect(),
+        (Some(src), Some(dst)) => {
+            let mut services = vec![];
+            for s in src {
+                let protocol = protocol_keyword(s)?;
+                for d in dst {
+                    if protocol_keyword(d)? == protocol {
+                        services.push(service(protocol, Some(s), Some(d)));
+                    }
+                }
+            }
+            Ok(services)
+        }
+    }
+}
+
+/// Combined TCP and UDP entries (see OptimizeOptions) are reported only, never exported
+fn protocol_keyword(ports: &ProtocolListOptimized) -> Result<&'static str, String> {
+    if ports.is_tcp_and_udp() {
+        return Err(format!("combined TCP and UDP entry ({})", ports));
+    }
+    match ports.get_protocol() {
+        Protocol::Number(6) => Ok("tcp"),
+        Protocol::Number(17) => Ok("udp"),
+        protocol => Err(format!("protocol {} ({})", protocol, ports)),
+    }
+}
+
+/// " eq 443", " range 80 81", or nothing for any port
+fn port_operator(ports: &ProtocolListOptimized) -> String {
+    match ports.get_ports() {
+        (0, 65535) => String::new(),
+        (start, end) if start == end => format!(" eq {}", start),
+        (start, end) => format!(" range {} {}", start, end),
+    }
+}
+
+/// "10.0.0.0/24" -> "10.0.0.0 255.255.255.0", "10.0.0.1/32" -> "host 10.0.0.1", "0.0.0.0/0" -> "any"
+fn addresses(cidrs: &[String]) -> Vec<String> {
+    cidrs
+        .iter()
+        .map(|cidr| match cidr.split_once('/') {
+            Some((_, "0")) => "any".to_string(),
+            Some((ip, "32")) => format!("host {}", ip),
+            Some((ip, mask_length)) => {
+                let mask_length = mask_length.parse::<u32>().unwrap_or(32);
+                let mask = u32::MAX.checked_shl(32 - mask_length).unwrap_or(0);
+                format!("{} {}", ip, IPv4::from(mask))
+            }
+            None => format!("host {}", cidr),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acp::rule::{OptimizeOptions, ProtocolObject};
+
+    fn rule_from(lines: &[&str]) -> Rule {
+        Rule::try_from(lines.iter().map(|l| l.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn test_permit_tcp() {
+        let rule = rule_from(&[
+            "----------[ Rule: Web ]-----------",
+            "    Action                : Allow",
+            "    Source Networks       : 10.0.0.0/25",
+            "                            10.0.0.128/25",
+            "    Destination Networks  : Server (10.1.1.1)",
+            "    Destination Ports     : HTTPS (protocol 6, port 443)",
+        ]);
+
+        assert_eq!(
+            to_asa_acl(&rule, DEFAULT_ACL_NAME).unwrap(),
+            vec![
+                "access-list CSM_FW_ACL_ extended permit tcp 10.0.0.0 255.255.255.0 host 10.1.1.1 eq 443"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ports_and_any() {
+        let rule = rule_from(&[
+            "----------[ Rule: Block ]-----------",
+            "    Action                : Block",
+            "    Source Ports          : ephemeral (protocol 6, port 1024-65535)",
+            "                            DNS (protocol 17, port 53)",
+            "    Destination Ports     : HTTP (protocol 6, port 80-81)",
+        ]);
+
+        assert_eq!(
+            to_asa_acl(&rule, "OUTSIDE").unwrap(),
+            vec!["access-list OUTSIDE extended deny tcp any range 1024 65535 any range 80 81"]
+        );
+
+        let rule = rule_from(&[
+            "----------[ Rule: Any ]-----------",
+            "    Action                : Trust",
+            "    Time Range            : Weekdays",
+        ]);
+        assert_eq!(
+            to_asa_acl(&rule, "OUTSIDE").unwrap(),
+            vec!["access-list OUTSIDE extended permit ip any any time-range Weekdays"]
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let rule = rule_from(&[
+            "----------[ Rule: Ping ]-----------",
+            "    Action                : Allow",
+            "    Destination Ports     : Echo (protocol 1, type 8)",
+        ]);
+        assert!(matches!(
+            to_asa_acl(&rule, DEFAULT_ACL_NAME),
+            Err(AsaError::Unsupported { .. })
+        ));
+
+        let rule = rule_from(&[
+            "----------[ Rule: Monitor ]-----------",
+            "    Action                : Monitor",
+        ]);
+        assert_eq!(
+            to_asa_acl(&rule, DEFAULT_ACL_NAME).unwrap_err().to_string(),
+            "Rule Monitor can't be exported to ASA: action Monitor"
+        );
+    }
+
+    #[test]
+    fn test_combined_tcp_udp_not_exported() {
+        let ports = ProtocolObject::try_from(&vec![
+            "Destination Ports : WEB (protocol 6, port 80)".to_string(),
+            "WEB-UDP (protocol 17, port 80)".to_string(),
+        ])
+        .unwrap();
+        let merged = ports.optimize_with(OptimizeOptions {
+            merge_tcp_udp: true,
+        });
+
+        assert_eq!(merged.len(), 1);
+        assert!(protocol_keyword(&merged[0])
+            .unwrap_err()
+            .starts_with("combined TCP and UDP entry"));
+        assert_eq!(protocol_keyword(&ports.optimize()[0]), Ok("tcp"));
+    }
+
+    #[test]
+    fn test_negated_ports() {
+        let rule = rule_from(&[
+            "----------[ Rule: Not-HTTP ]-----------",
+            "    Action                : Allow",
+            "    Destination Networks  : 10.1.1.0/24",
+            "    Destination Ports     : HTTP (protocol 6, port 80) negate",
+        ]);
+        assert_eq!(
+            to_asa_acl(&rule, DEFAULT_ACL_NAME).unwrap_err().to_string(),
+            "Rule Not-HTTP can't be exported to ASA: negated ports"
+        );
+
+        let rule = rule_from(&[
+            "----------[ Rule: Any-but-HTTP ]-----------",
+            "    Action                : Block",
+            "    Destination Ports     : ANY (protocol ip)",
+            "                            HTTP (protocol 6, port 80) negate",
+        ]);
+        assert_eq!(
+            to_asa_acl(&rule, DEFAULT_ACL_NAME).unwrap_err().to_string(),
+            "Rule Any-but-HTTP can't be exported to ASA: negated ports"
+        );
+
+        let rule = rule_from(&[
+            "----------[ Rule: High ]-----------",
+            "    Action                : Allow",
+            "    Destination Ports     : HIGH (protocol 6, port 1024-2048)",
+            "                            ALT (protocol 6, port 1500-1600) negate",
+        ]);
+        assert_eq!(
+            to_asa_acl(&rule, DEFAULT_ACL_NAME).unwrap(),
+            vec![
+                "access-list CSM_FW_ACL_ extended permit tcp any any range 1024 1499",
+                "access-list CSM_FW_ACL_ extended permit tcp any any range 1601 2048",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_addresses() {
+        let cidrs = ["0.0.0.0/0", "10.0.0.1/32", "172.16.0.0/12"].map(String::from);
+        assert_eq!(
+            addresses(&cidrs),
+            vec!["any", "host 10.0.0.1", "172.16.0.0 255.240.0.0"]
+        );
+    }
+}
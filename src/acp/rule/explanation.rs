@@ -0,0 +1,173 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::protocol_object::description::DescriptionType;
+
+/// Order of entry names in the optimized output: case-insensitive, ties broken by the exact name,
+/// so "LDP" and "ldp" are named the same way whatever order they appear in
+pub fn name_order(a: &str, b: &str) -> Ordering {
+    a.to_lowercase()
+        .cmp(&b.to_lowercase())
+        .then_with(|| a.cmp(b))
+}
+
+/// Single merge performed by the optimizer
+/// merged - entries merged so far
+/// next - entry merged into them
+/// capacity - capacity of the merged entry after this step
+#[derive(Debug)]
+pub struct OptimizationStep {
+    pub merged: String,
+    pub verb: DescriptionType,
+    pub next: String,
+    pub capacity: u64,
+}
+
+/// Optimization steps of a single rule section (networks or ports)
+#[derive(Debug)]
+pub struct SectionExplanation {
+    pub name: String,
+    pub capacity: u64,
+    pub optimized_capacity: u64,
+    pub steps: Vec<OptimizationStep>,
+}
+
+/// Two entries of a network object sharing at least one address
+/// section - name of the network object, e.g. "Source Networks"
+/// first - entry with the lower start address
+#[derive(Debug)]
+pub struct Overlap {
+    pub section: String,
+    pub first: String,
+    pub verb: DescriptionType,
+    pub second: String,
+}
+
+impl fmt::Display for Overlap {
+    // Example:
+    // Source Networks: 192.168.1.0/24 SHADOWS 192.168.1.128/25
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} {} {}",
+            self.section, self.first, self.verb, self.second
+        )
+    }
+}
+
+impl fmt::Display for OptimizationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} -> capacity {}",
+            self.merged, self.verb, self.next, self.capacity
+        )
+    }
+}
+
+impl fmt::Display for SectionExplanation {
+    // Example:
+    // --- Source Networks ---
+    //     capacity: 3
+    //     step 1: 10.0.0.0/24 ADJOINS 10.0.1.0/24 -> capacity 1
+    //     optimized capacity: 1
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "--- {} ---", self.name)?;
+        writeln!(f, "\tcapacity: {}", self.capacity)?;
+
+        if self.steps.is_empty() {
+            writeln!(f, "\tno entries merged")?;
+        }
+        for (idx, step) in self.steps.iter().enumerate() {
+            writeln!(f, "\tstep {}: {}", idx + 1, step)?;
+        }
+
+        write!(f, "\toptimized capacity: {}", self.optimized_capacity)
+    }
+}
+
+/// Port entries of one protocol on each side of the rule, None if the side doesn't restrict ports
+#[derive(Debug, PartialEq)]
+pub struct FactorTerm {
+    pub protocol: String,
+    pub src_count: Option<u64>,
+    pub dst_count: Option<u64>,
+}
+
+impl FactorTerm {
+    /// A side without entries of the protocol matches any port of it and counts as 1
+    pub fn product(&self) -> u64 {
+        self.src_count.unwrap_or(1) * self.dst_count.unwrap_or(1)
+    }
+}
+
+/// Frequency of every protocol in source and destination ports and their products,
+/// the protocol factor is the sum of the products (1 if no ports are restricted)
+#[derive(Debug)]
+pub struct ProtocolFactorExplanation {
+    pub terms: Vec<FactorTerm>,
+}
+
+impl ProtocolFactorExplanation {
+    pub fn factor(&self) -> u64 {
+        match self.terms.is_empty() {
+            true => 1,
+            false => self.terms.iter().map(|term| term.product()).sum(),
+        }
+    }
+}
+
+impl fmt::Display for ProtocolFactorExplanation {
+    // Example:
+    // --- Protocol factor ---
+    //     source ports: protocol 6 x2, protocol 17 x1
+    //     destination ports: protocol 6 x3, protocol 17 x1
+    //     protocol 6: 2 * 3 = 6
+    //     protocol 17: 1 * 1 = 1
+    //     protocol factor: 6 + 1 = 7
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "--- Protocol factor ---")?;
+
+        if self.terms.is_empty() {
+            return write!(f, "\tno ports restricted, protocol factor: 1");
+        }
+
+        let frequencies = |count: fn(&FactorTerm) -> Option<u64>| {
+            let counts = self
+                .terms
+                .iter()
+                .filter_map(|term| count(term).map(|c| format!("{} x{}", term.protocol, c)))
+                .collect::<Vec<_>>();
+            match counts.is_empty() {
+                true => "any".to_string(),
+                false => counts.join(", "),
+            }
+        };
+        writeln!(f, "\tsource ports: {}", frequencies(|t| t.src_count))?;
+        writeln!(f, "\tdestination ports: {}", frequencies(|t| t.dst_count))?;
+
+        let count = |c: Option<u64>| c.map_or("1 (any)".to_string(), |c| c.to_string());
+        for term in self.terms.iter() {
+            writeln!(
+                f,
+                "\t{}: {} * {} = {}",
+                term.protocol,
+                count(term.src_count),
+                count(term.dst_count),
+                term.product()
+            )?;
+        }
+
+        let products = self
+            .terms
+            .iter()
+            .map(|term| term.product().to_string())
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            "\tprotocol factor: {} = {}",
+            products.join(" + "),
+            self.factor()
+        )
+    }
+}
@@ -1,21 +1,126 @@
 pub mod network_object;
-use std::collections::HashMap;
+use std::cell::OnceCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use network_object::NetworkObject;
 
 mod protocol_object;
-use protocol_object::ProtocolObject;
+pub use protocol_object::{OptimizeOptions, ProtocolObject};
+
+pub use network_object::utilities::{set_max_group_depth, DEFAULT_MAX_GROUP_DEPTH};
+pub use protocol_object::set_lenient_protocols;
+pub use protocol_object::Protocol;
+
+pub mod asa;
+pub mod explanation;
+use explanation::SectionExplanation;
 
 use network_object::network_object_optimized::NetworkObjectOptimized;
-use protocol_object::protocol_list_optimized::ProtocolListOptimized;
+use protocol_object::protocol_list_optimized::{self, ProtocolListOptimized};
+
+/// Capacity of a single rule block of "show access-control-config"
+///
+/// ```rust
+/// let rule = "----------[ Rule: Custom_rule2 | FM-15046 ]-----------
+///     Source Networks       : Internal (group)
+///         OBJ-192.168.0.0 (192.168.0.0/16)
+///         OBJ-172.17.0.0 (172.17.0.0/16)
+///         OBJ-10.11.0.0 (10.11.0.0/16)
+///       OBJ-198.187.64.0_18 (198.187.64.0/18)
+///     Destination Networks  : OBJ-10.138.0.0_16 (10.138.0.0/16)
+///         10.0.0.0/8
+///         204.99.0.0/16
+///         172.16.0.0/12
+///       OBJ-192.168.243.0_24 (192.168.243.0/24)
+///       OBJ-10.18.46.62-69 (10.18.46.62-10.18.46.69)
+///     Source Ports     : ephemeral (protocol 6, port 1024)
+///     Destination Ports  : HTTPS (protocol 6, port 443)
+///     Logging Configuration";
+///
+/// assert_eq!(ftd_acl_optimizer::acp::rule::rule_capacity(rule).unwrap(), 32);
+/// ```
+pub fn rule_capacity(rule_text: &str) -> Result<u64, RuleError> {
+    Ok(rule_from_text(rule_text)?.capacity())
+}
+
+/// Capacity of a single rule block after the optimization
+///
+/// ```rust
+/// let rule = "----------[ Rule: Web ]-----------
+///     Source Networks       : 10.0.0.0/24
+///                             10.0.1.0/24
+///     Destination Ports     : HTTP (protocol 6, port 80)
+///                             HTTP-ALT (protocol 6, port 81)";
+///
+/// // adjacent ports are merged by FTD itself, so they count once in both capacities
+/// assert_eq!(ftd_acl_optimizer::acp::rule::rule_capacity(rule).unwrap(), 2);
+/// assert_eq!(ftd_acl_optimizer::acp::rule::optimized_rule_capacity(rule).unwrap(), 1);
+/// ```
+pub fn optimized_rule_capacity(rule_text: &str) -> Result<u64, RuleError> {
+    Ok(rule_from_text(rule_text)?.optimized_capacity())
+}
+
+fn rule_from_text(rule_text: &str) -> Result<Rule, RuleError> {
+    Rule::try_from(
+        rule_text
+            .lines()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>(),
+    )
+}
 
-#[derive(Debug)]
+/// Optimized source and destination protocols
+type OptimizedProtocols = (
+    Option<Vec<ProtocolListOptimized>>,
+    Option<Vec<ProtocolListOptimized>>,
+);
+
+/// Matchers of a rule that don't restrict the traffic, see Rule::is_overly_broad
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BroadnessReport {
+    pub any_source: bool,
+    pub any_destination: bool,
+    pub any_protocol: bool,
+}
+
+impl BroadnessReport {
+    /// At least one of source, destination or protocol matches anything
+    pub fn is_broad(&self) -> bool {
+        self.any_source || self.any_destination || self.any_protocol
+    }
+}
+
+impl std::fmt::Display for BroadnessReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let matchers = [
+            (self.any_source, "any source"),
+            (self.any_destination, "any destination"),
+            (self.any_protocol, "any protocol"),
+        ];
+        let matchers: Vec<&str> = matchers
+            .into_iter()
+            .filter(|(any, _)| *any)
+            .map(|(_, matcher)| matcher)
+            .collect();
+
+        write!(f, "{}", matchers.join(", "))
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Rule {
     name: String,
+    action: Option<String>,
+    description: Option<String>,
+    time_range: Option<String>,
     src_networks: Option<NetworkObject>,
     dst_networks: Option<NetworkObject>,
     src_protocols: Option<ProtocolObject>,
     dst_protocols: Option<ProtocolObject>,
+    applications: Vec<String>,
+    // capacity and optimized_capacity share the protocol optimization, computed once on demand
+    optimized_protocols: OnceCell<OptimizedProtocols>,
+    protocol_factor: OnceCell<u64>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -57,66 +162,44 @@ impl TryFrom<Vec<String>> for Rule {
     fn try_from(lines: Vec<String>) -> Result<Self, Self::Error> {
         // let mut reader = Reader::from(lines);
 
-        let name = get_name(&lines)?;
+        let lines = with_full_titles(strip_common_indentation(lines));
+        let lines = without_logging(&lines);
 
-        let source_networks: Vec<_> = lines_from_till(
-            &lines,
-            "Source Networks",
-            &[
-                "Destination Networks",
-                "Source Ports",
-                "Destination Ports",
-                "Logging",
-                "Users",
-                "URLs",
-                "Safe Search",
-                "Logging Configuration",
-            ],
-        )?;
+        let name = get_name(lines)?;
+        let action = get_action(lines);
+        let description = get_description(&lines_from_till(
+            lines,
+            "Description",
+            &end_markers("Description"),
+        )?);
+
+        let source_networks: Vec<_> =
+            lines_from_till(lines, "Source Networks", &end_markers("Source Networks"))?;
         let destination_networks: Vec<_> = lines_from_till(
-            &lines,
+            lines,
             "Destination Networks",
-            &[
-                "Source Networks",
-                "Source Ports",
-                "Destination Ports",
-                "Logging",
-                "Users",
-                "URLs",
-                "Safe Search",
-                "Logging Configuration",
-            ],
+            &end_markers("Destination Networks"),
         )?;
 
-        let source_ports: Vec<_> = lines_from_till(
-            &lines,
-            "Source Ports",
-            &[
-                "Source Networks",
-                "Destination Networks",
-                "Destination Ports",
-                "Logging",
-                "Users",
-                "URLs",
-                "Safe Search",
-                "Logging Configuration",
-            ],
-        )?;
+        let source_ports: Vec<_> =
+            lines_from_till(lines, "Source Ports", &end_markers("Source Ports"))?;
         let destination_ports: Vec<_> = lines_from_till(
-            &lines,
+            lines,
             "Destination Ports",
-            &[
-                "Source Networks",
-                "Destination Networks",
-                "Source Ports",
-                "Logging",
-                "Users",
-                "URLs",
-                "Safe Search",
-                "Logging Configuration",
-            ],
+            &end_markers("Destination Ports"),
         )?;
 
+        let applications = get_applications(&lines_from_till(
+            lines,
+            "Applications",
+            &end_markers("Applications"),
+        )?);
+        let time_range = get_time_range(&lines_from_till(
+            lines,
+            "Time Range",
+            &end_markers("Time Range"),
+        )?);
+
         let src_networks = match source_networks.is_empty() {
             true => None,
             false => Some(NetworkObject::try_from(&source_networks).map_err(|e| {
@@ -145,13 +228,123 @@ impl TryFrom<Vec<String>> for Rule {
             false => Some(ProtocolObject::try_from(&destination_ports)?),
         };
 
-        Ok(Self {
+        let mut builder = Builder::new(name).with_applications(applications);
+        if let Some(action) = action {
+            builder = builder.with_action(action);
+        }
+        if let Some(description) = description {
+            builder = builder.with_description(description);
+        }
+        if let Some(time_range) = time_range {
+            builder = builder.with_time_range(time_range);
+        }
+        if let Some(src_networks) = src_networks {
+            builder = builder.with_src_networks(src_networks);
+        }
+        if let Some(dst_networks) = dst_networks {
+            builder = builder.with_dst_networks(dst_networks);
+        }
+        if let Some(src_protocols) = src_protocols {
+            builder = builder.with_src_protocols(src_protocols);
+        }
+        if let Some(dst_protocols) = dst_protocols {
+            builder = builder.with_dst_protocols(dst_protocols);
+        }
+
+        Ok(builder.build())
+    }
+}
+
+/// Builds a rule from already parsed sections, a missing section means "any"
+pub struct Builder {
+    name: String,
+    action: Option<String>,
+    description: Option<String>,
+    time_range: Option<String>,
+    src_networks: Option<NetworkObject>,
+    dst_networks: Option<NetworkObject>,
+    src_protocols: Option<ProtocolObject>,
+    dst_protocols: Option<ProtocolObject>,
+    applications: Vec<String>,
+}
+
+impl Builder {
+    pub fn new(name: String) -> Self {
+        Builder {
             name,
-            src_networks,
-            dst_networks,
-            src_protocols,
-            dst_protocols,
-        })
+            action: None,
+            description: None,
+            time_range: None,
+            src_networks: None,
+            dst_networks: None,
+            src_protocols: None,
+            dst_protocols: None,
+            applications: vec![],
+        }
+    }
+
+    pub fn with_action(mut self, action: String) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    pub fn with_time_range(mut self, time_range: String) -> Self {
+        self.time_range = Some(time_range);
+        self
+    }
+
+    pub fn with_src_networks(mut self, networks: NetworkObject) -> Self {
+        self.src_networks = Some(networks);
+        self
+    }
+
+    pub fn with_dst_networks(mut self, networks: NetworkObject) -> Self {
+        self.dst_networks = Some(networks);
+        self
+    }
+
+    pub fn with_src_protocols(mut self, protocols: ProtocolObject) -> Self {
+        self.src_protocols = Some(protocols);
+        self
+    }
+
+    pub fn with_dst_protocols(mut self, protocols: ProtocolObject) -> Self {
+        self.dst_protocols = Some(protocols);
+        self
+    }
+
+    pub fn with_applications(mut self, applications: Vec<String>) -> Self {
+        self.applications = applications;
+        self
+    }
+
+    pub fn build(self) -> Rule {
+        Rule {
+            name: self.name,
+            action: self.action,
+            description: self.description,
+            time_range: self.time_range,
+            src_networks: self.src_networks,
+            dst_networks: self.dst_networks,
+            src_protocols: self.src_protocols,
+            dst_protocols: self.dst_protocols,
+            applications: self.applications,
+            optimized_protocols: OnceCell::new(),
+            protocol_factor: OnceCell::new(),
+        }
+    }
+}
+
+/// Rules are equal if they have the same name and match the same traffic (see same_matchers),
+/// the description is a comment and isn't compared
+impl PartialEq for Rule {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.same_matchers(other)
     }
 }
 
@@ -160,30 +353,295 @@ impl Rule {
         &self.name
     }
 
+    pub fn get_action(&self) -> Option<&str> {
+        self.action.as_deref()
+    }
+
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn get_time_range(&self) -> Option<&str> {
+        self.time_range.as_deref()
+    }
+
+    pub fn get_applications(&self) -> &[String] {
+        &self.applications
+    }
+
+    /// Application-based rules match less traffic than networks and ports allow,
+    /// so their capacity is an upper bound
+    pub fn is_application_filtered(&self) -> bool {
+        !self.applications.is_empty()
+    }
+
+    /// No networks, ports or applications at all: depending on FTD semantics the rule matches
+    /// everything or nothing, either way it is most likely a mistake
+    pub fn has_no_matchers(&self) -> bool {
+        self.src_networks.is_none()
+            && self.dst_networks.is_none()
+            && self.src_protocols.is_none()
+            && self.dst_protocols.is_none()
+            && self.applications.is_empty()
+    }
+
+    /// Number of ACEs produced by source and destination ports (see get_protocol_factor)
+    pub fn protocol_factor(&self) -> u64 {
+        *self.protocol_factor.get_or_init(|| {
+            let (src_protocols_opt, dst_protocols_opt) = self.optimized_protocols();
+            get_protocol_factor(src_protocols_opt, dst_protocols_opt)
+        })
+    }
+
+    fn optimized_protocols(&self) -> &OptimizedProtocols {
+        self.optimized_protocols.get_or_init(|| {
+            (
+                self.src_protocols.as_ref().map(|p| p.optimize()),
+                self.dst_protocols.as_ref().map(|p| p.optimize()),
+            )
+        })
+    }
+
+    /// Step by step derivation of protocol_factor, see get_protocol_factor
+    pub fn explain_protocol_factor(&self) -> explanation::ProtocolFactorExplanation {
+        let (src_protocols_opt, dst_protocols_opt) = self.optimized_protocols();
+        let (src_ports, dst_ports) = (
+            restricting(src_protocols_opt),
+            restricting(dst_protocols_opt),
+        );
+
+        let src_protocols = src_ports.map_or(HashMap::new(), |p| protocol_freq_distribution(p));
+        let dst_protocols = dst_ports.map_or(HashMap::new(), |p| protocol_freq_distribution(p));
+
+        let keys = src_protocols
+            .keys()
+            .chain(dst_protocols.keys())
+            .collect::<BTreeSet<_>>();
+
+        explanation::ProtocolFactorExplanation {
+            terms: keys
+                .into_iter()
+                .map(|key| explanation::FactorTerm {
+                    protocol: protocol_key_label(key),
+                    src_count: src_protocols.get(key).copied(),
+                    dst_count: dst_protocols.get(key).copied(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Simplified capacity: networks multiplied by the protocol factor.
+    /// A protocol present on one side only is counted as "any port" on the other side (see ace_count).
+    /// Missing networks mean "any" (see ANY_NETWORK_CAPACITY), so a ports-only (service-only) rule
+    /// has capacity equal to the protocol factor.
     pub fn capacity(&self) -> u64 {
-        let src_protocols_opt = self.src_protocols.as_ref().map(|p| p.optimize());
-        let dst_protocols_opt = self.dst_protocols.as_ref().map(|p| p.optimize());
-        let protocol_factor = get_protocol_factor(&src_protocols_opt, &dst_protocols_opt);
+        let protocol_factor = self.protocol_factor();
 
-        let src_networks_capacity = self.src_networks.as_ref().map_or(1, |n| n.capacity());
-        let dst_networks_capacity = self.dst_networks.as_ref().map_or(1, |n| n.capacity());
+        let src_networks_capacity = self
+            .src_networks
+            .as_ref()
+            .map_or(ANY_NETWORK_CAPACITY, |n| n.capacity());
+        let dst_networks_capacity = self
+            .dst_networks
+            .as_ref()
+            .map_or(ANY_NETWORK_CAPACITY, |n| n.capacity());
 
         src_networks_capacity * dst_networks_capacity * protocol_factor
     }
 
+    /// Number of ACEs FTD expands the rule into: networks × per-protocol port entries.
+    /// Differs from capacity() when both source and destination ports are set:
+    /// FTD can't combine ports of different protocols in one ACE, so a protocol
+    /// present on one side only (for example L3-only IGMP in destination ports
+    /// with TCP source ports) doesn't produce any ACE.
+    pub fn ace_count(&self) -> u64 {
+        let (src_protocols_opt, dst_protocols_opt) = self.optimized_protocols();
+        let ace_protocol_factor = get_ace_protocol_factor(src_protocols_opt, dst_protocols_opt);
+
+        self.networks_capacity() * ace_protocol_factor
+    }
+
+    /// ace_count split by protocol number, together with ace_count_any_protocol
+    /// the counts sum up to ace_count
+    pub fn ace_count_by_protocol(&self) -> HashMap<u8, u64> {
+        self.ace_counts()
+            .into_iter()
+            .filter_map(|(protocol, count)| Some((protocol.number()?, count)))
+            .collect()
+    }
+
+    /// ACEs matching any protocol: the rule has no ports or has "protocol ip"
+    pub fn ace_count_any_protocol(&self) -> u64 {
+        self.ace_counts()
+            .get(&Protocol::Any)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn ace_counts(&self) -> HashMap<Protocol, u64> {
+        let (src_protocols_opt, dst_protocols_opt) = self.optimized_protocols();
+        let networks_capacity = self.networks_capacity();
+
+        get_ace_protocol_factors(src_protocols_opt, dst_protocols_opt)
+            .into_iter()
+            .map(|(protocol, factor)| (protocol, networks_capacity * factor))
+            .collect()
+    }
+
+    /// Source networks multiplied by destination networks, before optimization
+    fn networks_capacity(&self) -> u64 {
+        let src_networks_capacity = self
+            .src_networks
+            .as_ref()
+            .map_or(ANY_NETWORK_CAPACITY, |n| n.capacity());
+        let dst_networks_capacity = self
+            .dst_networks
+            .as_ref()
+            .map_or(ANY_NETWORK_CAPACITY, |n| n.capacity());
+
+        src_networks_capacity * dst_networks_capacity
+    }
+
     pub fn optimized_capacity(&self) -> u64 {
-        let src_protocols_opt = self.src_protocols.as_ref().map(|p| p.optimize());
-        let dst_protocols_opt = self.dst_protocols.as_ref().map(|p| p.optimize());
-        let protocol_factor = get_protocol_factor(&src_protocols_opt, &dst_protocols_opt);
+        let protocol_factor = self.protocol_factor();
 
         let (src_networks_opt, dst_networks_opt) = self.get_optimized_networks();
 
-        let src_networks_capacity = src_networks_opt.as_ref().map_or(1, |n| n.capacity());
-        let dst_networks_capacity = dst_networks_opt.as_ref().map_or(1, |n| n.capacity());
+        let src_networks_capacity = src_networks_opt
+            .as_ref()
+            .map_or(ANY_NETWORK_CAPACITY, |n| n.capacity());
+        let dst_networks_capacity = dst_networks_opt
+            .as_ref()
+            .map_or(ANY_NETWORK_CAPACITY, |n| n.capacity());
 
         src_networks_capacity * dst_networks_capacity * protocol_factor
     }
 
+    /// Optimized source and destination ports rendered one item per line
+    pub fn get_optimized_ports_text(&self) -> (Option<String>, Option<String>) {
+        let (src_protocols_opt, dst_protocols_opt) = self.optimized_protocols();
+        (
+            src_protocols_opt
+                .as_ref()
+                .map(|p| protocol_list_optimized::to_text(p)),
+            dst_protocols_opt
+                .as_ref()
+                .map(|p| protocol_list_optimized::to_text(p)),
+        )
+    }
+
+    /// Same as get_optimized_ports_text with the opt-in optimizations of the options,
+    /// the capacity is not affected
+    pub fn get_optimized_ports_text_with(
+        &self,
+        options: OptimizeOptions,
+    ) -> (Option<String>, Option<String>) {
+        let text = |ports: &Option<ProtocolObject>| {
+            ports
+                .as_ref()
+                .map(|p| protocol_list_optimized::to_text(&p.optimize_with(options)))
+        };
+        (text(&self.src_protocols), text(&self.dst_protocols))
+    }
+
+    /// Step by step optimization of every present networks and ports section
+    pub fn explain(&self) -> Vec<SectionExplanation> {
+        let networks = [&self.src_networks, &self.dst_networks]
+            .into_iter()
+            .flatten()
+            .map(|n| n.explain());
+        let protocols = [&self.src_protocols, &self.dst_protocols]
+            .into_iter()
+            .flatten()
+            .map(|p| p.explain());
+
+        networks.chain(protocols).collect()
+    }
+
+    /// Action and matchers (networks, ports, applications) are the same as in the other rule.
+    /// Matchers are compared after the optimization, so object names and the order of entries
+    /// don't matter: 10.0.0.0/24 + 10.0.1.0/24 is the same as 10.0.0.0/23
+    pub fn same_matchers(&self, other: &Rule) -> bool {
+        self.canonical() == other.canonical()
+    }
+
+    /// Action, time range and optimized, sorted matchers of the rule. Logically identical
+    /// rules have equal canonical forms, whatever the object names and the order of entries
+    pub fn canonical(&self) -> CanonicalRule {
+        let (src_networks_opt, dst_networks_opt) = self.get_optimized_networks();
+        let (src_protocols_opt, dst_protocols_opt) = self.optimized_protocols();
+
+        let mut applications = self.applications.clone();
+        applications.sort();
+
+        CanonicalRule {
+            action: self.action.clone(),
+            time_range: self.time_range.clone(),
+            src_networks: src_networks_opt.map(|n| sorted(n.cidrs())),
+            dst_networks: dst_networks_opt.map(|n| sorted(n.cidrs())),
+            src_protocols: src_protocols_opt.as_ref().map(|p| protocol_matchers(p)),
+            dst_protocols: dst_protocols_opt.as_ref().map(|p| protocol_matchers(p)),
+            applications,
+        }
+    }
+
+    /// Reports the matchers allowing any traffic: a missing networks section or 0.0.0.0/0
+    /// matches any address, missing ports or "protocol ip" on both sides match any protocol
+    pub fn is_overly_broad(&self) -> BroadnessReport {
+        let (src_protocols_opt, dst_protocols_opt) = self.optimized_protocols();
+
+        BroadnessReport {
+            any_source: self.src_networks.as_ref().is_none_or(|n| n.is_any()),
+            any_destination: self.dst_networks.as_ref().is_none_or(|n| n.is_any()),
+            any_protocol: restricting(src_protocols_opt).is_none()
+                && restricting(dst_protocols_opt).is_none(),
+        }
+    }
+
+    /// Protocol numbers of source and destination ports, empty if the rule has no ports.
+    /// "protocol ip" entries have no number, see is_overly_broad
+    pub fn protocols_used(&self) -> BTreeSet<u8> {
+        let (src_protocols_opt, dst_protocols_opt) = self.optimized_protocols();
+
+        [src_protocols_opt, dst_protocols_opt]
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|p| p.get_protocol().number())
+            .collect()
+    }
+
+    /// Overlapping entries of source and destination networks (see NetworkObject::overlaps)
+    pub fn overlaps(&self) -> Vec<explanation::Overlap> {
+        [&self.src_networks, &self.dst_networks]
+            .into_iter()
+            .flatten()
+            .flat_map(|n| n.overlaps())
+            .collect()
+    }
+
+    /// Prefixes, ranges and hosts of source and destination networks, duplicates are kept
+    pub fn prefix_list_items(&self) -> Vec<&network_object::PrefixListItem> {
+        [&self.src_networks, &self.dst_networks]
+            .into_iter()
+            .flatten()
+            .flat_map(|n| n.prefix_list_items())
+            .collect()
+    }
+
+    /// Name and number of addresses of the widest object or group of source and destination
+    /// networks, e.g. "OBJ-10.0.0.0_8" for "OBJ-10.0.0.0_8 (10.0.0.0/8)" (see
+    /// NetworkObject::object_address_spans), the first one wins a tie.
+    /// None if the rule has no networks
+    pub fn largest_network_object(&self) -> Option<(&str, u64)> {
+        [&self.src_networks, &self.dst_networks]
+            .into_iter()
+            .flatten()
+            .flat_map(|n| n.object_address_spans())
+            .rev()
+            .max_by_key(|(_, addresses)| *addresses)
+    }
+
     pub fn get_optimized_networks(
         &self,
     ) -> (
@@ -201,37 +659,150 @@ impl Rule {
 /// For example:  
 /// src_protocols = [TCP, UDP, TCP] -> (TCP, 2 times), (UDP, 1 time)  
 /// dst_protocols = [TCP, UDP, UDP] -> (TCP, 1 time),  (UDP, 2 times)  
-/// protocol_factor =  TCP (2 * 1) + UDP (1 * 2) = 2 + 2 = 4
+/// protocol_factor =  TCP (2 * 1) + UDP (1 * 2) = 2 + 2 = 4  
+/// ICMP entries are keyed by type and code, every unique pair is a separate L3 entry:  
+/// src_protocols = [ICMP type 3], dst_protocols = [ICMP type 8] -> 1 + 1 = 2  
+/// A protocol present on one side only is multiplied by 1, as if the other side matched any port
+/// of it: src_protocols = [TCP, TCP, UDP], dst_protocols = [TCP] -> TCP (2 * 1) + UDP (1 * 1) = 3.  
+/// FTD drops such a protocol instead (see get_ace_protocol_factor), so the factor is an upper bound
+/// that doesn't depend on how the entries are split between the sides.
 fn get_protocol_factor(
     src_ports: &Option<Vec<ProtocolListOptimized>>,
     dst_ports: &Option<Vec<ProtocolListOptimized>>,
 ) -> u64 {
-    let src_protocols = src_ports
-        .as_ref()
-        .map_or(HashMap::new(), |p| protocol_freq_distribution(p));
-    let dst_protocols = dst_ports
-        .as_ref()
-        .map_or(HashMap::new(), |p| protocol_freq_distribution(p));
+    let (src_ports, dst_ports) = (restricting(src_ports), restricting(dst_ports));
+
+    let src_protocols = src_ports.map_or(HashMap::new(), |p| protocol_freq_distribution(p));
+    let dst_protocols = dst_ports.map_or(HashMap::new(), |p| protocol_freq_distribution(p));
 
     if src_protocols.is_empty() && dst_protocols.is_empty() {
         return 1;
     }
 
-    let (longest, shortest) = if src_protocols.len() > dst_protocols.len() {
-        (src_protocols, dst_protocols)
-    } else {
-        (dst_protocols, src_protocols)
-    };
+    let keys = src_protocols
+        .keys()
+        .chain(dst_protocols.keys())
+        .collect::<HashSet<_>>();
 
-    longest.iter().fold(0, |acc, (protocol, count1)| {
-        let count2 = shortest.get(protocol).unwrap_or(&1);
+    keys.into_iter().fold(0, |acc, key| {
+        let count1 = src_protocols.get(key).unwrap_or(&1);
+        let count2 = dst_protocols.get(key).unwrap_or(&1);
         acc + (*count1 * *count2)
     })
 }
 
-fn protocol_freq_distribution(l3_l4_proto: &[ProtocolListOptimized]) -> HashMap<u8, u64> {
+/// Port entries per protocol as FTD expands them into ACEs  
+/// If one side is missing it matches any port, so every entry of the other side is an ACE.  
+/// If both sides are set, only protocols present on both sides produce ACEs:  
+/// src_protocols = [TCP, TCP], dst_protocols = [TCP, IGMP] -> TCP (2 * 1) = 2
+fn get_ace_protocol_factor(
+    src_ports: &Option<Vec<ProtocolListOptimized>>,
+    dst_ports: &Option<Vec<ProtocolListOptimized>>,
+) -> u64 {
+    get_ace_protocol_factors(src_ports, dst_ports)
+        .values()
+        .sum()
+}
+
+/// get_ace_protocol_factor per protocol, ACEs matching any protocol are keyed by Protocol::Any
+fn get_ace_protocol_factors(
+    src_ports: &Option<Vec<ProtocolListOptimized>>,
+    dst_ports: &Option<Vec<ProtocolListOptimized>>,
+) -> HashMap<Protocol, u64> {
+    let (src_ports, dst_ports) = (restricting(src_ports), restricting(dst_ports));
+
+    let (src_protocols, dst_protocols) = match (src_ports, dst_ports) {
+        (Some(src), Some(dst)) => (
+            protocol_freq_distribution(src),
+            protocol_freq_distribution(dst),
+        ),
+        (Some(ports), None) | (None, Some(ports)) if !ports.is_empty() => {
+            return ports.iter().fold(HashMap::new(), |mut acc, p| {
+                *acc.entry(p.get_protocol()).or_insert(0) += 1;
+                acc
+            });
+        }
+        _ => return HashMap::from([(Protocol::Any, 1)]),
+    };
+
+    src_protocols
+        .iter()
+        .filter_map(|(key, count1)| {
+            dst_protocols
+                .get(key)
+                .map(|count2| (key.0, count1 * count2))
+        })
+        .fold(HashMap::new(), |mut acc, (protocol, count)| {
+            *acc.entry(protocol).or_insert(0) += count;
+            acc
+        })
+}
+
+/// Canonical form of everything the rule matches on, see Rule::canonical
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CanonicalRule {
+    action: Option<String>,
+    time_range: Option<String>,
+    src_networks: Option<Vec<String>>,
+    dst_networks: Option<Vec<String>>,
+    src_protocols: Option<Vec<ProtocolMatcher>>,
+    dst_protocols: Option<Vec<ProtocolMatcher>>,
+    applications: Vec<String>,
+}
+
+/// Protocol number, ICMP type and code, port range of an optimized port entry
+type ProtocolMatcher = (Protocol, Option<u8>, Option<u8>, (u16, u16));
+
+fn protocol_matchers(protocols: &[ProtocolListOptimized]) -> Vec<ProtocolMatcher> {
+    let matchers = protocols
+        .iter()
+        .map(|p| {
+            (
+                p.get_protocol(),
+                p.get_icmp_type(),
+                p.get_icmp_code(),
+                p.get_ports(),
+            )
+        })
+        .collect();
+
+    sorted(matchers)
+}
+
+fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+    items.sort();
+    items
+}
+
+/// Ports of a side that restrict the traffic, None for a missing side
+/// and for "protocol ip" (it matches every protocol, same as a missing side)
+fn restricting(ports: &Option<Vec<ProtocolListOptimized>>) -> Option<&Vec<ProtocolListOptimized>> {
+    ports
+        .as_ref()
+        .filter(|ports| !ports.iter().any(|p| p.is_all_protocols()))
+}
+
+/// Capacity of a missing network section: FTD matches "any" with a single entry
+const ANY_NETWORK_CAPACITY: u64 = 1;
+
+/// Key of the frequency distribution: protocol number, ICMP type and ICMP code (None for non-ICMP)
+type ProtocolKey = (Protocol, Option<u8>, Option<u8>);
+
+/// "protocol 6", "protocol 1 type 8 code 0"
+fn protocol_key_label((protocol, icmp_type, icmp_code): &ProtocolKey) -> String {
+    let mut label = format!("protocol {}", protocol);
+    if let Some(icmp_type) = icmp_type {
+        label.push_str(&format!(" type {}", icmp_type));
+    }
+    if let Some(icmp_code) = icmp_code {
+        label.push_str(&format!(" code {}", icmp_code));
+    }
+    label
+}
+
+fn protocol_freq_distribution(l3_l4_proto: &[ProtocolListOptimized]) -> HashMap<ProtocolKey, u64> {
     let protocol_freq = l3_l4_proto.iter().fold(HashMap::new(), |mut acc, p| {
-        let protocol = p.get_protocol();
+        let protocol = (p.get_protocol(), p.get_icmp_type(), p.get_icmp_code());
         let count = acc.entry(protocol).or_insert(0);
         *count += 1;
         acc
@@ -240,6 +811,109 @@ fn protocol_freq_distribution(l3_l4_proto: &[ProtocolListOptimized]) -> HashMap<
     protocol_freq
 }
 
+/// Titles of the rule sections, any of them terminates the section being captured.
+/// The "Logging" block ends the matching sections, so it is cut off before (see without_logging)
+const SECTION_MARKERS: [&str; 15] = [
+    "Description",
+    "Time Range",
+    "Source Networks",
+    "Destination Networks",
+    "VLAN Tags",
+    "Source Ports",
+    "Destination Ports",
+    "Source Security Group Tag",
+    "Destination Security Group Tag",
+    "Source SGT",
+    "Destination SGT",
+    "Applications",
+    "Users",
+    "URLs",
+    "Safe Search",
+];
+
+/// Abbreviated section titles of some FMC versions and the titles they stand for
+const SECTION_ALIASES: [(&str, &str); 3] = [
+    ("Src Ports", "Source Ports"),
+    ("Dst Ports", "Destination Ports"),
+    ("Dest Ports", "Destination Ports"),
+];
+
+/// Replaces abbreviated section titles with the full ones, so sections are found by SECTION_MARKERS
+// Example:
+//     Dst Ports             : HTTPS (protocol 6, port 443)
+// becomes
+//     Destination Ports     : HTTPS (protocol 6, port 443)
+fn with_full_titles(lines: Vec<String>) -> Vec<String> {
+    lines
+        .into_iter()
+        .map(|line| {
+            let Some((title, rest)) = line.split_once(':') else {
+                return line;
+            };
+
+            match SECTION_ALIASES
+                .iter()
+                .find(|(alias, _)| title.trim() == *alias)
+            {
+                Some((alias, full_title)) => {
+                    format!("{}:{}", title.replacen(alias, full_title, 1), rest)
+                }
+                None => line,
+            }
+        })
+        .collect()
+}
+
+/// End markers for a section are all other section titles
+fn end_markers(section: &str) -> Vec<&'static str> {
+    SECTION_MARKERS
+        .into_iter()
+        .filter(|&marker| marker != section)
+        .collect()
+}
+
+/// Lines of the rule before the "Logging" block. Matching sections precede it,
+/// and its sub-lines must not be taken for a section, whatever they contain
+fn without_logging(lines: &[String]) -> &[String] {
+    let end = lines
+        .iter()
+        .position(|line| is_logging_header(line))
+        .unwrap_or(lines.len());
+
+    &lines[..end]
+}
+
+// Example:
+//     Logging Configuration
+//     Logging Configuration : Enabled
+//     Logging               : Enabled
+fn is_logging_header(line: &str) -> bool {
+    let line = line.trim();
+    let title = line.split(':').next().unwrap_or_default().trim();
+
+    line.starts_with("Logging Configuration") || title == "Logging"
+}
+
+/// Removes indentation shared by all non-empty lines, so a rule block indented
+/// as a whole (banner included) is parsed the same way as a non-indented one
+fn strip_common_indentation(lines: Vec<String>) -> Vec<String> {
+    let common = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    if common == 0 {
+        return lines;
+    }
+
+    lines
+        .into_iter()
+        .map(|line| line.get(common..).unwrap_or_default().to_string())
+        .collect()
+}
+
 fn get_name(lines: &[String]) -> Result<String, RuleError> {
     let line = lines
         .iter()
@@ -255,17 +929,75 @@ fn get_name(lines: &[String]) -> Result<String, RuleError> {
     Ok(name.to_string())
 }
 
+// Example:
+//     Action                : Allow
+fn get_action(lines: &[String]) -> Option<String> {
+    lines
+        .iter()
+        .map(|line| line.trim())
+        .find(|line| line.starts_with("Action"))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, action)| action.trim().to_string())
+        .filter(|action| !action.is_empty())
+}
+
+// Example:
+//     Description           : Access to the web servers
+//                             from the branch offices
+fn get_description(lines: &[String]) -> Option<String> {
+    let description = section_values(lines).join(" ");
+    (!description.is_empty()).then_some(description)
+}
+
+// Example:
+//     Time Range            : Business-Hours
+fn get_time_range(lines: &[String]) -> Option<String> {
+    let time_range = section_values(lines).join(" ");
+    (!time_range.is_empty()).then_some(time_range)
+}
+
+// Example:
+//     Applications          : Facebook
+//                             YouTube
+fn get_applications(lines: &[String]) -> Vec<String> {
+    section_values(lines)
+}
+
+/// Non-empty values of a section: text after ':' on the title line, then every following line
+fn section_values(lines: &[String]) -> Vec<String> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| match idx {
+            0 => line.split_once(':').map_or("", |(_, app)| app),
+            _ => line.as_str(),
+        })
+        .map(|app| app.trim().to_string())
+        .filter(|app| !app.is_empty())
+        .collect()
+}
+
+/// Lines of the section titled `start` till the next section titled by any of `end`.
+/// Titles are compared as a whole, so an object name containing a title doesn't start or end
+/// a section, e.g. "Applications-DC (10.1.0.0/16)" or "Time Range-Hosts (10.2.0.0/16)"
 fn lines_from_till(lines: &[String], start: &str, end: &[&str]) -> Result<Vec<String>, RuleError> {
     let lines: Vec<_> = lines
         .iter()
-        .skip_while(|line| !line.contains(start))
-        .take_while(|line| !end.iter().any(|&e| line.contains(e)))
+        .skip_while(|line| section_title(line) != Some(start))
+        .take_while(|line| !section_title(line).is_some_and(|title| end.contains(&title)))
         .map(|line| line.to_string())
         .collect();
 
     Ok(lines)
 }
 
+// Example:
+//     Destination Networks  : Applications-DC (10.1.0.0/16)
+// returns "Destination Networks"
+fn section_title(line: &str) -> Option<&str> {
+    line.split_once(':').map(|(title, _)| title.trim())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -385,6 +1117,237 @@ mod tests {
         assert!(name.is_err());
     }
 
+    #[test]
+    fn test_get_action() {
+        let lines = vec![
+            "----------[ Rule: Custom_rule3 | FM-15045 ]-----------".to_string(),
+            "    Action                : Fast-path".to_string(),
+            "    Source Networks       : 10.0.0.0/8".to_string(),
+        ];
+        assert_eq!(get_action(&lines), Some("Fast-path".to_string()));
+    }
+
+    #[test]
+    fn test_get_action_missing() {
+        let lines = vec![
+            "----------[ Rule: Custom_rule3 | FM-15045 ]-----------".to_string(),
+            "    Source Networks       : 10.0.0.0/8".to_string(),
+        ];
+        assert_eq!(get_action(&lines), None);
+    }
+
+    #[test]
+    fn test_parse_rule_with_applications() {
+        let rule = "----------[ Rule: Custom_rule3 | FM-15045 ]-----------
+    Action                : Allow
+    Source Networks       : 10.0.0.0/8
+    Applications          : Facebook
+                            YouTube
+    Destination Ports     : HTTPS (protocol 6, port 443)
+    Logging Configuration";
+        let lines: Vec<String> = rule.lines().map(|s| s.to_string()).collect();
+        let rule = Rule::try_from(lines).unwrap();
+
+        assert_eq!(rule.get_applications(), ["Facebook", "YouTube"]);
+        assert!(rule.is_application_filtered());
+        assert_eq!(rule.capacity(), 1);
+    }
+
+    #[test]
+    fn test_parse_rule_with_time_range() {
+        let rule = "----------[ Rule: Business_hours ]-----------
+    Action                : Allow
+    Time Range            : Business-Hours
+    Source Networks       : 10.0.0.0/8
+    Destination Ports     : HTTPS (protocol 6, port 443)
+    Logging Configuration";
+        let lines: Vec<String> = rule.lines().map(|s| s.to_string()).collect();
+        let rule = Rule::try_from(lines).unwrap();
+
+        assert_eq!(rule.get_time_range(), Some("Business-Hours"));
+        assert_eq!(rule.get_action(), Some("Allow"));
+        assert_eq!(rule.prefix_list_items().len(), 1);
+        assert_eq!(rule.capacity(), 1);
+
+        let rule = rule_from(
+            "----------[ Rule: Always ]-----------
+    Source Networks       : 10.0.0.0/8",
+        );
+        assert_eq!(rule.get_time_range(), None);
+    }
+
+    #[test]
+    fn test_parse_rule_without_applications() {
+        let lines = vec![
+            "----------[ Rule: Custom_rule3 | FM-15045 ]-----------".to_string(),
+            "    Source Networks       : 10.0.0.0/8".to_string(),
+        ];
+        let rule = Rule::try_from(lines).unwrap();
+
+        assert!(rule.get_applications().is_empty());
+        assert!(!rule.is_application_filtered());
+    }
+
+    #[test]
+    fn test_logging_block_not_captured() {
+        let rule = rule_from(
+            "----------[ Rule: Web ]-----------
+    Description           : Logging of web access
+    Destination Networks  : 10.0.0.0/24
+    Logging Configuration
+    DC                    : Enabled
+      Source Networks     : 192.168.0.0/16
+      Destination Ports   : HTTPS (protocol 6, port 443)
+    Safe Search           : No",
+        );
+
+        assert_eq!(rule.get_description(), Some("Logging of web access"));
+        assert!(rule.src_networks.is_none());
+        assert!(rule.dst_protocols.is_none());
+        assert_eq!(rule.prefix_list_items().len(), 1);
+        assert_eq!(rule.capacity(), 1);
+    }
+
+    #[test]
+    fn test_without_logging() {
+        let lines: Vec<String> = [
+            "Source Networks       : 10.0.0.0/8",
+            "Logging               : Enabled",
+            "Destination Networks  : 10.0.0.0/8",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+        assert_eq!(without_logging(&lines), &lines[..1]);
+        assert!(is_logging_header("    Logging Configuration : Enabled"));
+        assert!(!is_logging_header("    Logging of web access"));
+    }
+
+    #[test]
+    fn test_abbreviated_port_titles() {
+        let rule = rule_from(
+            "----------[ Rule: Web ]-----------
+    Source Networks       : 10.0.0.0/24
+    Src Ports             : ephemeral (protocol 6, port 1024-65535)
+    Dst Ports             : HTTPS (protocol 6, port 443)
+                            HTTP (protocol 6, port 80)
+    Applications          : HTTP",
+        );
+
+        assert!(rule.src_protocols.is_some());
+        assert_eq!(
+            rule.get_optimized_ports_text().1.as_deref(),
+            Some("HTTP (protocol 6, port 80)\nHTTPS (protocol 6, port 443)")
+        );
+
+        let rule = rule_from(
+            "----------[ Rule: Dns ]-----------
+    Dest Ports            : DNS (protocol 17, port 53)",
+        );
+        assert_eq!(rule.protocols_used(), BTreeSet::from([17]));
+    }
+
+    #[test]
+    fn test_optimized_ports_text_with_merge_tcp_udp() {
+        let rule = rule_from(
+            "----------[ Rule: Web ]-----------
+    Destination Ports     : WEB (protocol 6, port 80-82)
+                            WEB-UDP (protocol 17, port 80-82)",
+        );
+        let merge = OptimizeOptions {
+            merge_tcp_udp: true,
+        };
+
+        assert_eq!(
+            rule.get_optimized_ports_text_with(merge),
+            (
+                None,
+                Some("WEB AND WEB-UDP (protocol any, port 80-82)".to_string())
+            )
+        );
+        assert_eq!(
+            rule.get_optimized_ports_text_with(OptimizeOptions::default()),
+            rule.get_optimized_ports_text()
+        );
+        assert_eq!(rule.capacity(), 2);
+    }
+
+    #[test]
+    fn test_object_name_with_section_title() {
+        let rule = rule_from(
+            "----------[ Rule: Dc ]-----------
+    Action                : Allow
+    Destination Networks  : Applications-DC (10.1.0.0/16)
+                            10.2.0.0/16
+    Applications          : HTTP",
+        );
+
+        assert!(rule.dst_networks.is_some());
+        assert_eq!(rule.capacity(), 2);
+        assert_eq!(rule.get_applications(), ["HTTP"]);
+    }
+
+    #[test]
+    fn test_object_name_with_description_title() {
+        let rule = rule_from(
+            "----------[ Rule: Docs ]-----------
+    Description           : Documentation servers
+    Source Networks       : Description-Servers (10.1.0.0/16)
+                            10.2.0.0/16
+    Destination Ports     : Description-Sync (protocol 6, port 873)
+                            HTTPS (protocol 6, port 443)",
+        );
+
+        assert_eq!(rule.get_description(), Some("Documentation servers"));
+        assert_eq!(rule.capacity(), 2 * 2);
+    }
+
+    #[test]
+    fn test_object_name_with_time_range_title() {
+        let rule = rule_from(
+            "----------[ Rule: Shifts ]-----------
+    Time Range            : Business-Hours
+    Source Networks       : Time Range-Hosts (10.1.0.0/16)
+                            10.2.0.0/16",
+        );
+
+        assert_eq!(rule.get_time_range(), Some("Business-Hours"));
+        assert_eq!(rule.capacity(), 2);
+    }
+
+    #[test]
+    fn test_ports_before_networks() {
+        let canonical = rule_from(
+            "----------[ Rule: Web ]-----------
+    Action                : Allow
+    Source Networks       : 10.0.0.0/24
+                            10.0.2.0/24
+    Destination Networks  : 192.168.0.0/24
+    Source Ports          : ephemeral (protocol 6, port 1024-65535)
+    Destination Ports     : HTTPS (protocol 6, port 443)
+                            DNS (protocol 17, port 53)",
+        );
+        let reordered = rule_from(
+            "----------[ Rule: Web ]-----------
+    Action                : Allow
+    Destination Ports     : HTTPS (protocol 6, port 443)
+                            DNS (protocol 17, port 53)
+    Source Ports          : ephemeral (protocol 6, port 1024-65535)
+    Source Networks       : 10.0.0.0/24
+                            10.0.2.0/24
+    Destination Networks  : 192.168.0.0/24",
+        );
+
+        assert_eq!(canonical.capacity(), 4);
+        assert_eq!(reordered.capacity(), canonical.capacity());
+        assert_eq!(
+            reordered.optimized_capacity(),
+            canonical.optimized_capacity()
+        );
+        assert_eq!(reordered, canonical);
+    }
+
     #[test]
     fn test_lines_from_till_with_no_start_marker() {
         let lines = vec![
@@ -398,45 +1361,123 @@ mod tests {
 
     #[test]
     fn test_rule_capacity_with_all_components() {
-        let source_networks = Some(
-            NetworkObject::try_from(&vec![
-                "Source Networks       : Internal (group)".to_string(),
-                "OBJ-192.168.0.0 (192.168.0.0/16)".to_string(),
-                "OBJ-172.17.0.0 (172.17.0.0/16)".to_string(),
-            ])
-            .unwrap(),
-        );
-        let destination_networks = Some(
-            NetworkObject::try_from(&vec![
-                "Destination Networks       : OBJ-10.138.0.0_16 (10.138.0.0/16)".to_string(),
-                "10.0.0.0/8".to_string(),
-            ])
-            .unwrap(),
-        );
-        let source_ports = Some(
-            ProtocolObject::try_from(&vec![
-                "Source Ports       : ephemeral (protocol 6, port 1024)".to_string(),
-            ])
-            .unwrap(),
-        );
-        let destination_ports = Some(
-            ProtocolObject::try_from(&vec![
-                "Destination Ports: HTTPS (protocol 6, port 443)".to_string()
-            ])
-            .unwrap(),
-        );
-
-        let rule = Rule {
-            name: "Custom_rule2".to_string(),
-            src_networks: source_networks,
-            dst_networks: destination_networks,
-            src_protocols: source_ports,
-            dst_protocols: destination_ports,
-        };
+        let source_networks = NetworkObject::try_from(&vec![
+            "Source Networks       : Internal (group)".to_string(),
+            "OBJ-192.168.0.0 (192.168.0.0/16)".to_string(),
+            "OBJ-172.17.0.0 (172.17.0.0/16)".to_string(),
+        ])
+        .unwrap();
+        let destination_networks = NetworkObject::try_from(&vec![
+            "Destination Networks       : OBJ-10.138.0.0_16 (10.138.0.0/16)".to_string(),
+            "10.0.0.0/8".to_string(),
+        ])
+        .unwrap();
+        let source_ports = ProtocolObject::try_from(&vec![
+            "Source Ports       : ephemeral (protocol 6, port 1024)".to_string(),
+        ])
+        .unwrap();
+        let destination_ports = ProtocolObject::try_from(&vec![
+            "Destination Ports: HTTPS (protocol 6, port 443)".to_string(),
+        ])
+        .unwrap();
+
+        let rule = Builder::new("Custom_rule2".to_string())
+            .with_src_networks(source_networks)
+            .with_dst_networks(destination_networks)
+            .with_src_protocols(source_ports)
+            .with_dst_protocols(destination_ports)
+            .build();
 
         assert_eq!(rule.capacity(), 2 * 2);
     }
 
+    #[test]
+    fn test_protocols_used() {
+        let source_ports = ProtocolObject::try_from(&vec![
+            "Source Ports       : ephemeral (protocol 6, port 1024)".to_string(),
+        ])
+        .unwrap();
+        let destination_ports = ProtocolObject::try_from(&vec![
+            "Destination Ports: HTTPS (protocol 6, port 443)".to_string(),
+            "DNS (protocol 17, port 53)".to_string(),
+        ])
+        .unwrap();
+
+        let rule = Builder::new("TCP_and_UDP".to_string())
+            .with_src_protocols(source_ports)
+            .with_dst_protocols(destination_ports)
+            .build();
+
+        assert_eq!(rule.protocols_used(), BTreeSet::from([6, 17]));
+        assert!(Builder::new("No_ports".to_string())
+            .build()
+            .protocols_used()
+            .is_empty());
+
+        let any_protocol =
+            ProtocolObject::try_from(&vec!["Destination Ports: ANY (protocol ip)".to_string()])
+                .unwrap();
+        assert!(Builder::new("Any_protocol".to_string())
+            .with_dst_protocols(any_protocol)
+            .build()
+            .protocols_used()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_largest_network_object() {
+        let source_networks = NetworkObject::try_from(&vec![
+            "Source Networks       : 10.1.1.0/24".to_string(),
+            "OBJ-10.0.0.0_8 (10.0.0.0/8)".to_string(),
+            "10.2.2.0/24".to_string(),
+        ])
+        .unwrap();
+        let destination_networks = NetworkObject::try_from(&vec![
+            "Destination Networks  : 192.168.1.0/24".to_string(),
+            "192.168.2.0/24".to_string(),
+        ])
+        .unwrap();
+
+        let rule = Builder::new("Wide".to_string())
+            .with_src_networks(source_networks)
+            .with_dst_networks(destination_networks)
+            .build();
+
+        assert_eq!(
+            rule.largest_network_object(),
+            Some(("OBJ-10.0.0.0_8", 1 << 24))
+        );
+
+        // a group counts as one object, its overlapping entries once
+        let grouped = Builder::new("Grouped".to_string())
+            .with_src_networks(
+                NetworkObject::try_from(&vec![
+                    "Source Networks       : OBJ-10.0.0.0_8 (10.0.0.0/8)".to_string(),
+                ])
+                .unwrap(),
+            )
+            .with_dst_networks(
+                NetworkObject::try_from(&vec![
+                    "Destination Networks  : Internal (group)".to_string(),
+                    "  10.0.0.0/8".to_string(),
+                    "  10.1.0.0/16".to_string(),
+                    "  192.168.0.0/16".to_string(),
+                ])
+                .unwrap(),
+            )
+            .build();
+        assert_eq!(
+            grouped.largest_network_object(),
+            Some(("Internal", (1 << 24) + (1 << 16)))
+        );
+        assert_eq!(
+            Builder::new("Any".to_string())
+                .build()
+                .largest_network_object(),
+            None
+        );
+    }
+
     #[test]
     fn test_rule_capacity_without_ports() {
         let source_networks = NetworkObject::try_from(&vec![
@@ -451,17 +1492,33 @@ mod tests {
         ])
         .unwrap();
 
-        let rule = Rule {
-            name: "Custom_rule2".to_string(),
-            src_networks: Some(source_networks),
-            dst_networks: Some(destination_networks),
-            src_protocols: None,
-            dst_protocols: None,
-        };
+        let rule = Builder::new("Custom_rule2".to_string())
+            .with_src_networks(source_networks)
+            .with_dst_networks(destination_networks)
+            .build();
 
         assert_eq!(rule.capacity(), 2 * 2);
     }
 
+    #[test]
+    fn test_rule_capacity_ports_only() {
+        let lines = vec![
+            "----------[ Rule: Web | FM-1 ]-----------".to_string(),
+            "    Action                : Allow".to_string(),
+            "    Destination Ports     : HTTP (protocol 6, port 80)".to_string(),
+            "      HTTPS (protocol 6, port 443)".to_string(),
+            "      DNS (protocol 17, port 53)".to_string(),
+            "    Logging Configuration".to_string(),
+        ];
+        let rule = Rule::try_from(lines).unwrap();
+
+        assert!(rule.src_networks.is_none());
+        assert!(rule.dst_networks.is_none());
+        assert_eq!(rule.protocol_factor(), 3);
+        assert_eq!(rule.capacity(), rule.protocol_factor());
+        assert_eq!(rule.optimized_capacity(), rule.protocol_factor());
+    }
+
     #[test]
     fn test_rule_capacity_with_one_port() {
         let source_networks = NetworkObject::try_from(&vec![
@@ -475,20 +1532,16 @@ mod tests {
             "10.0.0.0/8".to_string(),
         ])
         .unwrap();
-        let source_ports = Some(
-            ProtocolObject::try_from(&vec![
-                "Source Ports       : ephemeral (protocol 6, port 1024)".to_string(),
-            ])
-            .unwrap(),
-        );
-
-        let rule = Rule {
-            name: "Custom_rule2".to_string(),
-            src_networks: Some(source_networks),
-            dst_networks: Some(destination_networks),
-            src_protocols: source_ports,
-            dst_protocols: None,
-        };
+        let source_ports = ProtocolObject::try_from(&vec![
+            "Source Ports       : ephemeral (protocol 6, port 1024)".to_string(),
+        ])
+        .unwrap();
+
+        let rule = Builder::new("Custom_rule2".to_string())
+            .with_src_networks(source_networks)
+            .with_dst_networks(destination_networks)
+            .with_src_protocols(source_ports)
+            .build();
 
         assert_eq!(rule.capacity(), 2 * 2);
     }
@@ -506,26 +1559,21 @@ mod tests {
             "10.0.0.0/8".to_string(),
         ])
         .unwrap();
-        let source_ports = Some(
-            ProtocolObject::try_from(&vec![
-                "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
-            ])
-            .unwrap(),
-        );
-        let destination_ports = Some(
-            ProtocolObject::try_from(&vec![
-                "Destination Ports       : HTTPS (protocol 6, port 443-445)".to_string(),
-            ])
-            .unwrap(),
-        );
-
-        let rule = Rule {
-            name: "Custom_rule2".to_string(),
-            src_networks: Some(source_networks),
-            dst_networks: Some(destination_networks),
-            src_protocols: source_ports,
-            dst_protocols: destination_ports,
-        };
+        let source_ports = ProtocolObject::try_from(&vec![
+            "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
+        ])
+        .unwrap();
+        let destination_ports = ProtocolObject::try_from(&vec![
+            "Destination Ports       : HTTPS (protocol 6, port 443-445)".to_string(),
+        ])
+        .unwrap();
+
+        let rule = Builder::new("Custom_rule2".to_string())
+            .with_src_networks(source_networks)
+            .with_dst_networks(destination_networks)
+            .with_src_protocols(source_ports)
+            .with_dst_protocols(destination_ports)
+            .build();
 
         assert_eq!(rule.capacity(), 2 * 2);
     }
@@ -538,7 +1586,7 @@ mod tests {
         .unwrap()
         .optimize();
         let result = protocol_freq_distribution(&l3_l4_proto);
-        assert_eq!(result.get(&6), Some(&1));
+        assert_eq!(result.get(&(Protocol::Number(6), None, None)), Some(&1));
     }
 
     #[test]
@@ -550,7 +1598,7 @@ mod tests {
         .unwrap()
         .optimize();
         let result = protocol_freq_distribution(&l3_l4_proto);
-        assert_eq!(result.get(&6), Some(&2));
+        assert_eq!(result.get(&(Protocol::Number(6), None, None)), Some(&2));
     }
 
     #[test]
@@ -564,8 +1612,8 @@ mod tests {
         .optimize();
 
         let result = protocol_freq_distribution(&l3_l4_proto);
-        assert_eq!(result.get(&6), Some(&2));
-        assert_eq!(result.get(&17), Some(&1));
+        assert_eq!(result.get(&(Protocol::Number(6), None, None)), Some(&2));
+        assert_eq!(result.get(&(Protocol::Number(17), None, None)), Some(&1));
     }
 
     #[test]
@@ -582,39 +1630,173 @@ mod tests {
     }
 
     #[test]
-    fn test_get_protocol_factor_half_empty_1() {
-        let l3_l4_proto = ProtocolObject::try_from(&vec![
+    fn test_get_protocol_factor_half_empty_1() {
+        let l3_l4_proto = ProtocolObject::try_from(&vec![
+            "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
+            "HTTP (protocol 6, port 80)".to_string(),
+            "HTTP over UDP (protocol 17, port 80)".to_string(),
+        ])
+        .unwrap()
+        .optimize();
+
+        let result = get_protocol_factor(&Some(l3_l4_proto), &None);
+        assert_eq!(result, 2 + 1);
+    }
+
+    #[test]
+    fn test_get_protocol_factor_half_empty_2() {
+        let l3_l4_proto = ProtocolObject::try_from(&vec![
+            "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
+            "HTTP (protocol 6, port 80)".to_string(),
+            "HTTP over UDP (protocol 17, port 80)".to_string(),
+        ])
+        .unwrap()
+        .optimize();
+
+        let result = get_protocol_factor(&None, &Some(l3_l4_proto));
+        assert_eq!(result, 2 + 1);
+    }
+
+    #[test]
+    fn test_get_protocol_factor_1() {
+        let src_proto = ProtocolObject::try_from(&vec![
+            "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
+            "HTTP (protocol 6, port 80)".to_string(),
+            "HTTP over UDP (protocol 17, port 80)".to_string(),
+        ])
+        .unwrap()
+        .optimize();
+
+        let dst_proto = ProtocolObject::try_from(&vec![
+            "Destination Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
+            "HTTP (protocol 6, port 80)".to_string(),
+            "HTTP over UDP (protocol 17, port 80)".to_string(),
+        ])
+        .unwrap()
+        .optimize();
+
+        let result = get_protocol_factor(&Some(src_proto), &Some(dst_proto));
+        assert_eq!(result, 2 * 2 + 1);
+    }
+
+    #[test]
+    fn test_get_protocol_factor_2() {
+        let src_proto = ProtocolObject::try_from(&vec![
+            "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
+            "HTTP (protocol 6, port 80)".to_string(),
+            "HTTP over UDP (protocol 17, port 80)".to_string(),
+        ])
+        .unwrap()
+        .optimize();
+
+        let dst_proto = ProtocolObject::try_from(&vec![
+            "Destination Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
+            "HTTP (protocol 6, port 80)".to_string(),
+            "HTTPS (protocol 6, port 443)".to_string(),
+            "HTTP over UDP (protocol 17, port 80)".to_string(),
+        ])
+        .unwrap()
+        .optimize();
+
+        let result = get_protocol_factor(&Some(src_proto), &Some(dst_proto));
+        assert_eq!(result, 2 * 3 + 1);
+    }
+
+    #[test]
+    fn test_explain_protocol_factor_2() {
+        let src_proto = ProtocolObject::try_from(&vec![
+            "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
+            "HTTP (protocol 6, port 80)".to_string(),
+            "HTTP over UDP (protocol 17, port 80)".to_string(),
+        ])
+        .unwrap();
+        let dst_proto = ProtocolObject::try_from(&vec![
+            "Destination Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
+            "HTTP (protocol 6, port 80)".to_string(),
+            "HTTPS (protocol 6, port 443)".to_string(),
+            "HTTP over UDP (protocol 17, port 80)".to_string(),
+        ])
+        .unwrap();
+        let rule = Builder::new("Factor".to_string())
+            .with_src_protocols(src_proto)
+            .with_dst_protocols(dst_proto)
+            .build();
+
+        let explanation = rule.explain_protocol_factor();
+        assert_eq!(explanation.factor(), rule.protocol_factor());
+        assert_eq!(
+            explanation.to_string(),
+            "--- Protocol factor ---
+\tsource ports: protocol 6 x2, protocol 17 x1
+\tdestination ports: protocol 6 x3, protocol 17 x1
+\tprotocol 6: 2 * 3 = 6
+\tprotocol 17: 1 * 1 = 1
+\tprotocol factor: 6 + 1 = 7"
+        );
+    }
+
+    #[test]
+    fn test_explain_protocol_factor_one_side() {
+        let dst_proto = ProtocolObject::try_from(&vec![
+            "Destination Ports       : HTTP (protocol 6, port 80)".to_string(),
+            "Echo (protocol 1, type 8)".to_string(),
+        ])
+        .unwrap();
+        let rule = Builder::new("Factor".to_string())
+            .with_dst_protocols(dst_proto)
+            .build();
+
+        let explanation = rule.explain_protocol_factor();
+        assert_eq!(explanation.factor(), rule.protocol_factor());
+        assert_eq!(
+            explanation.to_string(),
+            "--- Protocol factor ---
+\tsource ports: any
+\tdestination ports: protocol 1 type 8 x1, protocol 6 x1
+\tprotocol 1 type 8: 1 (any) * 1 = 1
+\tprotocol 6: 1 (any) * 1 = 1
+\tprotocol factor: 1 + 1 = 2"
+        );
+
+        let any = Builder::new("Any".to_string()).build();
+        assert_eq!(any.explain_protocol_factor().factor(), 1);
+        assert!(any
+            .explain_protocol_factor()
+            .to_string()
+            .ends_with("no ports restricted, protocol factor: 1"));
+    }
+
+    #[test]
+    fn test_get_protocol_factor_3() {
+        let src_proto = ProtocolObject::try_from(&vec![
             "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
             "HTTP (protocol 6, port 80)".to_string(),
             "HTTP over UDP (protocol 17, port 80)".to_string(),
+            "IGMP (protocol 2)".to_string(),
         ])
         .unwrap()
         .optimize();
 
-        let result = get_protocol_factor(&Some(l3_l4_proto), &None);
-        assert_eq!(result, 2 + 1);
-    }
-
-    #[test]
-    fn test_get_protocol_factor_half_empty_2() {
-        let l3_l4_proto = ProtocolObject::try_from(&vec![
-            "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
+        let dst_proto = ProtocolObject::try_from(&vec![
+            "Destination Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
             "HTTP (protocol 6, port 80)".to_string(),
+            "HTTPS (protocol 6, port 443)".to_string(),
             "HTTP over UDP (protocol 17, port 80)".to_string(),
         ])
         .unwrap()
         .optimize();
 
-        let result = get_protocol_factor(&None, &Some(l3_l4_proto));
-        assert_eq!(result, 2 + 1);
+        let result = get_protocol_factor(&Some(src_proto), &Some(dst_proto));
+        assert_eq!(result, 2 * 3 + 1 + 1);
     }
 
     #[test]
-    fn test_get_protocol_factor_1() {
+    fn test_get_protocol_factor_4() {
         let src_proto = ProtocolObject::try_from(&vec![
             "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
             "HTTP (protocol 6, port 80)".to_string(),
             "HTTP over UDP (protocol 17, port 80)".to_string(),
+            "IGMP (protocol 2)".to_string(),
         ])
         .unwrap()
         .optimize();
@@ -622,85 +1804,162 @@ mod tests {
         let dst_proto = ProtocolObject::try_from(&vec![
             "Destination Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
             "HTTP (protocol 6, port 80)".to_string(),
+            "HTTPS (protocol 6, port 443)".to_string(),
+            "FTP (protocol 6, port 21)".to_string(),
             "HTTP over UDP (protocol 17, port 80)".to_string(),
         ])
         .unwrap()
         .optimize();
 
         let result = get_protocol_factor(&Some(src_proto), &Some(dst_proto));
-        assert_eq!(result, 2 * 2 + 1);
+        assert_eq!(result, 2 * 4 + 1 + 1);
     }
 
     #[test]
-    fn test_get_protocol_factor_2() {
+    fn test_get_protocol_factor_src_only_protocol() {
         let src_proto = ProtocolObject::try_from(&vec![
             "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
             "HTTP (protocol 6, port 80)".to_string(),
-            "HTTP over UDP (protocol 17, port 80)".to_string(),
+            "DNS (protocol 17, port 53)".to_string(),
         ])
         .unwrap()
         .optimize();
 
         let dst_proto = ProtocolObject::try_from(&vec![
-            "Destination Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
-            "HTTP (protocol 6, port 80)".to_string(),
-            "HTTPS (protocol 6, port 443)".to_string(),
-            "HTTP over UDP (protocol 17, port 80)".to_string(),
+            "Destination Ports       : HTTPS (protocol 6, port 443)".to_string(),
         ])
         .unwrap()
         .optimize();
 
-        let result = get_protocol_factor(&Some(src_proto), &Some(dst_proto));
-        assert_eq!(result, 2 * 3 + 1);
+        let (src_proto, dst_proto) = (Some(src_proto), Some(dst_proto));
+        // TCP (2 * 1) + UDP only in source (1 * 1)
+        assert_eq!(get_protocol_factor(&src_proto, &dst_proto), 2 + 1);
+        // FTD has no destination UDP port to combine with, so UDP doesn't produce an ACE
+        assert_eq!(get_ace_protocol_factor(&src_proto, &dst_proto), 2);
     }
 
     #[test]
-    fn test_get_protocol_factor_3() {
+    fn test_get_protocol_factor_dst_only_protocol() {
         let src_proto = ProtocolObject::try_from(&vec![
             "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
-            "HTTP (protocol 6, port 80)".to_string(),
-            "HTTP over UDP (protocol 17, port 80)".to_string(),
-            "IGMP (protocol 2)".to_string(),
         ])
         .unwrap()
         .optimize();
 
         let dst_proto = ProtocolObject::try_from(&vec![
-            "Destination Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
-            "HTTP (protocol 6, port 80)".to_string(),
+            "Destination Ports       : HTTP (protocol 6, port 80)".to_string(),
             "HTTPS (protocol 6, port 443)".to_string(),
-            "HTTP over UDP (protocol 17, port 80)".to_string(),
+            "DNS (protocol 17, port 53)".to_string(),
+            "NTP (protocol 17, port 123)".to_string(),
+            "IGMP (protocol 2)".to_string(),
+        ])
+        .unwrap()
+        .optimize();
+
+        let (src_proto, dst_proto) = (Some(src_proto), Some(dst_proto));
+        // TCP (1 * 2) + UDP only in destination (1 * 2) + IGMP only in destination (1 * 1)
+        assert_eq!(get_protocol_factor(&src_proto, &dst_proto), 2 + 2 + 1);
+        assert_eq!(get_ace_protocol_factor(&src_proto, &dst_proto), 2);
+    }
+
+    #[test]
+    fn test_protocol_freq_distribution_icmp_types() {
+        let l3_l4_proto = ProtocolObject::try_from(&vec![
+            "Source Ports       : Unreachable (protocol 1, type 3)".to_string(),
+            "Echo (protocol 1, type 8)".to_string(),
+            "Echo Reply (protocol 1, type 0)".to_string(),
+        ])
+        .unwrap()
+        .optimize();
+
+        let result = protocol_freq_distribution(&l3_l4_proto);
+        assert_eq!(result.len(), 3);
+        assert_eq!(result.get(&(Protocol::Number(1), Some(3), None)), Some(&1));
+        assert_eq!(result.get(&(Protocol::Number(1), Some(8), None)), Some(&1));
+        assert_eq!(result.get(&(Protocol::Number(1), None, None)), None);
+    }
+
+    #[test]
+    fn test_get_protocol_factor_icmp_same_type() {
+        let src_proto = ProtocolObject::try_from(&vec![
+            "Source Ports       : Unreachable (protocol 1, type 3, code 1)".to_string(),
+            "Unreachable (protocol 1, type 3, code 3)".to_string(),
+        ])
+        .unwrap()
+        .optimize();
+
+        let dst_proto = ProtocolObject::try_from(&vec![
+            "Destination Ports       : Unreachable (protocol 1, type 3, code 1)".to_string(),
+            "Unreachable (protocol 1, type 3, code 3)".to_string(),
         ])
         .unwrap()
         .optimize();
 
         let result = get_protocol_factor(&Some(src_proto), &Some(dst_proto));
-        assert_eq!(result, 2 * 3 + 1 + 1);
+        // each (type, code) pair is a separate entry: code 1 (1 * 1) + code 3 (1 * 1)
+        assert_eq!(result, 1 + 1);
     }
 
     #[test]
-    fn test_get_protocol_factor_4() {
+    fn test_get_protocol_factor_protocol_range() {
+        let dst_proto = ProtocolObject::try_from(&vec![
+            "Destination Ports       : RANGE (protocol 10-12)".to_string(),
+        ])
+        .unwrap()
+        .optimize();
+
+        let result = get_protocol_factor(&None, &Some(dst_proto));
+        assert_eq!(result, 3);
+    }
+
+    #[test]
+    fn test_protocol_freq_distribution_icmp_type_code_pairs() {
+        let l3_l4_proto = ProtocolObject::try_from(&vec![
+            "Source Ports       : Net Unreachable (protocol 1, type 3, code 0)".to_string(),
+            "Host Unreachable (protocol 1, type 3, code 1)".to_string(),
+            "TTL Exceeded (protocol 1, type 11, code 0)".to_string(),
+        ])
+        .unwrap()
+        .optimize();
+
+        let result = protocol_freq_distribution(&l3_l4_proto);
+        assert_eq!(result.len(), 3);
+        assert_eq!(
+            result.get(&(Protocol::Number(1), Some(3), Some(0))),
+            Some(&1)
+        );
+        assert_eq!(
+            result.get(&(Protocol::Number(1), Some(3), Some(1))),
+            Some(&1)
+        );
+        assert_eq!(
+            result.get(&(Protocol::Number(1), Some(11), Some(0))),
+            Some(&1)
+        );
+
+        // no destination ports: every pair is an entry of its own
+        let result = get_protocol_factor(&Some(l3_l4_proto), &None);
+        assert_eq!(result, 3);
+    }
+
+    #[test]
+    fn test_get_protocol_factor_icmp_different_types() {
         let src_proto = ProtocolObject::try_from(&vec![
-            "Source Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
-            "HTTP (protocol 6, port 80)".to_string(),
-            "HTTP over UDP (protocol 17, port 80)".to_string(),
-            "IGMP (protocol 2)".to_string(),
+            "Source Ports       : Unreachable (protocol 1, type 3, code 1)".to_string(),
+            "Unreachable (protocol 1, type 3, code 3)".to_string(),
         ])
         .unwrap()
         .optimize();
 
         let dst_proto = ProtocolObject::try_from(&vec![
-            "Destination Ports       : ephemeral (protocol 6, port 1024-1025)".to_string(),
-            "HTTP (protocol 6, port 80)".to_string(),
-            "HTTPS (protocol 6, port 443)".to_string(),
-            "FTP (protocol 6, port 21)".to_string(),
-            "HTTP over UDP (protocol 17, port 80)".to_string(),
+            "Destination Ports       : Echo (protocol 1, type 8)".to_string(),
+            "Echo Reply (protocol 1, type 0)".to_string(),
         ])
         .unwrap()
         .optimize();
 
         let result = get_protocol_factor(&Some(src_proto), &Some(dst_proto));
-        assert_eq!(result, 2 * 4 + 1 + 1);
+        assert_eq!(result, 2 + 1 + 1);
     }
 
     #[test]
@@ -730,6 +1989,75 @@ mod tests {
         assert_eq!(rule.capacity(), 32);
     }
 
+    #[test]
+    fn test_parse_rule_description() {
+        let rule = "----------[ Rule: Custom_rule2 | FM-15046 ]-----------
+    Action                : Allow
+    Source Networks       : 192.168.0.0/24
+    Description           : Web access for 10.0.0.0/8,
+                            requested by the branch offices
+    Destination Networks  : 172.16.0.0/12
+    Destination Ports     : HTTPS (protocol 6, port 443)
+    Logging Configuration";
+        let lines: Vec<String> = rule.lines().map(|s| s.to_string()).collect();
+        let rule = Rule::try_from(lines).unwrap();
+        assert_eq!(
+            rule.get_description(),
+            Some("Web access for 10.0.0.0/8, requested by the branch offices")
+        );
+        assert_eq!(rule.src_networks.as_ref().unwrap().capacity(), 1);
+        assert_eq!(rule.dst_networks.as_ref().unwrap().capacity(), 1);
+        assert_eq!(rule.capacity(), 1);
+    }
+
+    #[test]
+    fn test_parse_rule_without_description() {
+        let rule = "----------[ Rule: Custom_rule2 | FM-15046 ]-----------
+    Source Networks       : 192.168.0.0/24
+    Logging Configuration";
+        let lines: Vec<String> = rule.lines().map(|s| s.to_string()).collect();
+        let rule = Rule::try_from(lines).unwrap();
+        assert_eq!(rule.get_description(), None);
+    }
+
+    #[test]
+    fn test_parse_rule_indented_block() {
+        let rule = "----------[ Rule: Custom_rule2 | FM-15046 ]-----------
+    Source Networks       : Internal (group)
+        OBJ-192.168.0.0 (192.168.0.0/16)
+        OBJ-172.17.0.0 (172.17.0.0/16)
+      OBJ-198.187.64.0_18 (198.187.64.0/18)
+    Destination Networks  : 10.0.0.0/8
+    Destination Ports  : HTTPS (protocol 6, port 443)
+                         HTTP (protocol 6, port 80)
+    Logging Configuration";
+        let lines: Vec<String> = rule.lines().map(|s| s.to_string()).collect();
+        let indented: Vec<String> = lines.iter().map(|line| format!("    {line}")).collect();
+
+        let rule = Rule::try_from(lines).unwrap();
+        let indented_rule = Rule::try_from(indented).unwrap();
+        assert_eq!(indented_rule.get_name(), rule.get_name());
+        assert_eq!(indented_rule.capacity(), rule.capacity());
+        assert_eq!(indented_rule.capacity(), 3 * 2);
+        assert_eq!(
+            indented_rule.optimized_capacity(),
+            rule.optimized_capacity()
+        );
+    }
+
+    #[test]
+    fn test_strip_common_indentation() {
+        let lines = vec![
+            "    Rule".to_string(),
+            "".to_string(),
+            "      Body".to_string(),
+        ];
+        assert_eq!(
+            strip_common_indentation(lines),
+            vec!["Rule".to_string(), "".to_string(), "  Body".to_string()]
+        );
+    }
+
     #[test]
     fn test_parse_rule_missing_dst_ports() {
         let rule = "----------[ Rule: Custom_rule2 | FM-15046 ]-----------
@@ -856,6 +2184,150 @@ mod tests {
         assert_eq!(rule.optimized_capacity(), 3 * 3 * 2 * 2);
     }
 
+    #[test]
+    fn test_explain_three_prefix_merge() {
+        let rule = "----------[ Rule: Custom_rule2 | FM-15046 ]-----------
+    Source Networks       : 10.0.0.0/24
+      10.0.1.0/24
+      10.0.2.0/23
+    Destination Ports  : HTTPS (protocol 6, port 443)
+    Logging Configuration";
+        let lines: Vec<String> = rule.lines().map(|s| s.to_string()).collect();
+        let rule = Rule::try_from(lines).unwrap();
+
+        let explanation = rule.explain();
+        assert_eq!(explanation.len(), 2);
+        assert_eq!(
+            explanation[0].to_string(),
+            "--- Source Networks ---
+\tcapacity: 3
+\tstep 1: 10.0.0.0/24 ADJOINS 10.0.1.0/24 -> capacity 1
+\tstep 2: 10.0.0.0/24 ADJOINS 10.0.1.0/24 ADJOINS 10.0.2.0/23 -> capacity 1
+\toptimized capacity: 1"
+        );
+        assert_eq!(
+            explanation[1].to_string(),
+            "--- Destination Ports ---
+\tcapacity: 1
+\tno entries merged
+\toptimized capacity: 1"
+        );
+    }
+
+    #[test]
+    fn test_parse_rule_with_vlan_tags() {
+        let rule = "----------[ Rule: Custom_rule2 | FM-15046 ]-----------
+    Source Networks       : 10.0.0.0/24
+      10.1.0.0/24
+    Destination Networks  : 192.168.0.0/24
+    VLAN Tags             : 100
+      200-210
+    Source SGT            : Employees (5)
+    Destination Ports     : HTTPS (protocol 6, port 443)
+      SSH (protocol 6, port 22)
+    Logging Configuration";
+        let lines: Vec<String> = rule.lines().map(|s| s.to_string()).collect();
+        let rule = Rule::try_from(lines).unwrap();
+
+        assert_eq!(rule.src_networks.as_ref().unwrap().capacity(), 2);
+        assert_eq!(rule.dst_networks.as_ref().unwrap().capacity(), 1);
+        assert!(rule.src_protocols.is_none());
+        assert_eq!(rule.dst_protocols.as_ref().unwrap().optimize().len(), 2);
+        assert_eq!(rule.capacity(), 2 * 2);
+    }
+
+    #[test]
+    fn test_end_markers() {
+        let markers = end_markers("Source Ports");
+        assert!(!markers.contains(&"Source Ports"));
+        assert!(markers.contains(&"VLAN Tags"));
+        assert!(markers.contains(&"Destination Ports"));
+    }
+
+    #[test]
+    fn test_ace_count_tcp_ranges_with_l3_protocol() {
+        let rule = "----------[ Rule: Custom_rule2 | FM-15046 ]-----------
+    Source Networks       : 10.0.0.0/24
+      10.2.0.0/24
+    Source Ports     : ephemeral (protocol 6, port 1024-2047)
+    Destination Ports  : HTTP (protocol 6, port 80-81)
+       HTTPS (protocol 6, port 443)
+       IGMP (protocol 2)
+    Logging Configuration";
+        let lines: Vec<String> = rule.lines().map(|s| s.to_string()).collect();
+        let rule = Rule::try_from(lines).unwrap();
+
+        // TCP (1 * 2) + IGMP with "any" source port (1)
+        assert_eq!(rule.capacity(), 2 * (2 + 1));
+        // IGMP can't be combined with TCP source ports
+        assert_eq!(rule.ace_count(), 2 * 2);
+    }
+
+    #[test]
+    fn test_ace_count_by_protocol() {
+        let rule = "----------[ Rule: Custom_rule2 | FM-15046 ]-----------
+    Source Networks       : 10.0.0.0/24
+      10.2.0.0/24
+    Source Ports     : ephemeral (protocol 6, port 1024-2047)
+      DNS-client (protocol 17, port 5353)
+    Destination Ports  : HTTP (protocol 6, port 80-81)
+       HTTPS (protocol 6, port 443)
+       DNS (protocol 17, port 53)
+       IGMP (protocol 2)
+    Logging Configuration";
+        let lines: Vec<String> = rule.lines().map(|s| s.to_string()).collect();
+        let rule = Rule::try_from(lines).unwrap();
+
+        let by_protocol = rule.ace_count_by_protocol();
+        assert_eq!(by_protocol, HashMap::from([(6, 2 * 2), (17, 2)]));
+        assert_eq!(by_protocol.values().sum::<u64>(), rule.ace_count());
+
+        assert_eq!(rule.ace_count_any_protocol(), 0);
+
+        let any = Builder::new("Any".to_string()).build();
+        assert!(any.ace_count_by_protocol().is_empty());
+        assert_eq!(any.ace_count_any_protocol(), 1);
+    }
+
+    #[test]
+    fn test_ace_count_without_src_ports() {
+        let rule = "----------[ Rule: Custom_rule2 | FM-15046 ]-----------
+    Source Networks       : 10.0.0.0/24
+    Destination Ports  : HTTP (protocol 6, port 80-81)
+       HTTPS (protocol 6, port 443)
+       IGMP (protocol 2)
+    Logging Configuration";
+        let lines: Vec<String> = rule.lines().map(|s| s.to_string()).collect();
+        let rule = Rule::try_from(lines).unwrap();
+
+        assert_eq!(rule.capacity(), 3);
+        assert_eq!(rule.ace_count(), 3);
+    }
+
+    #[test]
+    fn test_protocol_factor_memoized() {
+        let rule = "----------[ Rule: Custom_rule2 | FM-15046 ]-----------
+    Source Networks       : 192.168.100.0/24
+    Source Ports     : ephemeral (protocol 6, port 1024)
+       FTP (protocol 6, port 21)
+    Destination Ports  : HTTPS (protocol 6, port 443)
+       FTP (protocol 6, port 21)
+    Logging Configuration";
+        let lines: Vec<String> = rule.lines().map(|s| s.to_string()).collect();
+        let rule = Rule::try_from(lines).unwrap();
+        assert!(rule.protocol_factor.get().is_none());
+        assert!(rule.optimized_protocols.get().is_none());
+
+        let capacity = rule.capacity();
+        let cached = rule.optimized_protocols() as *const OptimizedProtocols;
+        assert_eq!(rule.protocol_factor.get(), Some(&(2 * 2)));
+
+        assert_eq!(rule.capacity(), capacity);
+        assert_eq!(rule.optimized_capacity(), capacity);
+        assert_eq!(rule.protocol_factor(), 2 * 2);
+        assert!(std::ptr::eq(cached, rule.optimized_protocols()));
+    }
+
     #[test]
     fn test_optimized_capacity_missing_src_network() {
         let rule = "----------[ Rule: Custom_rule2 | FM-15046 ]-----------
@@ -965,4 +2437,218 @@ mod tests {
         assert_eq!(rule.capacity(), 10 * 8 * 2);
         assert_eq!(rule.optimized_capacity(), 3 * 3 * 2);
     }
+
+    fn rule_from(text: &str) -> Rule {
+        Rule::try_from(text.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn test_same_matchers() {
+        let rule = rule_from(
+            "----------[ Rule: Web ]-----------
+    Action                : Allow
+    Source Networks       : 10.0.0.0/24
+                            10.0.1.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)
+                            HTTP (protocol 6, port 80)",
+        );
+        let reordered = rule_from(
+            "----------[ Rule: Web_renamed ]-----------
+    Action                : Allow
+    Source Networks       : NET-10.0.0.0_23 (10.0.0.0/23)
+    Destination Ports     : TCP-80 (protocol 6, port 80)
+                            TCP-443 (protocol 6, port 443)",
+        );
+        let other_action = rule_from(
+            "----------[ Rule: Web ]-----------
+    Action                : Block
+    Source Networks       : 10.0.0.0/23
+    Destination Ports     : HTTPS (protocol 6, port 443)
+                            HTTP (protocol 6, port 80)",
+        );
+        let other_port = rule_from(
+            "----------[ Rule: Web ]-----------
+    Action                : Allow
+    Source Networks       : 10.0.0.0/23
+    Destination Ports     : HTTPS (protocol 6, port 443)",
+        );
+
+        assert!(rule.same_matchers(&rule));
+        assert!(rule.same_matchers(&reordered));
+        assert!(!rule.same_matchers(&other_action));
+        assert!(!rule.same_matchers(&other_port));
+    }
+
+    #[test]
+    fn test_canonical() {
+        let rule = rule_from(
+            "----------[ Rule: Web ]-----------
+    Action                : Allow
+    Source Networks       : 10.1.0.0/24
+                            OBJ-10.0.0.1 (10.0.0.1)
+                            192.168.0.0/16
+    Destination Networks  : 172.16.0.0/12",
+        );
+        let reordered = rule_from(
+            "----------[ Rule: Web_copy ]-----------
+    Action                : Allow
+    Source Networks       : 192.168.0.0/16
+                            10.0.0.1/32
+                            10.1.0.0/24
+    Destination Networks  : 172.16.0.0/12",
+        );
+
+        assert_eq!(rule.canonical(), reordered.canonical());
+        assert_ne!(
+            rule.canonical(),
+            rule_from(
+                "----------[ Rule: Web ]-----------
+    Action                : Allow
+    Source Networks       : 10.1.0.0/24",
+            )
+            .canonical()
+        );
+        assert_ne!(
+            rule.canonical(),
+            rule_from(
+                "----------[ Rule: Web ]-----------
+    Action                : Allow
+    Time Range            : Business-Hours
+    Source Networks       : 10.1.0.0/24
+                            OBJ-10.0.0.1 (10.0.0.1)
+                            192.168.0.0/16
+    Destination Networks  : 172.16.0.0/12",
+            )
+            .canonical()
+        );
+    }
+
+    #[test]
+    fn test_builder_equals_parsed_rule() {
+        let parsed = rule_from(
+            "----------[ Rule: Web ]-----------
+    Action                : Allow
+    Description           : Web servers
+    Source Networks       : 10.0.0.0/24
+                            10.0.1.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)",
+        );
+
+        let built = Builder::new("Web".to_string())
+            .with_action("Allow".to_string())
+            .with_src_networks(
+                NetworkObject::try_from(&vec![
+                    "Source Networks : NET-10.0.0.0_23 (10.0.0.0/23)".to_string()
+                ])
+                .unwrap(),
+            )
+            .with_dst_protocols(
+                ProtocolObject::try_from(&vec![
+                    "Destination Ports : TCP-443 (protocol 6, port 443)".to_string(),
+                ])
+                .unwrap(),
+            )
+            .build();
+
+        assert_eq!(built, parsed);
+        assert_eq!(built.clone(), parsed);
+        assert_eq!(built.capacity(), 1);
+
+        let renamed = Builder::new("Web_v2".to_string())
+            .with_action("Allow".to_string())
+            .build();
+        assert_ne!(renamed, parsed);
+    }
+
+    #[test]
+    fn test_get_protocol_factor_all_protocols() {
+        let ports = |lines: &[&str]| {
+            Some(
+                ProtocolObject::try_from(&lines.iter().map(|l| l.to_string()).collect())
+                    .unwrap()
+                    .optimize(),
+            )
+        };
+        let any = ports(&["Source Ports : ANY (protocol ip)"]);
+        let any_with_tcp = ports(&[
+            "Destination Ports : ANY (protocol all)",
+            "HTTP (protocol 6, port 80)",
+        ]);
+        let web = ports(&[
+            "Destination Ports : HTTP (protocol 6, port 80)",
+            "HTTPS (protocol 6, port 443)",
+        ]);
+
+        assert_eq!(get_protocol_factor(&any, &None), 1);
+        assert_eq!(get_protocol_factor(&any, &any_with_tcp), 1);
+        assert_eq!(get_protocol_factor(&any, &web), 2);
+        assert_eq!(get_ace_protocol_factor(&any, &web), 2);
+        assert_eq!(get_ace_protocol_factor(&any, &any_with_tcp), 1);
+    }
+
+    #[test]
+    fn test_optimized_capacity_is_optimized_networks_by_protocol_factor() {
+        let rule = rule_from(
+            "----------[ Rule: Web ]-----------
+    Source Networks       : 10.0.0.0/24
+                            10.0.1.0/24
+                            172.16.0.0/24
+    Destination Networks  : 192.168.0.0/24
+    Destination Ports     : TCP-80 (protocol 6, port 80)
+                            TCP-81 (protocol 6, port 81)
+                            DNS (protocol 17, port 53)",
+        );
+
+        let (src_networks, dst_networks) = rule.get_optimized_networks();
+        let src_capacity = src_networks.unwrap().capacity();
+        let dst_capacity = dst_networks.unwrap().capacity();
+
+        // adjacent TCP ports are merged, so the factor is 2 and not 3
+        assert_eq!(rule.protocol_factor(), 2);
+        assert_eq!(src_capacity, 2);
+        assert_eq!(dst_capacity, 1);
+        assert_eq!(
+            rule.optimized_capacity(),
+            src_capacity * dst_capacity * rule.protocol_factor()
+        );
+        assert_eq!(rule.capacity(), 3 * 2);
+    }
+
+    #[test]
+    fn test_is_overly_broad() {
+        let rule = rule_from(
+            "----------[ Rule: Permit-Any ]-----------
+    Source Networks       : ANY (0.0.0.0/0)
+    Destination Ports     : ANY (protocol ip)",
+        );
+
+        let report = rule.is_overly_broad();
+        assert_eq!(
+            report,
+            BroadnessReport {
+                any_source: true,
+                any_destination: true,
+                any_protocol: true,
+            }
+        );
+        assert!(report.is_broad());
+        assert_eq!(
+            report.to_string(),
+            "any source, any destination, any protocol"
+        );
+    }
+
+    #[test]
+    fn test_is_overly_broad_scoped_rule() {
+        let rule = rule_from(
+            "----------[ Rule: Web ]-----------
+    Source Networks       : 10.0.0.0/24
+    Destination Networks  : 192.168.0.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)",
+        );
+
+        let report = rule.is_overly_broad();
+        assert_eq!(report, BroadnessReport::default());
+        assert!(!report.is_broad());
+    }
 }
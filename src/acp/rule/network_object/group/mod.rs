@@ -3,6 +3,8 @@ use std::str::FromStr;
 pub mod prefix_list;
 use prefix_list::PrefixList;
 
+use super::utilities;
+
 #[derive(Debug, Clone)]
 pub struct Group {
     _name: String,
@@ -17,6 +19,10 @@ pub enum GroupError {
     General2(String, String),
     #[error("Failed to parse network group: {0}")]
     PrefixListError(#[from] prefix_list::PrefixListError),
+    #[error("Fail to parse network group {0}: groups are nested deeper than {1} levels")]
+    NestingTooDeep(String, usize),
+    #[error("Failed to parse network group: {0}")]
+    NestedGroup(#[from] utilities::UtilitiesError),
 }
 
 impl TryFrom<&Vec<String>> for Group {
@@ -31,9 +37,20 @@ impl TryFrom<&Vec<String>> for Group {
     //                           10.0.0.0/8
     //                           204.99.0.0/16
     //                           172.16.0.0/12
+    //                           DC (group)
+    //                             10.1.0.0/16
+    //
+    // Members of nested groups are flattened into prefix_lists
 
     fn try_from(lines: &Vec<String>) -> Result<Self, Self::Error> {
-        if let [title, ..] = lines.as_slice() {
+        Group::parse(lines, 1)
+    }
+}
+
+impl Group {
+    /// Parses the group at the given nesting depth, see utilities::max_group_depth
+    fn parse(lines: &[String], depth: usize) -> Result<Self, GroupError> {
+        if let [title, ..] = lines {
             if !title.contains(" (group)") {
                 return Err(GroupError::General(format!(
                     "Invalid network group format, should contain (group) {}",
@@ -41,13 +58,28 @@ impl TryFrom<&Vec<String>> for Group {
                 )));
             }
             let name = title.split('(').next().unwrap().trim().to_string();
+            if depth > utilities::max_group_depth() {
+                return Err(GroupError::NestingTooDeep(
+                    name,
+                    utilities::max_group_depth(),
+                ));
+            }
             let mut prefix_lists = vec![];
 
-            for line in &lines[1..] {
-                let prefix = line.trim();
+            let mut idx = 1;
+            while idx < lines.len() {
+                let prefix = lines[idx].trim();
+                if prefix.contains(" (group)") {
+                    let lines_in_group = utilities::calculate_lines_in_nested_group(&lines[idx..])?;
+                    let nested = Group::parse(&lines[idx..idx + lines_in_group], depth + 1)?;
+                    prefix_lists.extend(nested.prefix_lists);
+                    idx += lines_in_group;
+                    continue;
+                }
                 if !prefix.is_empty() {
                     prefix_lists.push(PrefixList::from_str(prefix)?);
                 }
+                idx += 1;
             }
 
             Ok(Self {
@@ -60,9 +92,11 @@ impl TryFrom<&Vec<String>> for Group {
             ))
         }
     }
-}
 
-impl Group {
+    pub fn get_name(&self) -> &str {
+        &self._name
+    }
+
     pub fn get_prefix_lists(&self) -> &Vec<PrefixList> {
         &self.prefix_lists
     }
@@ -95,6 +129,19 @@ mod tests {
         assert_eq!(group.prefix_lists.len(), 8);
     }
 
+    #[test]
+    fn test_valid_group_with_object_count_annotation() {
+        let lines = vec![
+            "Internal (group) [2 objects]".to_string(),
+            "      OBJ-157.121.0.0 (157.121.0.0/16)".to_string(),
+            "      10.0.0.0/8".to_string(),
+        ];
+
+        let group = Group::try_from(&lines).unwrap();
+        assert_eq!(group._name, "Internal");
+        assert_eq!(group.prefix_lists.len(), 2);
+    }
+
     #[test]
     fn test_invalid_group_format() {
         let lines = vec!["__Invalid group format__".to_string()];
@@ -117,6 +164,46 @@ mod tests {
         );
     }
 
+    fn nested_groups(levels: usize) -> Vec<String> {
+        (0..levels)
+            .flat_map(|depth| {
+                [
+                    format!("{}Level-{} (group)", " ".repeat(depth * 2), depth),
+                    format!("{}10.{}.0.0/16", " ".repeat(depth * 2 + 2), depth),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_nested_group() {
+        let lines = vec![
+            "Internal (group)".to_string(),
+            "  10.0.0.0/8".to_string(),
+            "  DC (group)".to_string(),
+            "    172.16.0.0/12".to_string(),
+            "    192.168.0.0/16".to_string(),
+            "  10.1.0.0/16".to_string(),
+        ];
+
+        let group = Group::try_from(&lines).unwrap();
+        assert_eq!(group._name, "Internal");
+        assert_eq!(group.prefix_lists.len(), 4);
+    }
+
+    #[test]
+    fn test_deeply_nested_group() {
+        let group = Group::try_from(&nested_groups(utilities::DEFAULT_MAX_GROUP_DEPTH)).unwrap();
+        assert_eq!(group.prefix_lists.len(), utilities::DEFAULT_MAX_GROUP_DEPTH);
+
+        let result = Group::try_from(&nested_groups(32));
+        assert!(matches!(result, Err(GroupError::NestingTooDeep(_, 16))));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Fail to parse network group Level-16: groups are nested deeper than 16 levels"
+        );
+    }
+
     #[test]
     fn test_group_with_empty_prefixes() {
         let lines = vec![
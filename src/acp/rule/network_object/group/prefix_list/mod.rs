@@ -13,11 +13,21 @@ pub struct PrefixList {
 pub enum PrefixListError {
     #[error("Fail to parse prefix list: {0}")]
     General(String),
-    #[error("Fail to parse prefix list '{content}' with error: {prefix_list_item_error}")]
+    /// Member of a comma-separated list failed to parse, index is 1-based.
+    /// The item error is boxed to keep the size of the error small
+    #[error(
+        "Fail to parse member {index} '{content}' of '{line}' with error: {prefix_list_item_error}"
+    )]
     PrefixListParseError {
+        index: usize,
         content: String,
-        prefix_list_item_error: prefix_list_item::PrefixListItemError,
+        line: String,
+        prefix_list_item_error: Box<prefix_list_item::PrefixListItemError>,
     },
+    /// Nothing between two commas or after the last comma, index is 1-based.
+    /// For example: "RFC1918 (10.0.0.0/8,, 172.16.0.0/12)"
+    #[error("Empty member at position {index} of '{line}'")]
+    EmptyMember { index: usize, line: String },
     /// This error is returned when the input string contains mismatched parentheses,
     /// making it an invalid prefix list format. For example, an input like "10.0.0.0/8)"
     /// or "(10.0.0.0/8" would trigger this error.
@@ -32,21 +42,23 @@ impl FromStr for PrefixList {
     // RFC1918 (10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16, 10.11.12.13-10.11.12.18)
     // Example line2:
     // 10.0.0.0/8
+    // Example line3:
+    // OBJ (East) (10.0.0.0/8)
     fn from_str(line: &str) -> Result<Self, Self::Err> {
         if line.contains("()") {
             return Err(PrefixListError::General("Empty prefix list.".to_string()));
         }
 
         if line.contains("(") && line.contains(")") {
-            let name = line.split("(").collect::<Vec<&str>>()[0].trim().to_string();
-
-            let prefix_str = line
-                .split("(")
-                .nth(1)
-                .ok_or(PrefixListError::General(format!(
-                    "Invalid prefix list format ({}), open parenthesis doesn't split prefix in two pieces.",
-                    line
-                )))?
+            // Name may contain parentheses itself, e.g. "OBJ (East) (10.0.0.0/8)",
+            // so the address list is opened by the last parenthesis
+            let open_idx = line.rfind("(").ok_or(PrefixListError::General(format!(
+                "Invalid prefix list format ({}), open parenthesis is missing.",
+                line
+            )))?;
+            let name = line[..open_idx].trim().to_string();
+
+            let prefix_str = line[open_idx + 1..]
                 .split(")")
                 .next()
                 .ok_or(PrefixListError::General(format!(
@@ -58,11 +70,21 @@ impl FromStr for PrefixList {
 
             let items = prefix_str
                 .split(",")
-                .map(|s| {
+                .enumerate()
+                .map(|(idx, s)| {
+                    if s.trim().is_empty() {
+                        return Err(PrefixListError::EmptyMember {
+                            index: idx + 1,
+                            line: line.trim().to_string(),
+                        });
+                    }
+
                     s.trim().parse::<PrefixListItem>().map_err(|e| {
                         PrefixListError::PrefixListParseError {
+                            index: idx + 1,
                             content: s.trim().to_string(),
-                            prefix_list_item_error: e,
+                            line: line.trim().to_string(),
+                            prefix_list_item_error: Box::new(e),
                         }
                     })
                 })
@@ -88,6 +110,11 @@ impl FromStr for PrefixList {
 }
 
 impl PrefixList {
+    /// Object name, or the entry itself if it has no name ("10.0.0.0/8")
+    pub fn get_name(&self) -> &str {
+        &self._name
+    }
+
     pub fn get_items(&self) -> &Vec<PrefixListItem> {
         &self.items
     }
@@ -115,6 +142,15 @@ mod tests {
         assert_eq!(prefix_list._name, "RFC1918");
     }
 
+    #[test]
+    fn test_valid_prefix_list_parenthesized_name() {
+        let line = "OBJ (East) (10.0.0.0/8)";
+        let prefix_list = PrefixList::from_str(line).unwrap();
+        assert_eq!(prefix_list._name, "OBJ (East)");
+        assert_eq!(prefix_list.items.len(), 1);
+        assert_eq!(prefix_list.items[0].get_name(), "10.0.0.0/8");
+    }
+
     #[test]
     fn test_valid_prefix_list2() {
         let line = "10.0.0.0/8";
@@ -164,6 +200,19 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_invalid_prefix_names_member() {
+        let line = "RFC1918 (10.0.0.0/8, 172.16.0.0/12, invalid_prefix)";
+        let result = PrefixList::from_str(line);
+        assert!(matches!(
+            result,
+            Err(PrefixListError::PrefixListParseError { index: 3, .. })
+        ));
+        assert!(result.unwrap_err().to_string().starts_with(
+            "Fail to parse member 3 'invalid_prefix' of 'RFC1918 (10.0.0.0/8, 172.16.0.0/12, invalid_prefix)'"
+        ));
+    }
+
     #[test]
     fn test_invalid_prefix_list_format_duplicate() {
         let line = "RFC1918 10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16";
@@ -218,6 +267,18 @@ mod tests {
         let line = "RFC1918 (10.0.0.0/8,, 172.16.0.0/12, 192.168.0.0/16)";
         let result = PrefixList::from_str(line);
         assert!(result.is_err());
+
+        let result = PrefixList::from_str("RFC1918 (10.0.0.0/8,, 172.16.0.0/12)");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Empty member at position 2 of 'RFC1918 (10.0.0.0/8,, 172.16.0.0/12)'"
+        );
+
+        let result = PrefixList::from_str("RFC1918 (10.0.0.0/8, 172.16.0.0/12, )");
+        assert!(matches!(
+            result,
+            Err(PrefixListError::EmptyMember { index: 3, .. })
+        ));
     }
 
     #[test]
@@ -259,6 +320,34 @@ mod tests {
         assert_eq!(prefix_list.capacity(), 10);
     }
 
+    #[test]
+    fn test_type_tagged_names() {
+        // 10.0.0.1/32, 10.0.0.2/31, 10.0.0.4/30, 10.0.0.8/31
+        for (line, name) in [
+            ("R-range (10.0.0.1-10.0.0.9)", "R-range"),
+            (
+                "R-10.0.0.1-10.0.0.9 (10.0.0.1-10.0.0.9)",
+                "R-10.0.0.1-10.0.0.9",
+            ),
+        ] {
+            let prefix_list = PrefixList::from_str(line).unwrap();
+            assert_eq!(prefix_list._name, name);
+            assert!(matches!(
+                prefix_list.items[..],
+                [PrefixListItem::IPRange(_)]
+            ));
+            assert_eq!(prefix_list.capacity(), 4);
+        }
+
+        let network = PrefixList::from_str("N-10.0.0.0_24 (10.0.0.0/24)").unwrap();
+        assert_eq!(network._name, "N-10.0.0.0_24");
+        assert_eq!(network.capacity(), 1);
+
+        let host = PrefixList::from_str("H-server (10.0.0.5)").unwrap();
+        assert_eq!(host._name, "H-server");
+        assert!(host.items[0].is_host());
+    }
+
     #[test]
     fn test_capacity_empty_prefix_list() {
         let line = "Empty ()";
@@ -1,47 +1,134 @@
+use std::cell::RefCell;
 use std::net::IpAddr;
 use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 
 use super::ipv4::IPv4;
 use std::net::ToSocketAddrs;
 
+/// Hostname resolved to a single IPv4 address.
+/// Unresolved hostname (DNS disabled) is kept as a placeholder with capacity 0.
 #[derive(Debug, Clone)]
 pub struct Hostname {
     name: String,
     start: IPv4,
     end: IPv4,
+    resolved: bool,
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum HostnameError {
     #[error("Fail to resolve name: {name}")]
     NameResolution { name: String },
+    #[error("Timeout ({timeout_ms} ms) resolving name: {name}")]
+    Timeout { name: String, timeout_ms: u128 },
     #[error("IPv6 not supported: {addr}")]
     IPv6NotSupported { addr: String },
     #[error("Transit error in Hostname from Io: {0}")]
     Io(#[from] std::io::Error),
 }
 
+/// Resolves hostnames found in network objects
+/// Ok(Some(ip)) - name resolved
+/// Ok(None) - resolution skipped, hostname becomes a placeholder
+pub trait Resolver {
+    fn resolve(&self, name: &str) -> Result<Option<IPv4>, HostnameError>;
+}
+
+/// Resolver backed by the system DNS, optionally bounded by a timeout
+#[derive(Debug, Default)]
+pub struct SystemResolver {
+    timeout: Option<Duration>,
+}
+
+impl SystemResolver {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        SystemResolver { timeout }
+    }
+}
+
+impl Resolver for SystemResolver {
+    fn resolve(&self, name: &str) -> Result<Option<IPv4>, HostnameError> {
+        let host = name.to_string();
+        let lookup = move || system_lookup(&host);
+
+        match self.timeout {
+            Some(timeout) => with_timeout(name, timeout, lookup),
+            None => lookup(),
+        }
+    }
+}
+
+/// Resolver that never touches the network (--no-dns)
+#[derive(Debug, Default)]
+pub struct NoDnsResolver;
+
+impl Resolver for NoDnsResolver {
+    fn resolve(&self, _name: &str) -> Result<Option<IPv4>, HostnameError> {
+        Ok(None)
+    }
+}
+
+thread_local! {
+    static RESOLVER: RefCell<Box<dyn Resolver>> = RefCell::new(Box::new(SystemResolver::default()));
+}
+
+/// Replaces the resolver used by Hostname::from_str on the current thread
+pub fn set_resolver(resolver: Box<dyn Resolver>) {
+    RESOLVER.with(|r| *r.borrow_mut() = resolver);
+}
+
+fn system_lookup(name: &str) -> Result<Option<IPv4>, HostnameError> {
+    let addrs_iter = format!("{name}:443").to_socket_addrs()?;
+    for addr in addrs_iter {
+        if let IpAddr::V4(ipv4) = addr.ip() {
+            return Ok(Some(IPv4::from(ipv4.to_bits())));
+        }
+    }
+
+    Err(HostnameError::NameResolution {
+        name: name.to_string(),
+    })
+}
+
+/// Runs the lookup in a separate thread, system resolver can't be cancelled,
+/// so the thread is abandoned on timeout
+fn with_timeout<F>(name: &str, timeout: Duration, lookup: F) -> Result<Option<IPv4>, HostnameError>
+where
+    F: FnOnce() -> Result<Option<IPv4>, HostnameError> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        // receiver is gone after timeout, nothing to report
+        let _ = tx.send(lookup());
+    });
+
+    rx.recv_timeout(timeout).unwrap_or_else(|_| {
+        Err(HostnameError::Timeout {
+            name: name.to_string(),
+            timeout_ms: timeout.as_millis(),
+        })
+    })
+}
+
 impl FromStr for Hostname {
     type Err = HostnameError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let addrs_iter = format!("{s}:443").to_socket_addrs()?;
-        for addr in addrs_iter {
-            let ip = addr.ip();
-
-            if let IpAddr::V4(ipv4) = ip {
-                let start = IPv4::from(ipv4.to_bits());
-                let end = start.clone();
-                return Ok(Hostname {
-                    name: s.to_string(),
-                    start,
-                    end,
-                });
-            }
-        }
+        let resolved = RESOLVER.with(|r| r.borrow().resolve(s))?;
+
+        let (ip, resolved) = match resolved {
+            Some(ip) => (ip, true),
+            None => (IPv4::from(0), false),
+        };
 
-        Err(HostnameError::NameResolution {
+        Ok(Hostname {
             name: s.to_string(),
+            start: ip.clone(),
+            end: ip,
+            resolved,
         })
     }
 }
@@ -59,8 +146,16 @@ impl Hostname {
         &self.end
     }
 
+    pub fn is_resolved(&self) -> bool {
+        self.resolved
+    }
+
     pub fn capacity(&self) -> u64 {
-        1
+        if self.resolved {
+            1
+        } else {
+            0
+        }
     }
 }
 
@@ -117,6 +212,7 @@ mod tests {
             name: "example.com".to_string(),
             start: IPv4::from(0),
             end: IPv4::from(0),
+            resolved: true,
         };
 
         assert_eq!(hostname.get_name(), "example.com");
@@ -129,6 +225,7 @@ mod tests {
             name: "example.com".to_string(),
             start: start_ip.clone(),
             end: start_ip.clone(),
+            resolved: true,
         };
 
         assert_eq!(hostname.start_ip(), &start_ip);
@@ -141,8 +238,70 @@ mod tests {
             name: "example.com".to_string(),
             start: end_ip.clone(),
             end: end_ip.clone(),
+            resolved: true,
         };
 
         assert_eq!(hostname.end_ip(), &end_ip);
     }
+
+    struct MockResolver {
+        ip: Option<IPv4>,
+    }
+
+    impl Resolver for MockResolver {
+        fn resolve(&self, _name: &str) -> Result<Option<IPv4>, HostnameError> {
+            Ok(self.ip.clone())
+        }
+    }
+
+    #[test]
+    fn test_hostname_uses_configured_resolver() {
+        set_resolver(Box::new(MockResolver {
+            ip: Some(IPv4::from(0x0a000001)),
+        }));
+
+        let hostname = Hostname::from_str("mock.example.com").unwrap();
+
+        assert_eq!(hostname.start_ip(), &IPv4::from(0x0a000001));
+        assert!(hostname.is_resolved());
+        assert_eq!(hostname.capacity(), 1);
+    }
+
+    #[test]
+    fn test_hostname_no_dns_capacity_zero() {
+        set_resolver(Box::new(NoDnsResolver));
+
+        let hostname = Hostname::from_str("ipv4.net").unwrap();
+
+        assert_eq!(hostname.get_name(), "ipv4.net");
+        assert!(!hostname.is_resolved());
+        assert_eq!(hostname.capacity(), 0);
+    }
+
+    #[test]
+    fn test_resolution_timeout() {
+        let slow_lookup = || {
+            thread::sleep(Duration::from_millis(500));
+            Ok(Some(IPv4::from(1)))
+        };
+
+        let result = with_timeout("slow.example.com", Duration::from_millis(10), slow_lookup);
+
+        match result {
+            Err(HostnameError::Timeout { name, timeout_ms }) => {
+                assert_eq!(name, "slow.example.com");
+                assert_eq!(timeout_ms, 10);
+            }
+            _ => panic!("Expected Timeout error"),
+        }
+    }
+
+    #[test]
+    fn test_resolution_within_timeout() {
+        let fast_lookup = || Ok(Some(IPv4::from(1)));
+
+        let result = with_timeout("fast.example.com", Duration::from_secs(5), fast_lookup);
+
+        assert_eq!(result.unwrap(), Some(IPv4::from(1)));
+    }
 }
@@ -6,6 +6,9 @@ use super::{
     prefix::Prefix,
 };
 
+/// Maximum number of addresses that can be enumerated from a single range (a /16)
+pub const MAX_ENUMERATION: u64 = 1 << 16;
+
 #[derive(Debug, Clone)]
 pub struct IPRange {
     name: String,
@@ -20,6 +23,9 @@ pub enum IPRangeError {
 
     #[error("Failed to parse IPv4 address: {0}")]
     IPv4Error(#[from] IPv4Error),
+
+    #[error("Ip range {name} has {size} addresses, enumeration is limited to {MAX_ENUMERATION}")]
+    TooLarge { name: String, size: u64 },
 }
 
 impl FromStr for IPRange {
@@ -71,6 +77,38 @@ impl IPRange {
         subnets.len() as u64
     }
 
+    /// Returns the range split into CIDR notation prefixes.
+    /// For example: 192.168.0.0-192.168.0.5 -> ["192.168.0.0/30", "192.168.0.4/31"]
+    pub fn cidrs(&self) -> Vec<String> {
+        split_ip_range_into_prefixes(&self.start, &self.end)
+            .iter()
+            .map(|prefix| prefix.get_name().to_string())
+            .collect()
+    }
+
+    /// Enumerates every address in the range, start and end included.
+    /// Ranges larger than MAX_ENUMERATION return an error.
+    pub fn addresses(&self) -> Result<impl Iterator<Item = IPv4>, IPRangeError> {
+        let size = self.end.0 - self.start.0 + 1;
+        if size > MAX_ENUMERATION {
+            return Err(IPRangeError::TooLarge {
+                name: self.name.clone(),
+                size,
+            });
+        }
+
+        let end = self.end.clone();
+        let addresses = std::iter::successors(Some(self.start.clone()), move |ip| {
+            if *ip < end {
+                Some(ip.next())
+            } else {
+                None
+            }
+        });
+
+        Ok(addresses)
+    }
+
     pub fn get_name(&self) -> &str {
         &self.name
     }
@@ -84,25 +122,25 @@ impl IPRange {
     }
 }
 
+/// Shortest mask of a prefix starting at current_ip and not going beyond end.
+/// The prefix is limited by the alignment of current_ip (trailing zero bits)
+/// and by the number of addresses left in the range.
+fn largest_prefix_mask(current_ip: &IPv4, end: &IPv4) -> u8 {
+    let current = u32::from(current_ip);
+    let remaining = u32::from(end) as u64 - current as u64 + 1;
+
+    let alignment_bits = current.trailing_zeros();
+    let size_bits = u64::BITS - 1 - remaining.leading_zeros();
+
+    (32 - alignment_bits.min(size_bits)) as u8
+}
+
 fn split_ip_range_into_prefixes(start: &IPv4, end: &IPv4) -> Vec<Prefix> {
     let mut prefixes = Vec::new();
     let mut current_ip = start.clone();
 
     while current_ip <= *end {
-        let mut mask = 0u8;
-
-        // Find the largest valid mask for the current IP range
-        while mask <= 32 {
-            let network_start = current_ip.get_network(mask);
-            let network_end = current_ip.get_broadcast(mask);
-
-            if network_start == current_ip && network_end <= *end {
-                // Valid prefix found
-                break;
-            }
-
-            mask += 1;
-        }
+        let mask = largest_prefix_mask(&current_ip, end);
 
         // Build the prefix and add it to the list
         let prefix =
@@ -319,6 +357,76 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cidrs() {
+        let ip_range = "192.168.0.0-192.168.0.5".parse::<IPRange>().unwrap();
+        assert_eq!(ip_range.cidrs(), vec!["192.168.0.0/30", "192.168.0.4/31"]);
+    }
+
+    /// Reference implementation: tries every mask from /0 to /32
+    fn split_ip_range_linear(start: &IPv4, end: &IPv4) -> Vec<String> {
+        let mut prefixes = Vec::new();
+        let mut current_ip = start.clone();
+
+        while current_ip <= *end {
+            let mask = (0..=32u8)
+                .find(|&mask| {
+                    current_ip.get_network(mask) == current_ip
+                        && current_ip.get_broadcast(mask) <= *end
+                })
+                .unwrap();
+
+            prefixes.push(format!("{}/{}", current_ip, mask));
+            current_ip = current_ip.get_broadcast(mask).next();
+        }
+
+        prefixes
+    }
+
+    #[test]
+    fn test_split_ip_range_matches_linear_search() {
+        let ranges = [
+            ("10.18.46.62", "10.18.46.69"),
+            ("0.0.0.0", "255.255.255.255"),
+            ("1.0.0.0", "254.255.255.255"),
+            ("127.255.255.255", "128.0.0.0"),
+            ("192.168.0.0", "192.168.0.0"),
+            ("10.0.0.1", "10.0.255.254"),
+            ("255.255.255.254", "255.255.255.255"),
+        ];
+
+        for (start, end) in ranges {
+            let start = start.parse::<IPv4>().unwrap();
+            let end = end.parse::<IPv4>().unwrap();
+
+            let prefixes = split_ip_range_into_prefixes(&start, &end)
+                .iter()
+                .map(|prefix| prefix.get_name().to_string())
+                .collect::<Vec<_>>();
+
+            assert_eq!(prefixes, split_ip_range_linear(&start, &end));
+        }
+    }
+
+    #[test]
+    fn test_largest_prefix_mask() {
+        let ip = |s: &str| s.parse::<IPv4>().unwrap();
+
+        assert_eq!(
+            largest_prefix_mask(&ip("0.0.0.0"), &ip("255.255.255.255")),
+            0
+        );
+        assert_eq!(
+            largest_prefix_mask(&ip("1.0.0.0"), &ip("254.255.255.255")),
+            8
+        );
+        assert_eq!(
+            largest_prefix_mask(&ip("10.0.0.1"), &ip("10.0.255.254")),
+            32
+        );
+        assert_eq!(largest_prefix_mask(&ip("10.0.0.0"), &ip("10.0.0.2")), 31);
+    }
+
     #[test]
     fn test_split_ip_range_into_prefixes_1() {
         let start = ("192.168.10.1").parse::<IPv4>().unwrap();
@@ -326,4 +434,42 @@ mod tests {
         let ip_range = split_ip_range_into_prefixes(&start, &end);
         assert_eq!(ip_range.len(), 5);
     }
+
+    #[test]
+    fn test_addresses() {
+        let ip_range = "10.0.0.254-10.0.1.2".parse::<IPRange>().unwrap();
+        let addresses: Vec<String> = ip_range
+            .addresses()
+            .unwrap()
+            .map(|ip| ip.to_string())
+            .collect();
+
+        assert_eq!(
+            addresses,
+            vec![
+                "10.0.0.254",
+                "10.0.0.255",
+                "10.0.1.0",
+                "10.0.1.1",
+                "10.0.1.2"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_addresses_single() {
+        let ip_range = "10.0.0.1-10.0.0.1".parse::<IPRange>().unwrap();
+        assert_eq!(ip_range.addresses().unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_addresses_too_large() {
+        let ip_range = "10.0.0.0-10.255.255.255".parse::<IPRange>().unwrap();
+        let result = ip_range.addresses();
+
+        assert!(matches!(
+            result,
+            Err(IPRangeError::TooLarge { size: 16777216, .. })
+        ));
+    }
 }
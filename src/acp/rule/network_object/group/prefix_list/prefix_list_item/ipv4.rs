@@ -15,6 +15,8 @@ pub enum IPv4Error {
     ParseError(#[from] std::num::ParseIntError),
 }
 
+/// Canonical dotted quad without leading zeros, e.g. 10.0.0.1
+/// Display output always parses back to the same value with from_str
 impl Display for IPv4 {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let octets = [
@@ -30,12 +32,20 @@ impl Display for IPv4 {
 impl FromStr for IPv4 {
     type Err = IPv4Error;
 
+    // Hot path of the parser: octets are parsed without collecting them into a Vec.
+    // Errors are checked in the same order as before: every part must be a number,
+    // then the number of parts, then the range of the octets
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let ip_parts: Vec<_> = s
-            .split(".")
-            .map(|s| s.parse::<u64>().map_err(IPv4Error::ParseError))
-            .collect::<Result<Vec<u64>, IPv4Error>>()?;
-        if ip_parts.len() != 4 {
+        let mut ip_parts = [0u64; 4];
+        let mut parts_count = 0;
+        for part in s.split('.') {
+            let part = part.parse::<u64>().map_err(IPv4Error::ParseError)?;
+            if let Some(ip_part) = ip_parts.get_mut(parts_count) {
+                *ip_part = part;
+            }
+            parts_count += 1;
+        }
+        if parts_count != 4 {
             return Err(IPv4Error::General(
                 format!("Invalid IP format (expected IPv4) in {}", &s).to_string(),
             ));
@@ -70,7 +80,7 @@ use core::cmp::PartialOrd;
 
 impl PartialOrd for IPv4 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.0.cmp(&other.0))
+        Some(self.cmp(other))
     }
 }
 
@@ -83,14 +93,40 @@ impl Ord for IPv4 {
 }
 
 impl IPv4 {
+    /// Last address of the network the address belongs to
+    ///
+    /// ```rust
+    /// # use ftd_acl_optimizer::acp::rule::network_object::IPv4;
+    /// let ip = "10.1.2.3".parse::<IPv4>().unwrap();
+    /// assert_eq!(ip.get_broadcast(24).to_string(), "10.1.2.255");
+    /// ```
     pub fn get_broadcast(&self, mask_length: u8) -> IPv4 {
         Self(self.0 | ((1 << (32 - mask_length)) - 1))
     }
 
+    /// First address of the network the address belongs to
+    ///
+    /// ```rust
+    /// # use ftd_acl_optimizer::acp::rule::network_object::IPv4;
+    /// let ip = "10.1.2.3".parse::<IPv4>().unwrap();
+    /// assert_eq!(ip.get_network(24).to_string(), "10.1.2.0");
+    /// ```
     pub fn get_network(&self, mask_length: u8) -> IPv4 {
         Self(self.0 & ((!0u64) << (32 - mask_length)))
     }
 
+    /// Checks if other belongs to the same network of the given mask length
+    ///
+    /// ```rust
+    /// # use ftd_acl_optimizer::acp::rule::network_object::IPv4;
+    /// let ip = "10.1.2.3".parse::<IPv4>().unwrap();
+    /// assert!(ip.contains(&"10.1.2.200".parse().unwrap(), 24));
+    /// assert!(!ip.contains(&"10.1.3.1".parse().unwrap(), 24));
+    /// ```
+    pub fn contains(&self, other: &IPv4, mask_length: u8) -> bool {
+        self.get_network(mask_length) == other.get_network(mask_length)
+    }
+
     pub fn next(&self) -> IPv4 {
         Self(self.0 + 1)
     }
@@ -100,6 +136,28 @@ impl IPv4 {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_network_and_broadcast_24() {
+        let ip = IPv4::from_str("10.1.2.3").unwrap();
+
+        assert_eq!(ip.get_network(24).to_string(), "10.1.2.0");
+        assert_eq!(ip.get_broadcast(24).to_string(), "10.1.2.255");
+        assert_eq!(ip.get_network(32), ip);
+        assert_eq!(ip.get_network(0).to_string(), "0.0.0.0");
+        assert_eq!(ip.get_broadcast(0).to_string(), "255.255.255.255");
+    }
+
+    #[test]
+    fn test_contains() {
+        let ip = IPv4::from_str("10.1.2.3").unwrap();
+
+        assert!(ip.contains(&IPv4::from_str("10.1.2.0").unwrap(), 24));
+        assert!(ip.contains(&IPv4::from_str("10.1.2.255").unwrap(), 24));
+        assert!(!ip.contains(&IPv4::from_str("10.1.3.0").unwrap(), 24));
+        assert!(ip.contains(&IPv4::from_str("10.1.3.0").unwrap(), 16));
+        assert!(ip.contains(&IPv4::from_str("192.168.0.1").unwrap(), 0));
+    }
+
     #[test]
     fn test_ipv4_from_str_valid() {
         assert_eq!("192.168.0.1".parse::<IPv4>().unwrap(), IPv4(0xC0A80001));
@@ -107,6 +165,44 @@ mod tests {
         assert_eq!("255.255.255.255".parse::<IPv4>().unwrap(), IPv4(0xFFFFFFFF));
     }
 
+    #[test]
+    fn test_ipv4_display_canonical() {
+        assert_eq!(IPv4(0x0A010001).to_string(), "10.1.0.1");
+        assert_eq!(IPv4(0).to_string(), "0.0.0.0");
+        assert_eq!(IPv4(0xFFFFFFFF).to_string(), "255.255.255.255");
+        assert_eq!(
+            "010.001.000.001".parse::<IPv4>().unwrap().to_string(),
+            "10.1.0.1"
+        );
+    }
+
+    #[test]
+    fn test_ipv4_display_from_str_round_trip() {
+        let boundaries = [
+            0u32,
+            1,
+            255,
+            256,
+            65535,
+            65536,
+            0x7FFFFFFF,
+            0x80000000,
+            u32::MAX,
+        ];
+
+        // boundaries plus a pseudo-random walk over the whole address space
+        let walk = (0..10_000u32).scan(0x12345678u32, |state, _| {
+            *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            Some(*state)
+        });
+
+        for value in boundaries.into_iter().chain(walk) {
+            let ip = IPv4::from(value);
+            let parsed = ip.to_string().parse::<IPv4>().unwrap();
+            assert_eq!(parsed, ip, "round trip failed for {}", ip);
+        }
+    }
+
     #[test]
     fn test_ipv4_from_str_invalid_format() {
         assert!("192.168.0".parse::<IPv4>().is_err());
@@ -120,6 +216,93 @@ mod tests {
         assert!("192.168.0.256".parse::<IPv4>().is_err());
     }
 
+    /// Implementation before the allocation-free parser, kept as the reference behavior
+    fn from_str_with_vec(s: &str) -> Result<IPv4, IPv4Error> {
+        let ip_parts: Vec<_> = s
+            .split(".")
+            .map(|s| s.parse::<u64>().map_err(IPv4Error::ParseError))
+            .collect::<Result<Vec<u64>, IPv4Error>>()?;
+        if ip_parts.len() != 4 {
+            return Err(IPv4Error::General(
+                format!("Invalid IP format (expected IPv4) in {}", &s).to_string(),
+            ));
+        }
+
+        for &part in &ip_parts {
+            if part > 255 {
+                return Err(IPv4Error::General(
+                    format!("IP parts must be in the range 0-255 in {}", &s).to_string(),
+                ));
+            }
+        }
+
+        let ip = (ip_parts[0] << 24) | (ip_parts[1] << 16) | (ip_parts[2] << 8) | ip_parts[3];
+        Ok(IPv4(ip))
+    }
+
+    #[test]
+    fn test_ipv4_from_str_error_messages() {
+        let cases = [
+            "192.168.0.1",
+            "192.168.0",
+            "192.168.0.1.1",
+            "192.168.0.1.x",
+            "192.168.0.abc",
+            "256.168.0.1",
+            "192.168.0.256",
+            "",
+            "...",
+            "1.2.3.4.",
+            "-1.2.3.4",
+            "99999999999999999999.0.0.0",
+        ];
+
+        for case in cases {
+            let new = IPv4::from_str(case).map_err(|e| e.to_string());
+            let old = from_str_with_vec(case).map_err(|e| e.to_string());
+            assert_eq!(new, old, "different result for {:?}", case);
+        }
+
+        assert_eq!(
+            IPv4::from_str("192.168.0").unwrap_err().to_string(),
+            "Fail to parse ipv4 address: Invalid IP format (expected IPv4) in 192.168.0"
+        );
+        assert_eq!(
+            IPv4::from_str("256.168.0.1").unwrap_err().to_string(),
+            "Fail to parse ipv4 address: IP parts must be in the range 0-255 in 256.168.0.1"
+        );
+        assert_eq!(
+            IPv4::from_str("192.168.0.abc").unwrap_err().to_string(),
+            "Failed to parse IPv4 address: invalid digit found in string"
+        );
+    }
+
+    /// cargo test --release bench_ipv4_from_str -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_ipv4_from_str() {
+        let addresses = (0..1_000_000u32)
+            .map(|i| IPv4::from(i.wrapping_mul(2_654_435_761)).to_string())
+            .collect::<Vec<_>>();
+
+        let start = std::time::Instant::now();
+        let old = addresses
+            .iter()
+            .map(|a| from_str_with_vec(a).unwrap().0)
+            .fold(0, u64::wrapping_add);
+        let old_elapsed = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let new = addresses
+            .iter()
+            .map(|a| IPv4::from_str(a).unwrap().0)
+            .fold(0, u64::wrapping_add);
+        let new_elapsed = start.elapsed();
+
+        assert_eq!(old, new);
+        println!("1M parses: with Vec {old_elapsed:?}, allocation-free {new_elapsed:?}");
+    }
+
     #[test]
     fn test_ipv4_ordering() {
         let ip1 = "192.168.0.1".parse::<IPv4>().unwrap();
@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-mod prefix;
+pub mod prefix;
 use prefix::Prefix;
 
 pub mod ip_range;
@@ -35,6 +35,9 @@ pub enum PrefixListItemError {
     #[error("Unknown type of prefix list item: {0}")]
     UnknownType(String),
 
+    #[error("IPv6 is not supported, only IPv4 entries can be optimized: {0}")]
+    IPv6NotSupported(String),
+
     #[error("Empty line")]
     EmptyLine,
 }
@@ -46,13 +49,20 @@ impl FromStr for PrefixListItem {
     // 10.0.0.0/8
     // or
     // 10.11.12.13-10.11.12.18
+    // or
+    // 10.11.12.13 (always a /32 prefix, never a hostname)
     fn from_str(line: &str) -> Result<Self, Self::Err> {
-        if is_ip_range(line) {
+        if is_dotted_quad(line) {
+            let prefix = line.parse::<Prefix>()?;
+            Ok(PrefixListItem::Prefix(prefix))
+        } else if is_ip_range(line) {
             let ip_range = line.parse::<IPRange>()?;
             Ok(PrefixListItem::IPRange(ip_range))
         } else if is_ip_prefix(line) {
             let prefix = line.parse::<Prefix>()?;
             Ok(PrefixListItem::Prefix(prefix))
+        } else if is_ipv6(line) {
+            Err(PrefixListItemError::IPv6NotSupported(line.to_string()))
         } else if is_hostname(line) {
             let hostname = line.parse::<Hostname>()?;
             Ok(PrefixListItem::Hostname(hostname))
@@ -73,6 +83,31 @@ impl PrefixListItem {
         }
     }
 
+    /// False for hostnames left unresolved when DNS is disabled
+    pub fn is_resolved(&self) -> bool {
+        match self {
+            PrefixListItem::Hostname(hostname) => hostname.is_resolved(),
+            _ => true,
+        }
+    }
+
+    /// Single host written without mask, e.g. "OBJ-host (10.1.1.1)".
+    /// Same capacity as a /32 prefix, but reported as a host.
+    pub fn is_host(&self) -> bool {
+        match self {
+            PrefixListItem::Prefix(prefix) => prefix.is_host(),
+            _ => false,
+        }
+    }
+
+    /// False for prefixes with host bits set, ranges and hostnames are always canonical
+    pub fn is_canonical(&self) -> bool {
+        match self {
+            PrefixListItem::Prefix(prefix) => prefix.is_canonical(),
+            _ => true,
+        }
+    }
+
     pub fn get_name(&self) -> &str {
         match self {
             PrefixListItem::Prefix(prefix) => prefix.get_name(),
@@ -110,6 +145,10 @@ fn is_ip_range(line: impl AsRef<str>) -> bool {
 fn is_ip_prefix(line: impl AsRef<str>) -> bool {
     let line = line.as_ref();
 
+    if is_ip_netmask(line) {
+        return true;
+    }
+
     let condition1 = line
         .chars()
         .all(|c| c.is_ascii_digit() || c == '.' || c == '/')
@@ -129,10 +168,38 @@ fn is_ip_prefix(line: impl AsRef<str>) -> bool {
     condition1
 }
 
+/// Address with a dotted-decimal netmask, e.g. 10.0.0.0/255.0.0.0 or 10.0.0.0 255.0.0.0
+fn is_ip_netmask(line: &str) -> bool {
+    line.split_once(['/', ' '])
+        .is_some_and(|(address, mask)| is_dotted_quad(address) && is_dotted_quad(mask.trim()))
+}
+
+/// IPv6 address, prefix (2001:db8::/32) or range (2001:db8::1-2001:db8::ff)
+fn is_ipv6(line: impl AsRef<str>) -> bool {
+    let address = line.as_ref().split(['/', '-']).next().unwrap_or_default();
+
+    address.parse::<std::net::Ipv6Addr>().is_ok()
+}
+
+/// Bare host address without mask, e.g. 10.11.12.13
+fn is_dotted_quad(line: impl AsRef<str>) -> bool {
+    line.as_ref().parse::<std::net::Ipv4Addr>().is_ok()
+}
+
+/// Dotted-quad addresses are never treated as hostnames to avoid resolving them.
+/// Same for names with a numeric last label ("53", "10.1"): the resolver would
+/// read them as a shorthand address (0.0.0.53, 10.0.0.1), a top-level domain is never numeric.
+/// A fully qualified name may end with the root dot ("example.com.")
 fn is_hostname(line: impl AsRef<str>) -> bool {
     let line = line.as_ref();
 
-    if line.is_empty() {
+    if line.is_empty() || is_dotted_quad(line) {
+        return false;
+    }
+
+    let name = line.strip_suffix('.').unwrap_or(line);
+    let last_label = name.rsplit('.').next().unwrap_or_default();
+    if last_label.is_empty() || last_label.chars().all(|c| c.is_ascii_digit()) {
         return false;
     }
 
@@ -156,6 +223,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_prefix_list_item_from_str_netmask() {
+        for input in ["10.0.0.0/255.255.255.0", "10.0.0.0 255.255.255.0"] {
+            let item = PrefixListItem::from_str(input).unwrap();
+            assert!(matches!(item, PrefixListItem::Prefix(_)));
+            assert_eq!(item.end_ip().to_string(), "10.0.0.255");
+        }
+        assert!(PrefixListItem::from_str("10.0.0.0/255.0.255.0").is_err());
+    }
+
     #[test]
     fn test_prefix_list_item_from_str_ip_range() {
         let input = "10.11.12.13-10.11.12.18";
@@ -168,6 +245,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_prefix_list_item_from_str_ipv6() {
+        for input in [
+            "2001:db8::/32",
+            "2001:db8::1",
+            "2001:db8::1-2001:db8::ff",
+            "::/0",
+        ] {
+            let result = PrefixListItem::from_str(input);
+            assert!(
+                matches!(result, Err(PrefixListItemError::IPv6NotSupported(ref item)) if item == input),
+                "{input}"
+            );
+        }
+
+        assert!(!is_ipv6("web-1.example.com"));
+        assert!(!is_ipv6("10.0.0.0/8"));
+    }
+
     #[test]
     fn test_prefix_list_item_from_str_invalid() {
         let input = "invalid";
@@ -216,10 +312,72 @@ mod tests {
         assert!(!is_ip_prefix(""));
     }
 
+    #[test]
+    fn test_is_dotted_quad() {
+        assert!(is_dotted_quad("10.11.12.13"));
+        assert!(is_dotted_quad("0.0.0.0"));
+        assert!(!is_dotted_quad("10.11.12.13/32"));
+        assert!(!is_dotted_quad("10.11.12.256"));
+        assert!(!is_dotted_quad("10.11.12"));
+        assert!(!is_dotted_quad("host.name"));
+    }
+
+    #[test]
+    fn test_prefix_list_item_from_str_bare_host_is_prefix() {
+        let item = PrefixListItem::from_str("10.11.12.13").unwrap();
+
+        match item {
+            PrefixListItem::Prefix(prefix) => {
+                assert_eq!(prefix.start_ip().to_string(), "10.11.12.13");
+                assert_eq!(prefix.end_ip().to_string(), "10.11.12.13");
+            }
+            _ => panic!("Expected Prefix, got {:?}", item),
+        }
+    }
+
+    #[test]
+    fn test_is_host() {
+        let host = PrefixListItem::from_str("10.1.1.1").unwrap();
+        let slash_32 = PrefixListItem::from_str("10.1.1.0/32").unwrap();
+        let range = PrefixListItem::from_str("10.1.1.1-10.1.1.1").unwrap();
+
+        assert_eq!(host.capacity(), 1);
+        assert_eq!(slash_32.capacity(), 1);
+        assert!(host.is_host());
+        assert!(!slash_32.is_host());
+        assert!(!range.is_host());
+    }
+
+    #[test]
+    fn test_prefix_list_item_from_str_numeric() {
+        for input in ["53", "10.1", "host.53"] {
+            assert!(!is_ip_prefix(input));
+            assert!(!is_ip_range(input));
+            assert!(!is_hostname(input));
+            assert!(matches!(
+                PrefixListItem::from_str(input),
+                Err(PrefixListItemError::UnknownType(_))
+            ));
+        }
+
+        assert!(is_hostname("53.example.com"));
+        assert!(is_hostname("2024-host"));
+    }
+
+    #[test]
+    fn test_is_hostname_trailing_dot() {
+        assert!(is_hostname("example.com."));
+        assert!(is_hostname("host."));
+        assert!(!is_hostname("."));
+        assert!(!is_hostname("example.com.."));
+        assert!(!is_hostname("53."));
+        assert!(!is_hostname("10.1."));
+    }
+
     #[test]
     fn test_is_hostname() {
         assert!(is_hostname("hostname"));
-        assert!(is_hostname("10.11.12.13"));
+        assert!(!is_hostname("10.11.12.13"));
         assert!(is_hostname("host-name"));
         assert!(is_hostname("host.name"));
         assert!(is_hostname("host123.name.com"));
@@ -2,11 +2,15 @@ use std::str::FromStr;
 
 use super::ipv4::{IPv4, IPv4Error};
 
+/// host - written as a bare address without mask (e.g. "10.1.1.1"), otherwise same as /32
+/// canonical - false if host bits are set (e.g. "10.1.2.3/24"), the prefix is still usable
 #[derive(Debug, Clone)]
 pub struct Prefix {
     name: String,
     start: IPv4,
     end: IPv4,
+    host: bool,
+    canonical: bool,
 }
 
 pub struct Builder {
@@ -28,13 +32,22 @@ pub enum PrefixError {
 impl FromStr for Prefix {
     type Err = PrefixError;
 
+    // Example:
+    // 10.0.0.0/8
+    // 10.0.0.0/255.0.0.0
+    // 10.0.0.0 255.0.0.0
+    // 10.11.12.13
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let name = String::from(s);
-        let parts: Vec<_> = s.split("/").collect();
+        let parts: Vec<_> = match s.split_once(' ') {
+            // the mask is always dotted-decimal if separated by a space
+            Some((address, mask)) if mask.contains('.') => vec![address, mask.trim()],
+            _ => s.split("/").collect(),
+        };
         match parts.len() {
             2 => {
                 let start = parts[0].parse::<IPv4>()?;
-                let mask_length: u8 = parts[1].parse()?;
+                let mask_length = parse_mask_length(parts[1], &name)?;
                 if !(0..=32).contains(&mask_length) {
                     return Err(PrefixError::General(
                         format!(
@@ -45,13 +58,26 @@ impl FromStr for Prefix {
                     ));
                 }
                 let end = start.get_broadcast(mask_length);
-                Ok(Prefix { name, start, end })
+                let canonical = start == start.get_network(mask_length);
+                Ok(Prefix {
+                    name,
+                    start,
+                    end,
+                    host: false,
+                    canonical,
+                })
             }
             1 => {
                 let start = parts[0].parse::<IPv4>()?;
                 let mask_length = 32;
                 let end = start.get_broadcast(mask_length);
-                Ok(Prefix { name, start, end })
+                Ok(Prefix {
+                    name,
+                    start,
+                    end,
+                    host: true,
+                    canonical: true,
+                })
             }
             _ => Err(PrefixError::General(
                 format!(
@@ -64,6 +90,24 @@ impl FromStr for Prefix {
     }
 }
 
+/// Mask length given as a number ("24") or as a dotted-decimal netmask ("255.255.255.0").
+/// A netmask must be contiguous: ones followed by zeros
+fn parse_mask_length(mask: &str, name: &str) -> Result<u8, PrefixError> {
+    if !mask.contains('.') {
+        return Ok(mask.parse()?);
+    }
+
+    let mask = u32::from(&mask.parse::<IPv4>()?);
+    if mask.leading_ones() + mask.trailing_zeros() != 32 {
+        return Err(PrefixError::General(format!(
+            "Non-contiguous netmask in {}.",
+            name
+        )));
+    }
+
+    Ok(mask.leading_ones() as u8)
+}
+
 impl Prefix {
     pub fn capacity(&self) -> u64 {
         1
@@ -73,6 +117,16 @@ impl Prefix {
         &self.name
     }
 
+    pub fn is_host(&self) -> bool {
+        self.host
+    }
+
+    /// False if the address has host bits set for the mask, e.g. "10.1.2.3/24".
+    /// Often a typo in the object, FMC accepts it and matches the whole network
+    pub fn is_canonical(&self) -> bool {
+        self.canonical
+    }
+
     pub fn start_ip(&self) -> &IPv4 {
         &self.start
     }
@@ -97,6 +151,8 @@ impl Builder {
             name: self.name,
             start: self.start,
             end,
+            host: false,
+            canonical: true,
         }
     }
 }
@@ -127,6 +183,20 @@ mod tests {
         assert_eq!(prefix.end.0, 0xC0A80000);
     }
 
+    #[test]
+    fn test_canonical_prefix() {
+        let clean = "10.1.2.0/24".parse::<Prefix>().unwrap();
+        assert!(clean.is_canonical());
+
+        let flagged = "10.1.2.3/24".parse::<Prefix>().unwrap();
+        assert!(!flagged.is_canonical());
+        assert_eq!(flagged.capacity(), 1);
+        assert_eq!(flagged.end_ip(), clean.end_ip());
+
+        let host = "10.1.2.3".parse::<Prefix>().unwrap();
+        assert!(host.is_canonical());
+    }
+
     #[test]
     fn test_invalid_prefix_format() {
         let prefix_str = "192.168.0.0-24";
@@ -205,6 +275,33 @@ mod tests {
         assert_eq!(prefix.end.0, 0x0A000000);
     }
 
+    #[test]
+    fn test_prefix_with_netmask() {
+        let prefix = "10.0.0.0/255.255.255.0".parse::<Prefix>().unwrap();
+        assert_eq!(prefix.start.0, 0x0A000000);
+        assert_eq!(prefix.end.0, 0x0A0000FF);
+        assert_eq!(prefix.get_name(), "10.0.0.0/255.255.255.0");
+
+        let prefix = "10.0.0.0 255.0.0.0".parse::<Prefix>().unwrap();
+        assert_eq!(prefix.end.0, 0x0AFFFFFF);
+        assert!(prefix.is_canonical());
+
+        let prefix = "0.0.0.0/0.0.0.0".parse::<Prefix>().unwrap();
+        assert_eq!(prefix.end.0, 0xFFFFFFFF);
+        let prefix = "10.1.1.1/255.255.255.255".parse::<Prefix>().unwrap();
+        assert_eq!(prefix.end.0, 0x0A010101);
+    }
+
+    #[test]
+    fn test_prefix_with_non_contiguous_netmask() {
+        let prefix = "10.0.0.0/255.0.255.0".parse::<Prefix>();
+        assert_eq!(
+            format!("{}", prefix.unwrap_err()),
+            "Fail to parse prefix: Non-contiguous netmask in 10.0.0.0/255.0.255.0."
+        );
+        assert!("10.0.0.0 24".parse::<Prefix>().is_err());
+    }
+
     #[test]
     fn test_prefix_default() {
         let prefix_str = "0.0.0.0/0";
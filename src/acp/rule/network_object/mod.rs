@@ -14,12 +14,19 @@ use network_object_item::NetworkObjectItem;
 mod prefix_list_item_optimized;
 use prefix_list_item_optimized::PrefixListItemOptimized;
 
-use group::prefix_list::prefix_list_item::PrefixListItem;
+pub use group::prefix_list::prefix_list_item::PrefixListItem;
+
+pub use group::prefix_list::prefix_list_item::hostname;
+pub use group::prefix_list::prefix_list_item::ipv4::IPv4;
+pub use group::prefix_list::prefix_list_item::prefix::{self, Prefix};
 
 pub mod network_object_optimized;
 use network_object_optimized::NetworkObjectOptimized;
 
-#[derive(Debug)]
+use super::explanation::{self, OptimizationStep, Overlap, SectionExplanation};
+use super::protocol_object::description;
+
+#[derive(Debug, Clone)]
 pub struct NetworkObject {
     name: String,
     items: Vec<NetworkObjectItem>,
@@ -81,7 +88,7 @@ fn get_object(lines: &[String]) -> Result<(NetworkObjectItem, usize), NetworkObj
 
     let first_line = lines[0].as_str();
     if first_line.contains("(group)") {
-        let lines_in_group = utilities::calculate_lines_in_group(lines)?;
+        let lines_in_group = utilities::calculate_lines_in_nested_group(lines)?;
         let group = Group::try_from(&lines[0..lines_in_group].to_vec())?;
         Ok((NetworkObjectItem::ObjectGroup(group), lines_in_group))
     } else {
@@ -96,24 +103,153 @@ impl NetworkObject {
     }
 
     pub fn optimize(&self) -> NetworkObjectOptimized {
+        let merged_items = optimize_prefixes(self.prefix_list_items(), &mut vec![]);
+
+        network_object_optimized::Builder::new(merged_items)
+            .with_name(self.name.clone())
+            .build()
+    }
+
+    /// Optimizes the network object and reports every merge that reduced the capacity
+    pub fn explain(&self) -> SectionExplanation {
+        let mut steps = vec![];
+        let merged_items = optimize_prefixes(self.prefix_list_items(), &mut steps);
+
+        let optimized = network_object_optimized::Builder::new(merged_items)
+            .with_name(self.name.clone())
+            .build();
+
+        SectionExplanation {
+            name: self.name.clone(),
+            capacity: self.capacity(),
+            optimized_capacity: optimized.capacity(),
+            steps,
+        }
+    }
+
+    /// Merges items of both network objects and optimizes the union.
+    /// Used to estimate the result of merging two rules into one.
+    pub fn union(&self, other: &NetworkObject) -> NetworkObjectOptimized {
         let items = self
-            .items
-            .iter()
-            .flat_map(|net_obj| net_obj.get_prefix_lists())
-            .flat_map(|prefix_list| prefix_list.get_items())
+            .prefix_list_items()
+            .into_iter()
+            .chain(other.prefix_list_items())
             .collect::<Vec<_>>();
 
-        let merged_items = optimize_prefixes(items);
+        let merged_items = optimize_prefixes(items, &mut vec![]);
 
         network_object_optimized::Builder::new(merged_items)
-            .with_name(self.name.clone())
+            .with_name(format!("{} + {}", self.name, other.name))
             .build()
     }
+
+    /// Smallest single prefix covering every address of the network object,
+    /// for example 10.0.0.0/24 and 10.0.3.0/24 are covered by 10.0.0.0/22.
+    /// None if the object has no items.
+    pub fn bounding_prefix(&self) -> Option<Prefix> {
+        let items = self.prefix_list_items();
+        let start = items.iter().map(|item| item.start_ip()).min()?;
+        let end = items.iter().map(|item| item.end_ip()).max()?;
+
+        // longest mask that puts start and end into the same network
+        let mask_length = (0..=32u8)
+            .rev()
+            .find(|&mask| start.contains(end, mask))
+            .unwrap_or(0);
+
+        let network = start.get_network(mask_length);
+        Some(
+            prefix::Builder::new(format!("{}/{}", network, mask_length), network, mask_length)
+                .build(),
+        )
+    }
+
+    /// Matches every address, some entry is 0.0.0.0/0 or a range covering the whole address space
+    pub fn is_any(&self) -> bool {
+        self.prefix_list_items()
+            .iter()
+            .any(|item| u32::from(item.start_ip()) == 0 && u32::from(item.end_ip()) == u32::MAX)
+    }
+
+    /// Every pair of entries sharing at least one address, before the optimization merges them.
+    /// Adjoining entries (10.0.0.0/24 and 10.0.1.0/24) don't overlap
+    pub fn overlaps(&self) -> Vec<Overlap> {
+        let mut sorted = self.prefix_list_items();
+        sorted.sort_by_key(|item| item.start_ip());
+
+        let mut overlaps = vec![];
+        for (idx, item) in sorted.iter().enumerate() {
+            let overlapping = sorted[idx + 1..]
+                .iter()
+                .take_while(|next| next.start_ip() <= item.end_ip());
+
+            for next in overlapping {
+                let verb = description::verb(
+                    item.end_ip().into(),
+                    next.start_ip().into(),
+                    next.end_ip().into(),
+                );
+
+                overlaps.push(Overlap {
+                    section: self.name.clone(),
+                    first: item.get_name().to_string(),
+                    verb,
+                    second: next.get_name().to_string(),
+                });
+            }
+        }
+
+        overlaps
+    }
+
+    /// Prefixes, ranges and hosts of all groups and prefix lists flattened into one list.
+    /// Unresolved hostnames have no address and are left out of the optimization
+    pub fn prefix_list_items(&self) -> Vec<&PrefixListItem> {
+        self.items
+            .iter()
+            .flat_map(|net_obj| net_obj.get_prefix_lists())
+            .flat_map(|prefix_list| prefix_list.get_items())
+            .filter(|item| item.is_resolved())
+            .collect()
+    }
+
+    /// Top level objects and groups with the number of addresses they match,
+    /// overlapping entries of a group are counted once, unresolved hostnames match nothing
+    pub fn object_address_spans(&self) -> Vec<(&str, u64)> {
+        self.items
+            .iter()
+            .map(|item| {
+                let entries = item
+                    .get_prefix_lists()
+                    .into_iter()
+                    .flat_map(|prefix_list| prefix_list.get_items())
+                    .filter(|entry| entry.is_resolved())
+                    .collect();
+                let span = optimize_prefixes(entries, &mut vec![])
+                    .iter()
+                    .fold(0u64, |span, merged| {
+                        span.saturating_add(merged.address_span())
+                    });
+
+                (item.get_name(), span)
+            })
+            .collect()
+    }
 }
 
-fn optimize_prefixes(items: Vec<&PrefixListItem>) -> Vec<PrefixListItemOptimized> {
+/// Merges sorted items, merges kept in the result are recorded into steps
+fn optimize_prefixes(
+    items: Vec<&PrefixListItem>,
+    steps: &mut Vec<OptimizationStep>,
+) -> Vec<PrefixListItemOptimized> {
     let mut sorted = items;
-    sorted.sort_by_key(|item| item.start_ip());
+    // entries with the same start are ordered by the end and the name, so the merged name is stable
+    sorted.sort_by(|a, b| {
+        a.start_ip()
+            .cmp(b.start_ip())
+            .then_with(|| a.end_ip().cmp(b.end_ip()))
+            .then_with(|| explanation::name_order(a.get_name(), b.get_name()))
+    });
 
     let mut result = vec![];
 
@@ -122,26 +258,41 @@ fn optimize_prefixes(items: Vec<&PrefixListItem>) -> Vec<PrefixListItemOptimized
     }
 
     let mut optimized_item = PrefixListItemOptimized::from(sorted[0]);
+    let mut pending_steps = vec![];
 
     for next_item in sorted.into_iter().skip(1) {
         let curr_end = optimized_item.end_ip();
         let (next_start, next_end) = (next_item.start_ip(), next_item.end_ip());
 
         if next_start <= &curr_end.next() {
-            use super::protocol_object::description;
             let verb = description::verb(curr_end.into(), next_start.into(), next_end.into());
 
-            let new_name = format!("{} {verb} {}", optimized_item.name(), next_item.get_name());
+            let merged = optimized_item.name().to_string();
+            let new_name = format!("{merged} {verb} {}", next_item.get_name());
             optimized_item.set_name(new_name);
 
             optimized_item.append(next_item);
+
+            pending_steps.push(OptimizationStep {
+                merged,
+                verb,
+                next: next_item.get_name().to_string(),
+                capacity: optimized_item.capacity(),
+            });
         } else {
+            if optimized_item.is_optimized() {
+                steps.append(&mut pending_steps);
+            }
+            pending_steps.clear();
             result = push_items_to_vec(result, optimized_item);
 
             optimized_item = PrefixListItemOptimized::from(next_item);
         }
     }
 
+    if optimized_item.is_optimized() {
+        steps.append(&mut pending_steps);
+    }
     result = push_items_to_vec(result, optimized_item);
 
     result
@@ -240,6 +391,22 @@ mod tests {
         assert_eq!(result.items.len(), 3);
     }
 
+    #[test]
+    fn test_try_from_tab_indented() {
+        let lines = vec![
+            "\tSource Networks       : Internal (group)".to_string(),
+            "\t\t\tOBJ-157.121.0.0 (157.121.0.0/16)".to_string(),
+            "\t\t\t10.0.0.0/8".to_string(),
+            "\t\t\t172.16.0.0/12".to_string(),
+            "\t\tOBJ-192.168.243.0_24 (192.168.243.0/24)".to_string(),
+            "\t\tOBJ-10.18.46.62-69 (10.18.46.62-10.18.46.69)".to_string(),
+        ];
+        let result = NetworkObject::try_from(&lines).unwrap();
+        assert_eq!(result.name, "Source Networks");
+        assert_eq!(result.items.len(), 3);
+        assert_eq!(result.capacity(), 3 + 1 + 3);
+    }
+
     #[test]
     fn test_try_from2() {
         let lines = vec![
@@ -314,6 +481,59 @@ mod tests {
         assert_eq!(result.items.len(), 7);
     }
 
+    #[test]
+    fn test_try_from_nested_group() {
+        let lines = vec![
+            "    Source Networks       : Internal (group)".to_string(),
+            "                              10.0.0.0/8".to_string(),
+            "                              DC (group)".to_string(),
+            "                                172.16.0.0/12".to_string(),
+            "                                192.168.0.0/16".to_string(),
+            "                            OBJ-10.18.46.62-69 (10.18.46.62-10.18.46.69)".to_string(),
+        ];
+        let result = NetworkObject::try_from(&lines).unwrap();
+        assert_eq!(result.items.len(), 2);
+        assert_eq!(result.capacity(), 3 + 3);
+    }
+
+    #[test]
+    fn test_try_from_nested_group_too_deep() {
+        let lines = vec![
+            "    Source Networks       : Internal (group)".to_string(),
+            "                              10.0.0.0/8".to_string(),
+            "                              DC (group)".to_string(),
+            "                                172.16.0.0/12".to_string(),
+            "                                Lab (group)".to_string(),
+            "                                  192.168.0.0/16".to_string(),
+        ];
+        assert_eq!(NetworkObject::try_from(&lines).unwrap().capacity(), 3);
+
+        utilities::set_max_group_depth(2);
+        let result = NetworkObject::try_from(&lines);
+        utilities::set_max_group_depth(utilities::DEFAULT_MAX_GROUP_DEPTH);
+
+        assert!(matches!(
+            result,
+            Err(NetworkObjectError::GroupError(
+                group::GroupError::NestingTooDeep(_, 2)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_try_from_group_with_object_count_annotation() {
+        let lines = vec![
+            "    Source Networks       : Internal (group) [12 objects]".to_string(),
+            "  OBJ-157.121.0.0 (157.121.0.0/16)".to_string(),
+            "  OBJ-206.213.0.0 (206.213.0.0/16)".to_string(),
+            "OBJ-192.168.243.0_24 (192.168.243.0/24)".to_string(),
+        ];
+        let result = NetworkObject::try_from(&lines).unwrap();
+        assert_eq!(result.name, "Source Networks");
+        assert_eq!(result.items.len(), 2);
+        assert_eq!(result.capacity(), 3);
+    }
+
     #[test]
     fn test_network_object_capacity_single_prefix_list() {
         let lines = vec![
@@ -346,6 +566,26 @@ mod tests {
         assert_eq!(network_object.capacity(), 5);
     }
 
+    #[test]
+    fn test_top_level_literal_ip_range() {
+        let lines = vec![
+            "Source Networks       : 10.0.0.1-10.0.0.9".to_string(),
+            "OBJ-10.1.1.0_24 (10.1.1.0/24)".to_string(),
+        ];
+        let network_object = NetworkObject::try_from(&lines).unwrap();
+
+        assert_eq!(network_object.items.len(), 2);
+        assert!(matches!(
+            network_object.items[0],
+            NetworkObjectItem::PrefixList(_)
+        ));
+        let items = network_object.prefix_list_items();
+        assert!(matches!(items[0], PrefixListItem::IPRange(_)));
+        // 10.0.0.1/32, 10.0.0.2/31, 10.0.0.4/30, 10.0.0.8/31 -> 10.0.0.1-10.0.0.9 needs 4 prefixes
+        assert_eq!(items[0].capacity(), 4);
+        assert_eq!(network_object.capacity(), 4 + 1);
+    }
+
     #[test]
     fn test_network_object_capacity_empty() {
         let lines = vec!["Source Networks       : Internal (group)".to_string()];
@@ -481,6 +721,55 @@ mod tests {
         assert_eq!(optimized.capacity(), 0);
     }
 
+    #[test]
+    fn test_optimized_contains_inside_merged_prefix() {
+        let lines = vec![
+            "Source Networks       : Internal (group)".to_string(),
+            "  10.11.12.0/24".to_string(),
+            "  10.11.13.0/24".to_string(),
+        ];
+        let optimized = NetworkObject::try_from(&lines).unwrap().optimize();
+        assert_eq!(optimized.capacity(), 1);
+        assert!(optimized.contains(&IPv4::from_str("10.11.12.13").unwrap()));
+        assert!(optimized.contains(&IPv4::from_str("10.11.13.255").unwrap()));
+    }
+
+    #[test]
+    fn test_optimized_address_span() {
+        let lines = vec![
+            "Source Networks       : Internal (group)".to_string(),
+            "  10.11.12.0/25".to_string(),
+            "  10.11.12.128/25".to_string(),
+        ];
+        let optimized = NetworkObject::try_from(&lines).unwrap().optimize();
+        assert_eq!(optimized.capacity(), 1);
+        assert_eq!(optimized.address_span(), 256);
+
+        let lines = vec![
+            "Source Networks       : 10.0.0.1".to_string(),
+            "10.0.0.5".to_string(),
+        ];
+        let optimized = NetworkObject::try_from(&lines).unwrap().optimize();
+        assert_eq!(optimized.capacity(), 2);
+        assert_eq!(optimized.address_span(), 2);
+
+        let lines = vec!["Source Networks       : 0.0.0.0/0".to_string()];
+        let optimized = NetworkObject::try_from(&lines).unwrap().optimize();
+        assert_eq!(optimized.address_span(), 1 << 32);
+    }
+
+    #[test]
+    fn test_optimized_contains_outside_merged_prefix() {
+        let lines = vec![
+            "Source Networks       : Internal (group)".to_string(),
+            "  10.11.12.0/24".to_string(),
+            "  10.11.13.0/24".to_string(),
+        ];
+        let optimized = NetworkObject::try_from(&lines).unwrap().optimize();
+        assert!(!optimized.contains(&IPv4::from_str("10.11.14.0").unwrap()));
+        assert!(!optimized.contains(&IPv4::from_str("10.11.11.255").unwrap()));
+    }
+
     #[test]
     fn optimize_prefixes_1() {
         let lines = vec![
@@ -523,4 +812,199 @@ mod tests {
         assert_eq!(optimized.items().len(), 1);
         assert_eq!(optimized.capacity(), 2);
     }
+
+    #[test]
+    fn optimize_single_ip_ranges_adjoin() {
+        let lines = vec![
+            "Source Networks       : Internal (group)".to_string(),
+            "  192.168.0.1-192.168.0.1".to_string(),
+            "  192.168.0.0-192.168.0.0".to_string(),
+        ];
+        let network_object = NetworkObject::try_from(&lines).unwrap();
+        assert_eq!(network_object.capacity(), 2);
+
+        let optimized = network_object.optimize();
+        assert_eq!(optimized.items().len(), 1);
+        assert_eq!(
+            optimized.items()[0].name(),
+            "192.168.0.0-192.168.0.0 ADJOINS 192.168.0.1-192.168.0.1"
+        );
+        assert_eq!(optimized.cidrs(), vec!["192.168.0.0/31".to_string()]);
+        assert_eq!(optimized.capacity(), 1);
+    }
+
+    #[test]
+    fn optimize_names_stable_across_orderings() {
+        // same range written as a prefix and as a range
+        let entries = ["10.0.0.0/24", "10.0.0.0-10.0.0.255", "10.0.1.0/24"];
+        let name = |entries: Vec<&str>| {
+            let lines: Vec<String> = std::iter::once("Source Networks       : Internal (group)")
+                .chain(entries)
+                .map(|line| format!("  {}", line))
+                .collect();
+            let optimized = NetworkObject::try_from(&lines).unwrap().optimize();
+            optimized.items()[0].name().to_string()
+        };
+
+        let forward = name(entries.to_vec());
+        let backward = name(entries.iter().rev().copied().collect());
+
+        assert_eq!(forward, backward);
+        assert_eq!(
+            forward,
+            "10.0.0.0-10.0.0.255 SHADOWS 10.0.0.0/24 ADJOINS 10.0.1.0/24"
+        );
+    }
+
+    #[test]
+    fn optimize_identical_single_ip_ranges() {
+        let lines = vec![
+            "Source Networks       : Internal (group)".to_string(),
+            "  192.168.0.0-192.168.0.0".to_string(),
+            "  192.168.0.0-192.168.0.0".to_string(),
+        ];
+        let network_object = NetworkObject::try_from(&lines).unwrap();
+        assert_eq!(network_object.capacity(), 2);
+
+        let optimized = network_object.optimize();
+        assert_eq!(optimized.items().len(), 1);
+        assert_eq!(
+            optimized.items()[0].name(),
+            "192.168.0.0-192.168.0.0 SHADOWS 192.168.0.0-192.168.0.0"
+        );
+        assert_eq!(optimized.capacity(), 1);
+    }
+
+    #[test]
+    fn union_collapses_adjacent_halves() {
+        let lines1 = vec!["Source Networks       : 10.0.0.0/9".to_string()];
+        let lines2 = vec!["Source Networks       : 10.128.0.0/9".to_string()];
+        let network_object1 = NetworkObject::try_from(&lines1).unwrap();
+        let network_object2 = NetworkObject::try_from(&lines2).unwrap();
+
+        let optimized = network_object1.union(&network_object2);
+        assert_eq!(optimized.items().len(), 1);
+        assert_eq!(optimized.capacity(), 1);
+        assert_eq!(optimized.cidrs(), vec!["10.0.0.0/8".to_string()]);
+    }
+
+    #[test]
+    fn bounding_prefix_22() {
+        let lines = vec![
+            "Source Networks       : 10.0.0.0/24".to_string(),
+            "10.0.3.0-10.0.3.255".to_string(),
+            "10.0.1.17".to_string(),
+        ];
+        let network_object = NetworkObject::try_from(&lines).unwrap();
+
+        let prefix = network_object.bounding_prefix().unwrap();
+        assert_eq!(prefix.get_name(), "10.0.0.0/22");
+        assert_eq!(prefix.start_ip().to_string(), "10.0.0.0");
+        assert_eq!(prefix.end_ip().to_string(), "10.0.3.255");
+    }
+
+    #[test]
+    fn bounding_prefix_two_slash_8() {
+        let lines = vec![
+            "Source Networks       : 10.0.0.0/8".to_string(),
+            "11.0.0.0/8".to_string(),
+        ];
+        let network_object = NetworkObject::try_from(&lines).unwrap();
+
+        let prefix = network_object.bounding_prefix().unwrap();
+        assert_eq!(prefix.get_name(), "10.0.0.0/7");
+    }
+
+    #[test]
+    fn bounding_prefix_single_host() {
+        let lines = vec!["Source Networks       : 192.168.1.1".to_string()];
+        let network_object = NetworkObject::try_from(&lines).unwrap();
+
+        let prefix = network_object.bounding_prefix().unwrap();
+        assert_eq!(prefix.get_name(), "192.168.1.1/32");
+    }
+
+    #[test]
+    fn no_dns_hostname_capacity_zero() {
+        hostname::set_resolver(Box::new(hostname::NoDnsResolver));
+        let lines = vec![
+            "Source Networks       : 10.0.0.0/24".to_string(),
+            "outlook.office365.com".to_string(),
+        ];
+        let network_object = NetworkObject::try_from(&lines).unwrap();
+
+        assert_eq!(network_object.capacity(), 1);
+        let optimized = network_object.optimize();
+        assert_eq!(optimized.cidrs(), vec!["10.0.0.0/24".to_string()]);
+    }
+
+    #[test]
+    fn decompose_lists_original_items() {
+        let lines = vec![
+            "Source Networks       : Internal (group)".to_string(),
+            "  10.11.12.0/24".to_string(),
+            "  10.11.13.0/24".to_string(),
+            "  10.11.14.0-10.11.15.255".to_string(),
+            "  192.168.1.1".to_string(),
+        ];
+        let optimized = NetworkObject::try_from(&lines).unwrap().optimize();
+        assert_eq!(optimized.items().len(), 2);
+
+        let components = optimized.decompose();
+        let names = components
+            .iter()
+            .map(|component| component.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(components.len(), 4);
+        for name in [
+            "10.11.12.0/24",
+            "10.11.13.0/24",
+            "10.11.14.0-10.11.15.255",
+            "192.168.1.1",
+        ] {
+            assert!(names.contains(&name), "{name} is missing in {names:?}");
+        }
+
+        let range = components
+            .iter()
+            .find(|component| component.name == "10.11.14.0-10.11.15.255")
+            .unwrap();
+        assert_eq!(range.cidrs, vec!["10.11.14.0/23".to_string()]);
+        assert_eq!(range.merged_into, optimized.items()[0].name());
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let lines = vec![
+            "Source Networks       : 192.168.1.0/24".to_string(),
+            "  192.168.1.128/25".to_string(),
+            "  192.168.1.200-192.168.2.10".to_string(),
+            "  192.168.3.0/24".to_string(),
+        ];
+        let network_object = NetworkObject::try_from(&lines).unwrap();
+        let overlaps = network_object
+            .overlaps()
+            .iter()
+            .map(|overlap| overlap.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            overlaps,
+            vec![
+                "Source Networks: 192.168.1.0/24 SHADOWS 192.168.1.128/25",
+                "Source Networks: 192.168.1.0/24 PARTIALLY OVERLAPS 192.168.1.200-192.168.2.10",
+                "Source Networks: 192.168.1.128/25 PARTIALLY OVERLAPS 192.168.1.200-192.168.2.10",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlaps_adjoining() {
+        let lines = vec![
+            "Source Networks       : 192.168.0.0/24".to_string(),
+            "  192.168.1.0/24".to_string(),
+        ];
+        let network_object = NetworkObject::try_from(&lines).unwrap();
+        assert!(network_object.overlaps().is_empty());
+    }
 }
@@ -15,6 +15,13 @@ impl NetworkObjectItem {
         }
     }
 
+    pub fn get_name(&self) -> &str {
+        match self {
+            NetworkObjectItem::ObjectGroup(group) => group.get_name(),
+            NetworkObjectItem::PrefixList(prefix_list) => prefix_list.get_name(),
+        }
+    }
+
     pub fn get_prefix_lists(&self) -> Vec<&PrefixList> {
         match self {
             NetworkObjectItem::ObjectGroup(group) => group.get_prefix_lists().iter().collect(),
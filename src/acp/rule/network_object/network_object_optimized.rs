@@ -1,5 +1,16 @@
+use super::group::prefix_list::prefix_list_item::ip_range::IPRange;
+use super::group::prefix_list::prefix_list_item::ipv4::IPv4;
 use super::prefix_list_item_optimized::PrefixListItemOptimized;
 
+/// Original item folded into an optimized entry
+/// merged_into - name of the optimized entry
+#[derive(Debug, PartialEq)]
+pub struct Component {
+    pub name: String,
+    pub cidrs: Vec<String>,
+    pub merged_into: String,
+}
+
 #[derive(Debug)]
 pub struct NetworkObjectOptimized {
     name: String,
@@ -41,4 +52,42 @@ impl NetworkObjectOptimized {
     pub fn capacity(&self) -> u64 {
         self.items.iter().map(|item| item.capacity()).sum()
     }
+
+    /// Number of addresses matched by the merged ranges, unlike capacity it doesn't depend on
+    /// how many subnets a range is split into
+    pub fn address_span(&self) -> u64 {
+        self.items
+            .iter()
+            .fold(0u64, |span, item| span.saturating_add(item.address_span()))
+    }
+
+    /// Returns all optimized items in CIDR notation
+    pub fn cidrs(&self) -> Vec<String> {
+        self.items.iter().flat_map(|item| item.cidrs()).collect()
+    }
+
+    /// Checks if the ip address is matched by any of the optimized (merged) items.
+    pub fn contains(&self, ip: &IPv4) -> bool {
+        self.items.iter().any(|item| item.contains(ip))
+    }
+
+    /// Lists original items folded into the optimized entries,
+    /// e.g. to report that 7 objects became 3
+    pub fn decompose(&self) -> Vec<Component> {
+        self.items
+            .iter()
+            .flat_map(|optimized| {
+                optimized.items().iter().map(|item| Component {
+                    name: item.get_name().to_string(),
+                    cidrs: IPRange::new(
+                        item.get_name().to_string(),
+                        item.start_ip().clone(),
+                        item.end_ip().clone(),
+                    )
+                    .cidrs(),
+                    merged_into: optimized.name().to_string(),
+                })
+            })
+            .collect()
+    }
 }
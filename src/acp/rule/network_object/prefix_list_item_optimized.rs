@@ -28,6 +28,14 @@ impl PrefixListItemOptimized {
         self.items.as_ref()
     }
 
+    pub fn start_ip(&self) -> &IPv4 {
+        self.items
+            .iter()
+            .map(|item| item.start_ip())
+            .min()
+            .unwrap_or_else(|| panic!("Logic error: PrefixListItemOptimized ({}) should have at least one PrefixListItem, if this error is triggered, parsing logic must be fixed. Currently the only way to craft obj is from-trait which accepts correct object", self.name))
+    }
+
     pub fn end_ip(&self) -> &IPv4 {
         self.items
             .iter()
@@ -44,6 +52,17 @@ impl PrefixListItemOptimized {
         self.name = name;
     }
 
+    /// Returns the merged range in CIDR notation
+    pub fn cidrs(&self) -> Vec<String> {
+        let ip_range = IPRange::new(
+            self.name.clone(),
+            self.start_ip().clone(),
+            self.end_ip().clone(),
+        );
+
+        ip_range.cidrs()
+    }
+
     pub fn capacity(&self) -> u64 {
         let start_ip = self.items.iter().map(|item| item.start_ip()).min().unwrap_or_else(|| panic!("Logic error: PrefixListItemOptimized ({}) should have at least one PrefixListItem, if this error is triggered, parsing logic must be fixed. Currently the only way to craft obj is from-trait which accepts correct object", self.name));
         let end_ip = self.items.iter().map(|item| item.end_ip()).max().unwrap_or_else(|| panic!("Logic error: PrefixListItemOptimized ({}) should have at least one PrefixListItem, if this error is triggered, parsing logic must be fixed. Currently the only way to craft obj is from-trait which accepts correct object", self.name));
@@ -53,6 +72,16 @@ impl PrefixListItemOptimized {
         ip_range.capacity()
     }
 
+    /// Number of addresses in the merged range [start_ip, end_ip]
+    pub fn address_span(&self) -> u64 {
+        u32::from(self.end_ip()) as u64 - u32::from(self.start_ip()) as u64 + 1
+    }
+
+    /// Checks if the ip address falls into the merged range [start_ip, end_ip].
+    pub fn contains(&self, ip: &IPv4) -> bool {
+        self.start_ip() <= ip && ip <= self.end_ip()
+    }
+
     pub fn is_optimized(&self) -> bool {
         let optimized_capacity = self.capacity();
 
@@ -1,3 +1,22 @@
+use std::cell::Cell;
+
+/// Nesting depth of groups accepted by default, the outermost group is at depth 1
+pub const DEFAULT_MAX_GROUP_DEPTH: usize = 16;
+
+thread_local! {
+    static MAX_GROUP_DEPTH: Cell<usize> = const { Cell::new(DEFAULT_MAX_GROUP_DEPTH) };
+}
+
+/// Limits the nesting depth of network and port groups on the current thread,
+/// deeper groups fail to parse instead of exhausting the stack
+pub fn set_max_group_depth(depth: usize) {
+    MAX_GROUP_DEPTH.with(|d| d.set(depth));
+}
+
+pub fn max_group_depth() -> usize {
+    MAX_GROUP_DEPTH.with(|d| d.get())
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum UtilitiesError {
     #[error("Fail to extract name: {0}")]
@@ -42,15 +61,25 @@ pub fn extract_name(lines: &[String]) -> Result<(String, Vec<String>), Utilities
         })?
         .trim()
         .to_string();
-    let merged_lines: Vec<_> = first_line[1..]
+    let mut merged_lines: Vec<_> = first_line[1..]
         .iter()
         .map(|x| x.to_string())
         .chain(lines[1..].iter().map(|x| x.to_string()))
         .collect();
 
+    // blank lines trailing the section are not objects
+    while merged_lines.len() > 1 && merged_lines.last().is_some_and(|x| x.trim().is_empty()) {
+        merged_lines.pop();
+    }
+
     Ok((name, merged_lines))
 }
 
+// Group members are the lines following the title that are more indented than the title,
+// the amount of indentation doesn't matter as long as it is consistent.
+// The title merged from the "Name : title" line has lost its column, so the first member
+// indentation is used as a lower bound as well: a less indented line starts the next object.
+//
 // Example1:
 // Internal (group)
 //   OBJ-157.121.0.0 (157.121.0.0/16)
@@ -69,6 +98,28 @@ pub fn extract_name(lines: &[String]) -> Result<(String, Vec<String>), Utilities
 // Another (group)
 // return 1
 pub fn calculate_lines_in_group(lines: &[String]) -> Result<usize, UtilitiesError> {
+    lines_in_group(lines, false)
+}
+
+// Same as calculate_lines_in_group, but a "(group)" line indented as a member is a nested group,
+// it belongs to the group together with its own members.
+// A "(group)" line right after the title is the next object: the title merged from
+// "Name : title" has lost its column and can't tell a nested group from the next one,
+// the members are flattened either way.
+//
+// Example:
+// Web (group)
+//   HTTP (protocol 6, port 80)
+//   TLS (group)
+//     HTTPS (protocol 6, port 443)
+//     IMAPS (protocol 6, port 993)
+// SSH (protocol 6, port 22)
+// return 5
+pub fn calculate_lines_in_nested_group(lines: &[String]) -> Result<usize, UtilitiesError> {
+    lines_in_group(lines, true)
+}
+
+fn lines_in_group(lines: &[String], nested: bool) -> Result<usize, UtilitiesError> {
     if lines.is_empty() {
         return Err(UtilitiesError::GroupLineCalculationError(
             "Input lines are empty".to_string(),
@@ -78,21 +129,24 @@ pub fn calculate_lines_in_group(lines: &[String]) -> Result<usize, UtilitiesErro
         return Ok(1);
     }
 
-    let [_, first_line, ..] = lines else {
+    let [title, first_member, ..] = lines else {
         return Err(UtilitiesError::GroupLineCalculationError(format!(
             "Panic {:?}",
             lines
         )));
     };
 
-    let reference_padding = first_line.len() - first_line.trim_start().len();
+    let title_padding = indentation(title);
+    let member_padding = indentation(first_member);
+    let nested = nested && !first_member.contains("(group)");
+
     let mut idx = 1;
     while idx < lines.len() {
-        if lines[idx].contains("(group)") {
+        if !nested && lines[idx].contains("(group)") {
             return Ok(idx);
         }
-        let padding = lines[idx].len() - lines[idx].trim_start().len();
-        if padding != reference_padding {
+        let padding = indentation(&lines[idx]);
+        if padding <= title_padding || padding < member_padding {
             return Ok(idx);
         }
         idx += 1;
@@ -100,6 +154,17 @@ pub fn calculate_lines_in_group(lines: &[String]) -> Result<usize, UtilitiesErro
     Ok(idx)
 }
 
+/// Width of a tab character when calculating indentation
+const TAB_WIDTH: usize = 8;
+
+/// Width of the leading whitespace, tabs are normalized to TAB_WIDTH spaces
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,6 +188,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_extract_name_trailing_blank_lines() {
+        let lines = vec![
+            "Destination Ports     : HTTP-HTTPS_1 (group)".to_string(),
+            "".to_string(),
+            "    ".to_string(),
+        ];
+        let (name, merged_lines) = extract_name(&lines).unwrap();
+        assert_eq!(name, "Destination Ports");
+        assert_eq!(merged_lines, vec!["HTTP-HTTPS_1 (group)".to_string()]);
+    }
+
     #[test]
     fn test_extract_name_invalid_format() {
         let lines = vec!["Source Networks Internal (group)".to_string()];
@@ -169,6 +246,68 @@ mod tests {
         assert_eq!(result, 8);
     }
 
+    #[test]
+    fn test_calculate_lines_in_group_mixed_tabs_and_spaces() {
+        let lines = vec![
+            "Internal (group)".to_string(),
+            "\t\tOBJ-157.121.0.0 (157.121.0.0/16)".to_string(),
+            "                10.0.0.0/8".to_string(),
+            "\t        172.16.0.0/12".to_string(),
+            "\tOBJ-192.168.243.0_24 (192.168.243.0/24)".to_string(),
+        ];
+        let result = calculate_lines_in_group(&lines).unwrap();
+        assert_eq!(result, 4);
+    }
+
+    fn group_with_indent(indent: &str) -> Vec<String> {
+        vec![
+            "Internal (group)".to_string(),
+            format!("{}OBJ-157.121.0.0 (157.121.0.0/16)", indent),
+            format!("{}10.0.0.0/8", indent),
+            format!("{}172.16.0.0/12", indent),
+            "OBJ-192.168.243.0_24 (192.168.243.0/24)".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_calculate_lines_in_group_any_indent_width() {
+        for indent in ["  ", "    ", "        "] {
+            let lines = group_with_indent(indent);
+            let result = calculate_lines_in_group(&lines).unwrap();
+            assert_eq!(result, 4, "indent width {}", indent.len());
+        }
+    }
+
+    #[test]
+    fn test_calculate_lines_in_group_indented_title() {
+        let lines = vec![
+            "    Internal (group)".to_string(),
+            "      OBJ-157.121.0.0 (157.121.0.0/16)".to_string(),
+            "      10.0.0.0/8".to_string(),
+            "    OBJ-192.168.243.0_24 (192.168.243.0/24)".to_string(),
+        ];
+        let result = calculate_lines_in_group(&lines).unwrap();
+        assert_eq!(result, 3);
+    }
+
+    #[test]
+    fn test_calculate_lines_in_group_not_indented_member() {
+        let lines = vec![
+            "    Internal (group)".to_string(),
+            "    OBJ-192.168.243.0_24 (192.168.243.0/24)".to_string(),
+        ];
+        let result = calculate_lines_in_group(&lines).unwrap();
+        assert_eq!(result, 1);
+    }
+
+    #[test]
+    fn test_indentation() {
+        assert_eq!(indentation("10.0.0.0/8"), 0);
+        assert_eq!(indentation("  10.0.0.0/8"), 2);
+        assert_eq!(indentation("\t10.0.0.0/8"), TAB_WIDTH);
+        assert_eq!(indentation("\t  10.0.0.0/8"), TAB_WIDTH + 2);
+    }
+
     #[test]
     fn test_calculate_lines_in_group_multiple_groups() {
         let lines = vec![
@@ -179,6 +318,30 @@ mod tests {
         assert_eq!(result, 1);
     }
 
+    #[test]
+    fn test_calculate_lines_in_nested_group_after_title() {
+        let lines = vec![
+            "Internal (group)".to_string(),
+            " Another (group)".to_string(),
+            "  10.0.0.0/8".to_string(),
+        ];
+        assert_eq!(calculate_lines_in_nested_group(&lines).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_calculate_lines_in_nested_group() {
+        let lines = vec![
+            "Web (group)".to_string(),
+            "  HTTP (protocol 6, port 80)".to_string(),
+            "  TLS (group)".to_string(),
+            "    HTTPS (protocol 6, port 443)".to_string(),
+            "    IMAPS (protocol 6, port 993)".to_string(),
+            "SSH (protocol 6, port 22)".to_string(),
+        ];
+        assert_eq!(calculate_lines_in_nested_group(&lines).unwrap(), 5);
+        assert_eq!(calculate_lines_in_group(&lines).unwrap(), 2);
+    }
+
     #[test]
     fn test_calculate_lines_in_group_empty_lines() {
         let lines: Vec<String> = vec![];
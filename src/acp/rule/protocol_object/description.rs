@@ -1,10 +1,12 @@
 use std::fmt::Display;
 use std::fmt::Formatter;
 
+#[derive(Debug, PartialEq, Eq)]
 pub enum DescriptionType {
     Adjoins,
     Shadows,
     PartiallyOverlaps,
+    Excludes,
 }
 
 impl Display for DescriptionType {
@@ -13,10 +15,14 @@ impl Display for DescriptionType {
             DescriptionType::Adjoins => write!(f, "ADJOINS"),
             DescriptionType::Shadows => write!(f, "SHADOWS"),
             DescriptionType::PartiallyOverlaps => write!(f, "PARTIALLY OVERLAPS"),
+            DescriptionType::Excludes => write!(f, "EXCLUDES"),
         }
     }
 }
 
+/// Relation of the next (sorted by start) entry to the merged ones.
+/// Holds for single-address entries (start == end) as well:
+/// 10.0.0.0 and 10.0.0.1 ADJOIN, 10.0.0.0 and 10.0.0.0 SHADOW.
 pub fn verb(curr_end: u32, next_start: u32, next_end: u32) -> DescriptionType {
     if curr_end as u64 + 1 == next_start as u64 {
         DescriptionType::Adjoins
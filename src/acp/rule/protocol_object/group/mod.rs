@@ -1,10 +1,13 @@
 pub mod protocol_list;
 use protocol_list::ProtocolList;
 
-#[derive(Debug)]
+use super::super::network_object::utilities;
+
+#[derive(Debug, Clone)]
 pub struct Group {
     pub _name: String,
     pub port_lists: Vec<ProtocolList>,
+    pub negated_lists: Vec<ProtocolList>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -13,6 +16,14 @@ pub enum GroupError {
     General(String),
     #[error("Failed to parse port group: {0}")]
     PortListError(#[from] protocol_list::PortListError),
+    #[error("Failed to parse port group: {0}")]
+    NestedGroup(#[from] utilities::UtilitiesError),
+    #[error("Fail to parse port group {0}: groups are nested deeper than {1} levels")]
+    NestingTooDeep(String, usize),
+    #[error(
+        "Fail to parse port group {0}: negated entry {1} inside a negated group is not supported"
+    )]
+    DoubleNegation(String, String),
 }
 
 impl TryFrom<&Vec<String>> for Group {
@@ -22,9 +33,23 @@ impl TryFrom<&Vec<String>> for Group {
     // HTTP-HTTPS_1 (group)
     //   HTTP (protocol 6, port 80)
     //   HTTPS (protocol 6, port 443)
+    //   Mail (group)
+    //     SMTP (protocol 6, port 25)
+    //
+    // Members of nested groups are flattened into port_lists,
+    // negated members ("HTTP (protocol 6, port 80) negate") and members of a negated group
+    // are collected into negated_lists.
+    // A negated member of a negated group is rejected, FTD doesn't document what it matches
 
     fn try_from(lines: &Vec<String>) -> Result<Self, Self::Error> {
-        if let [title, ..] = lines.as_slice() {
+        Group::parse(lines, 1)
+    }
+}
+
+impl Group {
+    /// Parses the group at the given nesting depth, see utilities::max_group_depth
+    fn parse(lines: &[String], depth: usize) -> Result<Self, GroupError> {
+        if let [title, ..] = lines {
             if !title.contains(" (group)") {
                 return Err(GroupError::General(format!(
                     "Invalid group format, should contain (group) {}",
@@ -32,19 +57,52 @@ impl TryFrom<&Vec<String>> for Group {
                 )));
             }
             let name = title.split('(').next().unwrap().trim().to_string();
+            if depth > utilities::max_group_depth() {
+                return Err(GroupError::NestingTooDeep(
+                    name,
+                    utilities::max_group_depth(),
+                ));
+            }
+            let group_negated = strip_negation(title).is_some();
             let mut port_lists = vec![];
+            let mut negated_lists = vec![];
 
-            for line in &lines[1..] {
-                let port = line.trim();
-                if !port.is_empty() {
+            let mut idx = 1;
+            while idx < lines.len() {
+                let port = lines[idx].trim();
+                if port.contains(" (group)") {
+                    let lines_in_group = utilities::calculate_lines_in_nested_group(&lines[idx..])?;
+                    let nested = Group::parse(&lines[idx..idx + lines_in_group], depth + 1)?;
+                    if let (true, Some(negated)) = (group_negated, nested.negated_lists.first()) {
+                        return Err(GroupError::DoubleNegation(name, negated.to_string()));
+                    }
+                    match group_negated {
+                        true => negated_lists.extend(nested.port_lists),
+                        false => port_lists.extend(nested.port_lists),
+                    }
+                    negated_lists.extend(nested.negated_lists);
+                    idx += lines_in_group;
+                    continue;
+                }
+                if let Some(port) = strip_negation(port) {
+                    if group_negated {
+                        return Err(GroupError::DoubleNegation(name, port.to_string()));
+                    }
+                    negated_lists.extend(ProtocolList::from_str_expanded(port)?);
+                } else if !port.is_empty() {
                     let objects = ProtocolList::from_str_expanded(port)?;
-                    port_lists.extend(objects);
+                    match group_negated {
+                        true => negated_lists.extend(objects),
+                        false => port_lists.extend(objects),
+                    }
                 }
+                idx += 1;
             }
 
             Ok(Self {
                 _name: name,
                 port_lists,
+                negated_lists,
             })
         } else {
             Err(GroupError::General("Invalid group format.".to_string()))
@@ -52,6 +110,18 @@ impl TryFrom<&Vec<String>> for Group {
     }
 }
 
+/// Entry without the trailing negation marker, e.g. "HTTP (protocol 6, port 80) negate" -> "HTTP (protocol 6, port 80)".
+/// None if the entry isn't negated: the marker must follow the entry,
+/// so objects named "Negate" or "Negate-Tunnel" are ordinary entries
+pub fn strip_negation(line: &str) -> Option<&str> {
+    let (entry, marker) = line.trim().rsplit_once(char::is_whitespace)?;
+    let entry = entry.trim_end();
+    let is_marker = marker.eq_ignore_ascii_case("negate") || marker.eq_ignore_ascii_case("negated");
+    let ends_entry = entry.ends_with(')') || entry.ends_with(|c: char| c.is_ascii_digit());
+
+    (is_marker && ends_entry).then_some(entry)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -81,6 +151,86 @@ mod tests {
         assert_eq!(group.port_lists.len(), 4);
     }
 
+    #[test]
+    fn valid_group_with_object_count_annotation() {
+        let lines = vec![
+            "HTTP-HTTPS_1 (group) [2 objects]".to_string(),
+            "  HTTP (protocol 6, port 80)".to_string(),
+            "  HTTPS (protocol 6, port 443)".to_string(),
+        ];
+        let group = Group::try_from(&lines).unwrap();
+        assert_eq!(group._name, "HTTP-HTTPS_1");
+        assert_eq!(group.port_lists.len(), 2);
+    }
+
+    #[test]
+    fn valid_nested_group() {
+        let lines = vec![
+            "Web (group)".to_string(),
+            "  HTTP (protocol 6, port 80)".to_string(),
+            "  TLS (group)".to_string(),
+            "    HTTPS (protocol 6, port 443)".to_string(),
+            "    IMAPS (protocol 6, port 993)".to_string(),
+            "  HTTP-ALT (protocol 6, port 8080)".to_string(),
+        ];
+        let group = Group::try_from(&lines).unwrap();
+
+        let names: Vec<&str> = group.port_lists.iter().map(|p| p.get_name()).collect();
+        assert_eq!(names, vec!["HTTP", "HTTPS", "IMAPS", "HTTP-ALT"]);
+    }
+
+    fn nested_groups(levels: usize) -> Vec<String> {
+        (0..levels)
+            .flat_map(|depth| {
+                [
+                    format!("{}Level-{} (group)", " ".repeat(depth), depth),
+                    format!(
+                        "{}TCP-{} (protocol 6, port {})",
+                        " ".repeat(depth + 1),
+                        depth,
+                        depth
+                    ),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn double_negation_rejected() {
+        for lines in [
+            vec![
+                "Web (group) negate",
+                "  HTTP (protocol 6, port 80)",
+                "  HTTPS (protocol 6, port 443) negate",
+            ],
+            vec![
+                "Web (group) negate",
+                "  HTTP (protocol 6, port 80)",
+                "  TLS (group)",
+                "    HTTPS (protocol 6, port 443) negate",
+            ],
+        ] {
+            let lines: Vec<String> = lines.into_iter().map(String::from).collect();
+            assert!(matches!(
+                Group::try_from(&lines),
+                Err(GroupError::DoubleNegation(_, _))
+            ));
+        }
+    }
+
+    #[test]
+    fn deeply_nested_group() {
+        let group = Group::try_from(&nested_groups(utilities::DEFAULT_MAX_GROUP_DEPTH)).unwrap();
+        assert_eq!(group.port_lists.len(), utilities::DEFAULT_MAX_GROUP_DEPTH);
+
+        let result = Group::try_from(&nested_groups(200));
+        assert!(matches!(result, Err(GroupError::NestingTooDeep(_, 16))));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Fail to parse port group Level-16: groups are nested deeper than 16 levels"
+        );
+    }
+
     #[test]
     fn test_invalid_group_format() {
         let lines = vec![
@@ -22,24 +22,23 @@ pub enum IcmpError {
 
 impl fmt::Display for Icmp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(code) = self.code {
-            write!(
+        match (self.icmp_type, self.code) {
+            (Some(icmp_type), Some(code)) => write!(
                 f,
                 "{} (protocol {}, type {}, code {})",
-                self.name,
-                self.protocol,
-                self.icmp_type
-                    .expect("PANIC: ICMP type is None while ICMP code is dedined"),
-                code
-            )
-        } else if let Some(icmp_type) = self.icmp_type {
-            write!(
+                self.name, self.protocol, icmp_type, code
+            ),
+            (None, Some(code)) => write!(
+                f,
+                "{} (protocol {}, type any, code {})",
+                self.name, self.protocol, code
+            ),
+            (Some(icmp_type), None) => write!(
                 f,
                 "{} (protocol {}, type {})",
                 self.name, self.protocol, icmp_type
-            )
-        } else {
-            write!(f, "{} (protocol {})", self.name, self.protocol)
+            ),
+            (None, None) => write!(f, "{} (protocol {})", self.name, self.protocol),
         }
     }
 }
@@ -59,10 +58,13 @@ impl FromStr for Icmp {
     // Example 4
     // ICMP (protocol 1, type 200)
 
+    // Example 5
+    // ICMP (protocol 1, type any, code 4)
+
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (name, proto_and_ports) = common::parse_name_and_protocol(s)?;
 
-        let protocol = common::parse_protocol(proto_and_ports)?;
+        let protocol = common::parse_protocol_number(proto_and_ports)?;
 
         let (icmp_type, code) = parse_type_and_code(proto_and_ports)?;
 
@@ -87,6 +89,14 @@ impl Icmp {
     pub fn get_protocol(&self) -> u8 {
         self.protocol
     }
+
+    pub fn get_icmp_type(&self) -> Option<u8> {
+        self.icmp_type
+    }
+
+    pub fn get_icmp_code(&self) -> Option<u8> {
+        self.code
+    }
 }
 
 impl PartialEq for Icmp {
@@ -115,50 +125,45 @@ impl Hash for Icmp {
 
 // Example 3
 // protocol 1
+
+// Example 4
+// protocol 1, type any, code 4
 fn parse_type_and_code(s: &str) -> Result<(Option<u8>, Option<u8>), IcmpError> {
     let mut parts = s.split(",");
 
     match parts.clone().count() {
         1 => Ok((None, None)),
         2 => {
-            let icmp_type = parts.nth(1).unwrap().trim();
-            let icmp_type = icmp_type.split_whitespace().last().unwrap();
-            let icmp_type = icmp_type.parse().map_err(|_| {
-                IcmpError::General(format!(
-                    "Failed to parse ICMP type: {} from {}",
-                    icmp_type, s
-                ))
-            })?;
-
-            Ok((Some(icmp_type), None))
+            let icmp_type = parse_type_or_code("type", parts.nth(1).unwrap(), s)?;
+
+            Ok((icmp_type, None))
         }
         3 => {
-            let icmp_type = parts.nth(1).unwrap().trim();
-            let icmp_type = icmp_type.split_whitespace().last().unwrap();
-            let icmp_type = icmp_type.parse().map_err(|_| {
-                IcmpError::General(format!(
-                    "Failed to parse ICMP type: {} from {}",
-                    icmp_type, s
-                ))
-            })?;
-
-            let code = parts.next().unwrap().trim();
-            let code = code.split_whitespace().last().unwrap();
-
-            if code.to_lowercase() == "any" {
-                return Ok((Some(icmp_type), None));
-            }
-
-            let code = code.parse().map_err(|_| {
-                IcmpError::General(format!("Failed to parse ICMP code: {} from  {}", code, s))
-            })?;
-
-            Ok((Some(icmp_type), Some(code)))
+            let icmp_type = parse_type_or_code("type", parts.nth(1).unwrap(), s)?;
+            let code = parse_type_or_code("code", parts.next().unwrap(), s)?;
+
+            Ok((icmp_type, code))
         }
         _ => Err(IcmpError::General(format!("Invalid ICMP: {}", s))),
     }
 }
 
+// "type 3" -> Some(3), "code any" -> None
+fn parse_type_or_code(kind: &str, part: &str, s: &str) -> Result<Option<u8>, IcmpError> {
+    let value = part.split_whitespace().last().unwrap_or_default();
+
+    if value.eq_ignore_ascii_case("any") {
+        return Ok(None);
+    }
+
+    value.parse().map(Some).map_err(|_| {
+        IcmpError::General(format!(
+            "Failed to parse ICMP {}: {} from {}",
+            kind, value, s
+        ))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -271,6 +276,24 @@ mod tests {
         assert_eq!(icmp.code, None);
     }
 
+    #[test]
+    fn test_parse_type_any_with_code() {
+        let icmp = "Odd-ICMP (protocol 1, type any, code 4)"
+            .parse::<Icmp>()
+            .unwrap();
+        assert_eq!(icmp.icmp_type, None);
+        assert_eq!(icmp.code, Some(4));
+        assert_eq!(icmp.to_string(), "Odd-ICMP (protocol 1, type any, code 4)");
+
+        let icmp = Icmp {
+            name: "Code-only".to_string(),
+            protocol: 1,
+            icmp_type: None,
+            code: Some(0),
+        };
+        assert_eq!(icmp.to_string(), "Code-only (protocol 1, type any, code 0)");
+    }
+
     #[test]
     fn test_parse_invalid_format() {
         assert!("Invalid (protocol 1, type, code)".parse::<Icmp>().is_err());
@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::fmt;
 use std::str::FromStr;
 
@@ -28,6 +29,16 @@ pub enum PortListError {
     CommonError(#[from] common::CommonError),
 }
 
+thread_local! {
+    static LENIENT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Enables inference of the protocol from well-known names ("ICMP Echo") on the current thread.
+/// Strict parsing (default) requires an explicit "protocol N"
+pub fn set_lenient_protocols(lenient: bool) {
+    LENIENT.with(|l| l.set(lenient));
+}
+
 impl fmt::Display for ProtocolList {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -53,17 +64,20 @@ impl FromStr for ProtocolList {
     // Example 4
     // IGMP (protocol 2)
 
+    // Example 5
+    // IP (protocol 0) - protocol number 0 is an ordinary L3 entry, unlike "protocol ip"
+
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (_name, ports) = common::parse_name_and_protocol(s)?;
 
         let protocol = common::parse_protocol(ports)?;
 
         match protocol {
-            6 | 17 => {
+            common::Protocol::Number(6 | 17) => {
                 let tcp_udp = tcp_udp::TcpUdp::from_str(s)?;
                 Ok(Self::TcpUdp(tcp_udp))
             }
-            1 | 58 => {
+            common::Protocol::Number(1 | 58) => {
                 let icmp = icmp::Icmp::from_str(s)?;
                 Ok(Self::Icmp(icmp))
             }
@@ -76,26 +90,45 @@ impl FromStr for ProtocolList {
 }
 
 impl ProtocolList {
-    /// Parses a string into a ProtocolList, expanding "protocol any" to both TCP and UDP.
+    /// Parses a string into a ProtocolList, expanding "protocol any" to both TCP and UDP,
+    /// a protocol range ("protocol 10-12") to one L3 entry per protocol
+    /// and a list of protocols ("protocol 6, protocol 17, port 80") to one entry per protocol.
     pub fn from_str_expanded(s: &str) -> Result<Vec<Self>, PortListError> {
-        const PROTOCOL_ANY_PORT: &str = "protocol any, port ";
-
-        let expanded_protocols = if s.contains(PROTOCOL_ANY_PORT) {
-            vec![
-                s.replace(PROTOCOL_ANY_PORT, "protocol 6, port "),
-                s.replace(PROTOCOL_ANY_PORT, "protocol 17, port "),
-            ]
-        } else {
-            vec![s.to_string()]
-        };
+        if is_protocol_range(s) {
+            let protocols = other_protocol::OtherProtocol::from_str_range(s)?;
+            return Ok(protocols.into_iter().map(Self::OtherProtocol).collect());
+        }
+
+        // matches both "port" and "ports" keywords
+        const PROTOCOL_ANY_PORT: &str = "protocol any, port";
+
+        let expanded_protocols = split_protocols(s).into_iter().flat_map(|s| {
+            if s.contains(PROTOCOL_ANY_PORT) {
+                vec![
+                    s.replace(PROTOCOL_ANY_PORT, "protocol 6, port"),
+                    s.replace(PROTOCOL_ANY_PORT, "protocol 17, port"),
+                ]
+            } else {
+                vec![s]
+            }
+        });
         let protocol_list = expanded_protocols
             .into_iter()
-            .map(|s| ProtocolList::from_str(&s))
+            .map(|s| match LENIENT.with(|l| l.get()) {
+                true => ProtocolList::from_str_lenient(&s),
+                false => ProtocolList::from_str(&s),
+            })
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(protocol_list)
     }
 
+    /// Parses a string into a ProtocolList, inferring the protocol of well-known names
+    /// written without "(protocol N)", for example "ICMP Echo" -> protocol 1, type 8
+    pub fn from_str_lenient(s: &str) -> Result<Self, PortListError> {
+        ProtocolList::from_str(&common::with_inferred_protocol(s))
+    }
+
     pub fn is_l4(&self) -> bool {
         match self {
             ProtocolList::TcpUdp(tcp_udp) => tcp_udp.is_l4(),
@@ -110,25 +143,181 @@ impl ProtocolList {
             ProtocolList::Icmp(icmp) => icmp.get_name(),
         }
     }
-    pub fn get_protocol(&self) -> u8 {
+    pub fn get_protocol(&self) -> common::Protocol {
         match self {
-            ProtocolList::TcpUdp(tcp_udp) => tcp_udp.get_protocol(),
+            ProtocolList::TcpUdp(tcp_udp) => common::Protocol::Number(tcp_udp.get_protocol()),
             ProtocolList::OtherProtocol(other_protocol) => other_protocol.get_protocol(),
-            ProtocolList::Icmp(icmp) => icmp.get_protocol(),
+            ProtocolList::Icmp(icmp) => common::Protocol::Number(icmp.get_protocol()),
         }
     }
+    /// "protocol ip" / "protocol all" entry, matches every protocol
+    pub fn is_all_protocols(&self) -> bool {
+        self.get_protocol() == common::Protocol::Any
+    }
     pub fn get_ports(&self) -> (u16, u16) {
         match self {
             ProtocolList::TcpUdp(tcp_udp) => tcp_udp.get_ports(),
             _ => (0, 0),
         }
     }
+    /// Same entry restricted to another port range, non-L4 entries have no ports and are unchanged
+    pub fn with_ports(&self, start: u16, end: u16) -> ProtocolList {
+        match self {
+            ProtocolList::TcpUdp(tcp_udp) => ProtocolList::TcpUdp(tcp_udp.with_ports(start, end)),
+            _ => self.clone(),
+        }
+    }
+    /// ICMP type if set, None for any other protocol
+    pub fn get_icmp_type(&self) -> Option<u8> {
+        match self {
+            ProtocolList::Icmp(icmp) => icmp.get_icmp_type(),
+            _ => None,
+        }
+    }
+    /// ICMP code if set, None for any other protocol
+    pub fn get_icmp_code(&self) -> Option<u8> {
+        match self {
+            ProtocolList::Icmp(icmp) => icmp.get_icmp_code(),
+            _ => None,
+        }
+    }
+}
+
+fn is_protocol_range(s: &str) -> bool {
+    common::parse_name_and_protocol(s)
+        .and_then(|(_name, ports)| common::parse_protocol_range(ports))
+        .is_ok_and(|(start, end)| start != end)
+}
+
+/// Splits "TCP/UDP (protocol 6, protocol 17, port 80)" into one line per protocol,
+/// every line keeps the name and the rest of the clauses:
+/// "TCP/UDP (protocol 6, port 80)" and "TCP/UDP (protocol 17, port 80)"
+fn split_protocols(s: &str) -> Vec<String> {
+    let Ok((name, ports)) = common::parse_name_and_protocol(s) else {
+        return vec![s.to_string()];
+    };
+
+    let (protocols, rest): (Vec<&str>, Vec<&str>) = ports
+        .split(',')
+        .map(|clause| clause.trim())
+        .partition(|clause| clause.starts_with("protocol "));
+
+    if protocols.len() < 2 {
+        return vec![s.to_string()];
+    }
+
+    protocols
+        .into_iter()
+        .map(|protocol| {
+            let clauses = std::iter::once(protocol)
+                .chain(rest.iter().copied())
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{} ({})", name, clauses)
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_multiple_protocols_on_one_line() {
+        let port_lists =
+            ProtocolList::from_str_expanded("TCP/UDP (protocol 6, protocol 17, port 80)").unwrap();
+
+        assert_eq!(port_lists.len(), 2);
+        assert_eq!(port_lists[0].get_protocol(), 6);
+        assert_eq!(port_lists[0].get_ports(), (80, 80));
+        assert_eq!(port_lists[0].get_name(), "TCP/UDP");
+        assert_eq!(port_lists[1].get_protocol(), 17);
+        assert_eq!(port_lists[1].get_ports(), (80, 80));
+    }
+
+    #[test]
+    fn test_multiple_protocols_without_ports() {
+        let port_lists =
+            ProtocolList::from_str_expanded("ROUTING (protocol 88, protocol 89)").unwrap();
+
+        let protocols: Vec<common::Protocol> =
+            port_lists.iter().map(|p| p.get_protocol()).collect();
+        assert_eq!(protocols, vec![88, 89]);
+        assert!(port_lists.iter().all(|p| !p.is_l4()));
+    }
+
+    #[test]
+    fn test_icmp_name_without_protocol() {
+        let icmp = ProtocolList::from_str_lenient("ICMP Echo").unwrap();
+        assert_eq!(icmp.get_protocol(), 1);
+        assert_eq!(icmp.get_icmp_type(), Some(8));
+
+        assert!(ProtocolList::from_str("ICMP Echo").is_err());
+        assert!(ProtocolList::from_str_lenient("Unknown service").is_err());
+    }
+
+    #[test]
+    fn test_lenient_protocols_expanded() {
+        set_lenient_protocols(true);
+        let lenient = ProtocolList::from_str_expanded("ICMP Echo");
+        set_lenient_protocols(false);
+        let strict = ProtocolList::from_str_expanded("ICMP Echo");
+
+        assert_eq!(lenient.unwrap()[0].get_protocol(), 1);
+        assert!(strict.is_err());
+    }
+
+    #[test]
+    fn test_all_protocols() {
+        let port_lists = ProtocolList::from_str_expanded("ANY (protocol ip)").unwrap();
+        assert_eq!(port_lists.len(), 1);
+        assert!(port_lists[0].is_all_protocols());
+        assert!(!port_lists[0].is_l4());
+
+        let tcp = ProtocolList::from_str("HTTP (protocol 6, port 80)").unwrap();
+        assert!(!tcp.is_all_protocols());
+    }
+
+    #[test]
+    fn test_protocol_255_is_not_all_protocols() {
+        let port_list = ProtocolList::from_str("RESERVED (protocol 255)").unwrap();
+        assert_eq!(port_list.get_protocol(), 255);
+        assert!(!port_list.is_all_protocols());
+        assert_eq!(port_list.to_string(), "RESERVED (protocol 255)");
+
+        let range = ProtocolList::from_str_expanded("RANGE (protocol 250-255)").unwrap();
+        assert_eq!(range.len(), 6);
+        assert!(range.iter().all(|p| !p.is_all_protocols()));
+    }
+
+    #[test]
+    fn test_ip_protocol_0() {
+        let port_list = ProtocolList::from_str("IP (protocol 0)").unwrap();
+        assert!(matches!(port_list, ProtocolList::OtherProtocol(_)));
+        assert_eq!(port_list.get_protocol(), 0);
+        assert!(!port_list.is_l4());
+        assert!(!port_list.is_all_protocols());
+        assert_eq!(port_list.to_string(), "IP (protocol 0)");
+    }
+
+    #[test]
+    fn test_protocol_range_expanded() {
+        use std::collections::HashSet;
+
+        let port_lists = ProtocolList::from_str_expanded("RANGE (protocol 10-12)").unwrap();
+        assert_eq!(port_lists.len(), 3);
+        assert!(port_lists.iter().all(|p| !p.is_l4()));
+
+        let unique = port_lists.iter().collect::<HashSet<_>>();
+        assert_eq!(unique.len(), 3);
+
+        let protocols = port_lists
+            .iter()
+            .map(|p| p.get_protocol())
+            .collect::<Vec<_>>();
+        assert_eq!(protocols, vec![10, 11, 12]);
+    }
+
     #[test]
     fn test_tcp_port() {
         let port_list = ProtocolList::from_str("protocol 6, port 17444").unwrap();
@@ -232,6 +421,31 @@ mod tests {
         assert_eq!(port_list[1].get_ports(), (8080, 8080));
     }
 
+    #[test]
+    fn from_str_expanded_7() {
+        let port_list =
+            ProtocolList::from_str_expanded("ALL (protocol any, ports 8080-8081)").unwrap();
+        assert_eq!(port_list.len(), 2);
+        assert_eq!(port_list[0].get_protocol(), 6);
+        assert_eq!(port_list[0].get_ports(), (8080, 8081));
+        assert_eq!(port_list[1].get_protocol(), 17);
+        assert_eq!(port_list[1].get_ports(), (8080, 8081));
+    }
+
+    #[test]
+    fn multi_word_names() {
+        let port_list = ProtocolList::from_str("HTTP Proxy (protocol 6, port 8080)").unwrap();
+        assert_eq!(port_list.get_name(), "HTTP Proxy");
+        assert_eq!(port_list.get_ports(), (8080, 8080));
+
+        let port_list = ProtocolList::from_str("Internet Group Mgmt (protocol 2)").unwrap();
+        assert_eq!(port_list.get_name(), "Internet Group Mgmt");
+        assert_eq!(port_list.get_protocol(), 2);
+
+        let port_list = ProtocolList::from_str("Echo Request (protocol 1, type 8)").unwrap();
+        assert_eq!(port_list.get_name(), "Echo Request");
+    }
+
     #[test]
     fn from_str_expanded_empty_1() {
         let port_list = ProtocolList::from_str_expanded("");
@@ -7,7 +7,7 @@ use super::tcp_udp::common;
 #[derive(Debug, Clone)]
 pub struct OtherProtocol {
     name: String,
-    protocol: u8,
+    protocol: common::Protocol,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -33,6 +33,9 @@ impl FromStr for OtherProtocol {
     // Example 2
     // IGMP (protocol 2)
 
+    // Example 3
+    // ANY (protocol ip)
+
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (name, proto_and_ports) = common::parse_name_and_protocol(s)?;
 
@@ -46,17 +49,34 @@ impl FromStr for OtherProtocol {
 }
 
 impl OtherProtocol {
+    /// Expands a protocol range into one entry per protocol number
+    /// "RANGE (protocol 10-12)" -> protocols 10, 11 and 12 named "RANGE"
+    pub fn from_str_range(s: &str) -> Result<Vec<Self>, OtherProtocolError> {
+        let (name, proto_and_ports) = common::parse_name_and_protocol(s)?;
+
+        let (start, end) = common::parse_protocol_range(proto_and_ports)?;
+
+        Ok((start..=end)
+            .map(|protocol| Self {
+                name: name.to_string(),
+                protocol: common::Protocol::Number(protocol),
+            })
+            .collect())
+    }
+
     pub fn get_name(&self) -> &str {
         &self.name
     }
     pub fn is_l4(&self) -> bool {
         false
     }
-    pub fn get_protocol(&self) -> u8 {
+    pub fn get_protocol(&self) -> common::Protocol {
         self.protocol
     }
 }
 
+/// Equality and hash use the protocol number only,
+/// so "LDP (protocol 39)", "ldp (protocol 39)" and "protocol 39" are the same entry
 impl PartialEq for OtherProtocol {
     fn eq(&self, other: &Self) -> bool {
         self.protocol == other.protocol
@@ -91,6 +111,25 @@ mod tests {
         assert_eq!(port_obj.protocol, 2);
     }
 
+    #[test]
+    fn parse_protocol_range() {
+        let input = "RANGE (protocol 10-12)";
+        let port_objs = OtherProtocol::from_str_range(input).unwrap();
+        let protocols = port_objs.iter().map(|p| p.protocol).collect::<Vec<_>>();
+        assert_eq!(protocols, vec![10, 11, 12]);
+        assert!(port_objs.iter().all(|p| p.name == "RANGE"));
+    }
+
+    #[test]
+    fn parse_all_protocols() {
+        let port_obj = OtherProtocol::from_str("ANY (protocol ip)").unwrap();
+        assert_eq!(port_obj.protocol, common::Protocol::Any);
+        assert_eq!(port_obj.to_string(), "ANY (protocol ip)");
+
+        let port_obj = OtherProtocol::from_str("protocol all").unwrap();
+        assert_eq!(port_obj.protocol, common::Protocol::Any);
+    }
+
     #[test]
     fn parse_invalid_format() {
         let input = "invalid format";
@@ -107,7 +146,7 @@ mod tests {
     fn test_display() {
         let port_obj = OtherProtocol {
             name: "IGMP".to_string(),
-            protocol: 2,
+            protocol: common::Protocol::Number(2),
         };
         assert_eq!(port_obj.to_string(), "IGMP (protocol 2)");
     }
@@ -116,8 +155,34 @@ mod tests {
     fn test_is_mergeable() {
         let port_obj = OtherProtocol {
             name: "IGMP".to_string(),
-            protocol: 2,
+            protocol: common::Protocol::Number(2),
         };
         assert!(!port_obj.is_l4());
     }
+
+    #[test]
+    fn test_eq_and_hash_ignore_name() {
+        use std::collections::HashSet;
+        use std::hash::DefaultHasher;
+
+        let hash = |p: &OtherProtocol| {
+            let mut hasher = DefaultHasher::new();
+            p.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let upper = OtherProtocol::from_str("LDP (protocol 39)").unwrap();
+        let lower = OtherProtocol::from_str("ldp (protocol 39)").unwrap();
+        let unnamed = OtherProtocol::from_str("protocol 39").unwrap();
+        let other = OtherProtocol::from_str("LDP (protocol 40)").unwrap();
+
+        assert_eq!(upper, lower);
+        assert_eq!(upper, unnamed);
+        assert_ne!(upper, other);
+        assert_eq!(hash(&upper), hash(&lower));
+        assert_eq!(hash(&upper), hash(&unnamed));
+
+        let unique: HashSet<_> = [&upper, &lower, &unnamed, &other].into_iter().collect();
+        assert_eq!(unique.len(), 2);
+    }
 }
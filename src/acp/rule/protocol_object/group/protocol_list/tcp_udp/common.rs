@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(thiserror::Error, Debug)]
 pub enum CommonError {
     #[error("Failed to parse name and protocol: {0}")]
@@ -15,6 +17,9 @@ pub enum CommonError {
 // Example 3
 // HTTP (protocol 6, port 80-81)
 
+// Example 4
+// HTTP Proxy (protocol 6, port 8080)
+
 pub fn parse_name_and_protocol(s: &str) -> Result<(&str, &str), CommonError> {
     // let mut parts = s.split('(');
     // let name = parts.clone().next().unwrap().trim(); // clone() is needed to avoid consuming the iterator
@@ -23,11 +28,13 @@ pub fn parse_name_and_protocol(s: &str) -> Result<(&str, &str), CommonError> {
 
     // Ok((name, ports))
 
-    let mut parts = s.split('(');
+    // Name may contain spaces and parentheses, e.g. "HTTP Proxy (protocol 6, port 8080)",
+    // so the name is everything before the "(protocol" token
+    let open_idx = s.rfind("(protocol").or_else(|| s.rfind('('));
 
-    match parts.clone().count() {
-        1 => {
-            let name = parts.next().unwrap().trim();
+    match open_idx {
+        None => {
+            let name = s.trim();
             let ports = name;
 
             if name.contains(')') {
@@ -39,9 +46,9 @@ pub fn parse_name_and_protocol(s: &str) -> Result<(&str, &str), CommonError> {
 
             Ok((name, ports))
         }
-        2 => {
-            let name = parts.next().unwrap().trim();
-            let ports = parts.next().unwrap().trim();
+        Some(idx) => {
+            let name = s[..idx].trim();
+            let ports = s[idx + 1..].trim();
 
             if let Some(ports) = ports.strip_suffix(')') {
                 return Ok((name, ports));
@@ -51,33 +58,156 @@ pub fn parse_name_and_protocol(s: &str) -> Result<(&str, &str), CommonError> {
                 s
             )))
         }
-        _ => Err(CommonError::NameAndProtocol(format!(
-            "Invalid port list {}",
-            s
-        ))),
     }
 }
 
-pub fn parse_protocol(s: &str) -> Result<u8, CommonError> {
-    let mut parts = s.split(',');
+/// Protocol of well-known names some dumps write without "(protocol N)"
+const WELL_KNOWN_PROTOCOLS: [(&str, &str); 14] = [
+    ("ICMP", "protocol 1"),
+    ("ICMP Echo", "protocol 1, type 8"),
+    ("ICMP Echo Reply", "protocol 1, type 0"),
+    ("ICMP Unreachable", "protocol 1, type 3"),
+    ("ICMP Time Exceeded", "protocol 1, type 11"),
+    ("ICMPv6", "protocol 58"),
+    ("IGMP", "protocol 2"),
+    ("GRE", "protocol 47"),
+    ("ESP", "protocol 50"),
+    ("AH", "protocol 51"),
+    ("SSH", "protocol 6, port 22"),
+    ("TELNET", "protocol 6, port 23"),
+    ("HTTP", "protocol 6, port 80"),
+    ("HTTPS", "protocol 6, port 443"),
+];
+
+/// Adds the protocol of a well-known name (case-insensitive) if the protocol token is absent
+/// "ICMP Echo" -> "ICMP Echo (protocol 1, type 8)"
+/// Strings with a protocol token and unknown names are returned unchanged
+pub fn with_inferred_protocol(s: &str) -> String {
+    let name = s.trim();
+    if name.contains("protocol") {
+        return s.to_string();
+    }
+
+    WELL_KNOWN_PROTOCOLS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map_or_else(
+            || s.to_string(),
+            |(_, protocol)| format!("{} ({})", name, protocol),
+        )
+}
+
+/// Trims whitespace around commas, so "protocol 6,port 80" and
+/// "protocol 6 , port 80" both become "protocol 6, port 80"
+pub fn normalize_commas(s: &str) -> String {
+    s.split(',').map(str::trim).collect::<Vec<_>>().join(", ")
+}
+
+/// Protocol of a port entry: a protocol number,
+/// or Any for "protocol ip" / "protocol all" entries matching every protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Protocol {
+    Number(u8),
+    Any,
+}
+
+/// "6", "ip" for any protocol
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Protocol::Number(protocol) => write!(f, "{}", protocol),
+            Protocol::Any => write!(f, "ip"),
+        }
+    }
+}
+
+/// A protocol number is equal to the Number variant only
+impl PartialEq<u8> for Protocol {
+    fn eq(&self, other: &u8) -> bool {
+        *self == Protocol::Number(*other)
+    }
+}
+
+impl Protocol {
+    /// Protocol number, None for any protocol
+    pub fn number(&self) -> Option<u8> {
+        match self {
+            Protocol::Number(protocol) => Some(*protocol),
+            Protocol::Any => None,
+        }
+    }
+}
+
+/// "protocol 6, port 80" -> Number(6), "protocol ip" or "protocol all" -> Any
+pub fn parse_protocol(s: &str) -> Result<Protocol, CommonError> {
+    let s = normalize_commas(s);
+    let protocol = protocol_field(&s)?;
+
+    if protocol.eq_ignore_ascii_case("ip") || protocol.eq_ignore_ascii_case("all") {
+        return Ok(Protocol::Any);
+    }
+
+    let (start, end) = parse_protocol_range(&s)?;
+
+    if start != end {
+        return Err(CommonError::Protocol(format!(
+            "Unexpected protocol range {}-{} in {}",
+            start, end, s
+        )));
+    }
+
+    Ok(Protocol::Number(start))
+}
+
+/// Protocol number of entries that can't match any protocol (TCP/UDP ports, ICMP types)
+pub fn parse_protocol_number(s: &str) -> Result<u8, CommonError> {
+    parse_protocol(s)?
+        .number()
+        .ok_or_else(|| CommonError::Protocol(format!("Expected a protocol number in {}", s)))
+}
+
+/// Protocol number or range of protocol numbers
+/// "protocol 6, port 80" -> (6, 6)
+/// "protocol 10-12" -> (10, 12)
+pub fn parse_protocol_range(s: &str) -> Result<(u8, u8), CommonError> {
+    let s = normalize_commas(s);
+    let protocol = protocol_field(&s)?;
+
+    let parse = |value: &str| {
+        value.trim().parse::<u8>().map_err(|_| {
+            CommonError::Protocol(format!("Invalid protocol number {} in {}", protocol, s))
+        })
+    };
+
+    let range = match protocol.split_once('-') {
+        Some((start, end)) => (parse(start)?, parse(end)?),
+        None => (parse(protocol)?, parse(protocol)?),
+    };
 
-    let protocol = parts
+    if range.0 > range.1 {
+        return Err(CommonError::Protocol(format!(
+            "Invalid protocol range {} in {}",
+            protocol, s
+        )));
+    }
+
+    Ok(range)
+}
+
+/// Value of the protocol clause: "protocol 6, port 80" -> "6"
+fn protocol_field(s: &str) -> Result<&str, CommonError> {
+    let protocol = s
+        .split(',')
         .next()
         .ok_or_else(|| CommonError::Protocol(format!("Missing comma in port list ({})", s)))?
         .trim();
 
-    let protocol = protocol
+    Ok(protocol
         .strip_prefix("protocol")
         .ok_or_else(|| {
             CommonError::Protocol(format!("Missing 'protocol' prefix {} in {}", protocol, s))
         })?
-        .trim();
-
-    let protocol_val = protocol.parse().map_err(|_| {
-        CommonError::Protocol(format!("Invalid protocol number {} in {}", protocol, s))
-    })?;
-
-    Ok(protocol_val)
+        .trim())
 }
 
 #[cfg(test)]
@@ -91,6 +221,65 @@ mod tests {
         assert_eq!(protocol, 6);
     }
 
+    #[test]
+    fn test_parse_protocol_no_space_after_comma() {
+        let input = "protocol 6,port 80";
+        let protocol = parse_protocol(input).unwrap();
+        assert_eq!(protocol, 6);
+    }
+
+    #[test]
+    fn test_with_inferred_protocol() {
+        assert_eq!(
+            with_inferred_protocol("ICMP Echo"),
+            "ICMP Echo (protocol 1, type 8)"
+        );
+        assert_eq!(
+            with_inferred_protocol(" icmp echo "),
+            "icmp echo (protocol 1, type 8)"
+        );
+        assert_eq!(
+            with_inferred_protocol("ICMP Echo (protocol 1, type 0)"),
+            "ICMP Echo (protocol 1, type 0)"
+        );
+        assert_eq!(with_inferred_protocol("Unknown"), "Unknown");
+    }
+
+    #[test]
+    fn test_parse_protocol_all() {
+        assert_eq!(parse_protocol("protocol ip").unwrap(), Protocol::Any);
+        assert_eq!(parse_protocol("protocol all").unwrap(), Protocol::Any);
+        assert_eq!(parse_protocol("protocol IP").unwrap(), Protocol::Any);
+        assert_eq!(parse_protocol("protocol 255").unwrap(), 255);
+        assert_ne!(parse_protocol("protocol 255").unwrap(), Protocol::Any);
+
+        assert!(parse_protocol_number("protocol ip").is_err());
+        assert!(parse_protocol_range("protocol ip").is_err());
+        assert_eq!(Protocol::Any.to_string(), "ip");
+        assert_eq!(Protocol::Number(6).to_string(), "6");
+    }
+
+    #[test]
+    fn test_normalize_commas() {
+        assert_eq!(
+            normalize_commas("protocol 6,port 80"),
+            "protocol 6, port 80"
+        );
+        assert_eq!(
+            normalize_commas("protocol 6 , port 80"),
+            "protocol 6, port 80"
+        );
+        assert_eq!(normalize_commas("protocol 6"), "protocol 6");
+    }
+
+    #[test]
+    fn test_parse_protocol_range() {
+        assert_eq!(parse_protocol_range("protocol 10-12").unwrap(), (10, 12));
+        assert_eq!(parse_protocol_range("protocol 6, port 80").unwrap(), (6, 6));
+        assert!(parse_protocol_range("protocol 12-10").is_err());
+        assert!(parse_protocol("protocol 10-12").is_err());
+    }
+
     #[test]
     fn test_parse_protocol_missing_protocol() {
         let input = "6, port 17444";
@@ -129,6 +318,22 @@ mod tests {
         assert_eq!(ports, "protocol 6, port 80-81");
     }
 
+    #[test]
+    fn test_get_name_and_ports_multi_word_name() {
+        let input = "HTTP Proxy (protocol 6, port 8080)";
+        let (name, ports) = parse_name_and_protocol(input).unwrap();
+        assert_eq!(name, "HTTP Proxy");
+        assert_eq!(ports, "protocol 6, port 8080");
+    }
+
+    #[test]
+    fn test_get_name_and_ports_name_with_parenthesis() {
+        let input = "Web Proxy (alt) (protocol 6, port 8080-8081)";
+        let (name, ports) = parse_name_and_protocol(input).unwrap();
+        assert_eq!(name, "Web Proxy (alt)");
+        assert_eq!(ports, "protocol 6, port 8080-8081");
+    }
+
     #[test]
     fn test_get_name_and_ports_missing_closing_parenthesis() {
         let input = "HTTP (protocol 6, port 80-81";
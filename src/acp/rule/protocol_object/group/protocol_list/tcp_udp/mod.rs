@@ -53,10 +53,13 @@ impl FromStr for TcpUdp {
     // Example 4
     // HTTP (protocol 6)
 
+    // Example 5
+    // HTTP (protocol 6, ports 80-81)
+
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (name, proto_and_ports) = common::parse_name_and_protocol(s)?;
 
-        let protocol = common::parse_protocol(proto_and_ports)?;
+        let protocol = common::parse_protocol_number(proto_and_ports)?;
 
         let (start, end) = parse_ports(proto_and_ports)?;
 
@@ -69,14 +72,26 @@ impl FromStr for TcpUdp {
     }
 }
 
+/// Port range of the entry
+/// "protocol 6" -> (0, 65535), no port means any port
+/// "protocol 6, port 0" -> (0, 0), explicit port 0 is a single port
+/// "protocol 6, port" -> error, the port keyword requires a value
 fn parse_ports(s: &str) -> Result<(u16, u16), TcpUdpError> {
-    let mut parts = s.split("port");
+    let s = common::normalize_commas(s);
 
-    let ports = match parts.nth(1) {
-        Some(ports) => ports.trim(),
+    // port token follows the comma, e.g. "port 80-81"
+    let ports = match s.split(", ").nth(1) {
+        Some(ports) => ports,
         None => return Ok((0, 65535)),
     };
 
+    // both "port" and "ports" keywords are accepted
+    let ports = ports
+        .strip_prefix("ports")
+        .or_else(|| ports.strip_prefix("port"))
+        .ok_or_else(|| TcpUdpError::General(format!("Missing 'port' keyword ({})", s)))?
+        .trim();
+
     let mut split = ports.split('-');
 
     let start = split
@@ -114,6 +129,15 @@ impl TcpUdp {
     pub fn get_ports(&self) -> (u16, u16) {
         (self.start, self.end)
     }
+
+    /// Same entry restricted to another port range, e.g. the ports left after an exclusion
+    pub fn with_ports(&self, start: u16, end: u16) -> TcpUdp {
+        TcpUdp {
+            start,
+            end,
+            ..self.clone()
+        }
+    }
 }
 
 impl PartialEq for TcpUdp {
@@ -150,6 +174,54 @@ mod tests {
         assert_eq!(ports, (17444, 17445));
     }
 
+    #[test]
+    fn test_parse_ports_plural_single_port() {
+        let input = "protocol 6, ports 80";
+        let ports = parse_ports(input).unwrap();
+        assert_eq!(ports, (80, 80));
+    }
+
+    #[test]
+    fn test_parse_ports_plural_range() {
+        let input = "protocol 6, ports 80-81";
+        let ports = parse_ports(input).unwrap();
+        assert_eq!(ports, (80, 81));
+    }
+
+    #[test]
+    fn test_named_port_range_plural() {
+        let input = "TCP (protocol 6, ports 80-81)";
+        let port_list = input.parse::<TcpUdp>().unwrap();
+        assert_eq!(port_list.name, "TCP");
+        assert_eq!(port_list.protocol, 6);
+        assert_eq!(port_list.start, 80);
+        assert_eq!(port_list.end, 81);
+    }
+
+    #[test]
+    fn test_parse_ports_no_space_after_comma() {
+        let input = "protocol 6,port 80";
+        let ports = parse_ports(input).unwrap();
+        assert_eq!(ports, (80, 80));
+    }
+
+    #[test]
+    fn test_parse_ports_space_before_comma() {
+        let input = "protocol 6 , port 80";
+        let ports = parse_ports(input).unwrap();
+        assert_eq!(ports, (80, 80));
+    }
+
+    #[test]
+    fn test_named_port_no_space_after_comma() {
+        let input = "HTTP (protocol 6,port 80-81)";
+        let port_list = input.parse::<TcpUdp>().unwrap();
+        assert_eq!(port_list.name, "HTTP");
+        assert_eq!(port_list.protocol, 6);
+        assert_eq!(port_list.start, 80);
+        assert_eq!(port_list.end, 81);
+    }
+
     #[test]
     fn test_parse_ports_missing_ports() {
         let input = "protocol 6";
@@ -157,6 +229,22 @@ mod tests {
         assert_eq!(ports, (0, 65535));
     }
 
+    #[test]
+    fn test_parse_ports_explicit_port_0() {
+        assert_eq!(parse_ports("protocol 6, port 0").unwrap(), (0, 0));
+        assert_eq!(parse_ports("protocol 6, port 0-0").unwrap(), (0, 0));
+        assert_ne!(
+            parse_ports("protocol 6, port 0").unwrap(),
+            parse_ports("protocol 6").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_ports_keyword_without_value() {
+        assert!(parse_ports("protocol 6, port").is_err());
+        assert!(parse_ports("protocol 6,").is_err());
+    }
+
     #[test]
     fn test_parse_ports_invalid_ports() {
         let input = "protocol 6, port 17444-";
@@ -225,6 +313,16 @@ mod tests {
         assert_eq!(port_list.end, 65535);
     }
 
+    #[test]
+    fn test_explicit_port_0_is_not_any() {
+        let port_0 = "TCP-0 (protocol 6, port 0)".parse::<TcpUdp>().unwrap();
+        let any = "TCP (protocol 6)".parse::<TcpUdp>().unwrap();
+
+        assert_eq!(port_0.get_ports(), (0, 0));
+        assert_eq!(any.get_ports(), (0, 65535));
+        assert_ne!(port_0, any);
+    }
+
     #[test]
     fn test_invalid_port_range() {
         let input = "HTTP (protocol 6, port 81-)";
@@ -1,7 +1,12 @@
+use std::str::FromStr;
+
 mod group;
 use group::protocol_list::{self, ProtocolList};
 use group::Group;
 
+pub use group::protocol_list::set_lenient_protocols;
+pub use group::protocol_list::tcp_udp::common::Protocol;
+
 use super::network_object::utilities;
 
 pub mod protocol_list_optimized;
@@ -11,13 +16,25 @@ mod protocol_object_item;
 use protocol_object_item::ProtocolObjectItem;
 
 pub mod description;
+use description::DescriptionType;
 
-#[derive(Debug)]
+use super::explanation::{self, OptimizationStep, SectionExplanation};
+
+#[derive(Debug, Clone)]
 pub struct ProtocolObject {
     _name: String,
     items: Vec<ProtocolObjectItem>,
 }
 
+/// Optimizations FTD doesn't perform, the result is for reporting only
+/// and is not used for the capacity
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OptimizeOptions {
+    /// TCP and UDP entries with exactly the same port range are reported as one
+    /// "protocol any" entry
+    pub merge_tcp_udp: bool,
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum PortObjectError {
     #[error("Failed to parse port object: {0}")]
@@ -65,12 +82,89 @@ impl ProtocolObject {
     /// Optimizes all PortLists inside the PortObject.
     /// Those optimizations automatically performed by FTD
     pub fn optimize(&self) -> Vec<ProtocolListOptimized> {
+        self.optimize_with(OptimizeOptions::default())
+    }
+
+    /// Optimizes the protocol object, with the opt-in optimizations of the options on top
+    pub fn optimize_with(&self, options: OptimizeOptions) -> Vec<ProtocolListOptimized> {
+        let optimized = self.optimize_with_steps(&mut vec![]);
+
+        match options.merge_tcp_udp {
+            true => merge_tcp_udp(optimized),
+            false => optimized,
+        }
+    }
+
+    /// Optimizes the protocol object and reports every merge of L4 port ranges.
+    /// Capacity of the section is the number of entries before and after optimization.
+    pub fn explain(&self) -> SectionExplanation {
+        let mut steps = vec![];
+        let optimized = self.optimize_with_steps(&mut steps);
+
+        SectionExplanation {
+            name: self._name.clone(),
+            capacity: self
+                .items
+                .iter()
+                .map(|item| item.collect_objects().len() as u64)
+                .sum(),
+            optimized_capacity: optimized.len() as u64,
+            steps,
+        }
+    }
+
+    /// Some negated entry still overlaps the optimized entries: it was left out of "protocol ip"
+    /// (or an object of negated entries only) or is narrower than the entry it negates,
+    /// e.g. "Echo (protocol 1, type 8) negate" of "ICMP (protocol 1)".
+    /// The optimized entries then match more than the object, which is fine for the capacity
+    /// but not for an exported policy
+    pub fn has_unapplied_exclusions(&self) -> bool {
+        let optimized = self.optimize();
+
+        self.items
+            .iter()
+            .flat_map(|item| item.collect_exclusions())
+            .map(ProtocolListOptimized::from)
+            .any(|excluded| optimized.iter().any(|item| item.overlaps(&excluded)))
+    }
+
+    /// No traffic is matched by both objects: no common L3 protocol and no intersecting
+    /// port ranges of the same L4 protocol (see ProtocolListOptimized::overlaps)
+    pub fn disjoint_from(&self, other: &ProtocolObject) -> bool {
+        let other = other.optimize();
+
+        !self
+            .optimize()
+            .iter()
+            .any(|item| other.iter().any(|other_item| item.overlaps(other_item)))
+    }
+
+    fn optimize_with_steps(&self, steps: &mut Vec<OptimizationStep>) -> Vec<ProtocolListOptimized> {
         let protocol_lists: Vec<&ProtocolList> = self
             .items
             .iter()
             .flat_map(|item| item.collect_objects())
             .collect();
 
+        let exclusions: Vec<&ProtocolList> = self
+            .items
+            .iter()
+            .flat_map(|item| item.collect_exclusions())
+            .collect();
+
+        // "protocol ip" matches every protocol, all other entries are shadowed by it.
+        // Exclusions can't be cut out of it, so they don't reduce the entry
+        if let Some(all_protocols) = protocol_lists.iter().find(|p| p.is_all_protocols()) {
+            return vec![ProtocolListOptimized::from(all_protocols)];
+        }
+
+        // only negated entries: everything except them is matched, which is "protocol ip"
+        if protocol_lists.is_empty() && !exclusions.is_empty() {
+            let all_protocols = ProtocolList::from_str(ALL_PROTOCOLS_ENTRY)
+                .expect("Logic error: ALL_PROTOCOLS_ENTRY must be a valid entry");
+            return vec![ProtocolListOptimized::from(&all_protocols)];
+        }
+
         let l3_items: Vec<&ProtocolList> = protocol_lists
             .iter()
             .filter(|port_list| !port_list.is_l4())
@@ -88,15 +182,124 @@ impl ProtocolObject {
             .filter(|port_list| port_list.is_l4())
             .copied()
             .collect();
-        let optimized_l4 = optimize_l4_items(l4_items);
+        let optimized_l4 = optimize_l4_items(l4_items, steps);
 
-        unique_l3_items
+        let optimized = unique_l3_items
             .into_iter()
             .chain(optimized_l4)
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>();
+
+        apply_exclusions(optimized, &exclusions, steps)
     }
 }
 
+/// Entry standing for an object of negated entries only
+const ALL_PROTOCOLS_ENTRY: &str = "ANY (protocol ip)";
+
+/// Removes negated entries from the optimized ones:
+/// L4 port ranges are split around the excluded ports, e.g.
+/// HTTP-RANGE (protocol 6, port 80-90) EXCLUDES HTTP-ALT (protocol 6, port 85)
+/// -> protocol 6, port 80-84 and protocol 6, port 86-90,
+/// L3 entries are dropped when the excluded entry covers them (same protocol, ICMP type and code or unset).
+/// Every affected entry is reported as an EXCLUDES step
+fn apply_exclusions(
+    optimized: Vec<ProtocolListOptimized>,
+    exclusions: &[&ProtocolList],
+    steps: &mut Vec<OptimizationStep>,
+) -> Vec<ProtocolListOptimized> {
+    let mut result = optimized;
+
+    for excluded in exclusions {
+        result = result
+            .into_iter()
+            .flat_map(|item| exclude(item, excluded, steps))
+            .collect();
+    }
+
+    result
+}
+
+fn exclude(
+    item: ProtocolListOptimized,
+    excluded: &ProtocolList,
+    steps: &mut Vec<OptimizationStep>,
+) -> Vec<ProtocolListOptimized> {
+    if item.get_protocol() != excluded.get_protocol() {
+        return vec![item];
+    }
+
+    let remaining = if excluded.is_l4() {
+        let (start, end) = item.get_ports();
+        let (excluded_start, excluded_end) = excluded.get_ports();
+        if excluded_end < start || end < excluded_start {
+            return vec![item];
+        }
+
+        let name = format!(
+            "{} {} {}",
+            item.get_name(),
+            DescriptionType::Excludes,
+            excluded.get_name()
+        );
+        let mut remaining = vec![];
+        if start < excluded_start {
+            remaining.push(item.with_ports(start, excluded_start - 1));
+        }
+        if excluded_end < end {
+            remaining.push(item.with_ports(excluded_end + 1, end));
+        }
+        remaining.iter_mut().for_each(|r| r.set_name(name.clone()));
+        remaining
+    } else {
+        let covers =
+            |excluded: Option<u8>, item: Option<u8>| excluded.is_none() || excluded == item;
+        if !covers(excluded.get_icmp_type(), item.get_icmp_type())
+            || !covers(excluded.get_icmp_code(), item.get_icmp_code())
+        {
+            return vec![item];
+        }
+        vec![]
+    };
+
+    steps.push(OptimizationStep {
+        merged: item.get_name().to_string(),
+        verb: DescriptionType::Excludes,
+        next: excluded.get_name().to_string(),
+        capacity: remaining.len() as u64,
+    });
+
+    remaining
+}
+
+/// Combines every TCP entry with the UDP entry of the same port range, for example:
+/// HTTP (protocol 6, port 80-82) + HTTP-UDP (protocol 17, port 80-82)
+/// -> HTTP AND HTTP-UDP (protocol any, port 80-82)
+/// Optimized entries are sorted by protocol, so TCP entries come before UDP ones
+fn merge_tcp_udp(optimized: Vec<ProtocolListOptimized>) -> Vec<ProtocolListOptimized> {
+    let mut result: Vec<ProtocolListOptimized> = vec![];
+
+    for item in optimized {
+        let tcp_twin = result.iter_mut().find(|tcp| {
+            tcp.get_protocol() == TCP
+                && !tcp.is_tcp_and_udp()
+                && tcp.get_ports() == item.get_ports()
+        });
+
+        match tcp_twin {
+            Some(tcp) if item.get_protocol() == UDP => {
+                tcp.set_name(format!("{} AND {}", tcp.get_name(), item.get_name()));
+                tcp.absorb(item);
+            }
+            _ => result.push(item),
+        }
+    }
+
+    result
+}
+
+const TCP: u8 = 6;
+const UDP: u8 = 17;
+
 /// Get the next object from input lines (either Group or PortList) and the number of lines to consume.
 fn get_object(lines: &[String]) -> Result<(Vec<ProtocolObjectItem>, usize), PortObjectError> {
     if lines.is_empty() {
@@ -107,9 +310,16 @@ fn get_object(lines: &[String]) -> Result<(Vec<ProtocolObjectItem>, usize), Port
 
     let first_line = lines[0].as_str();
     if first_line.contains("(group)") {
-        let lines_in_group = utilities::calculate_lines_in_group(lines)?;
+        let lines_in_group = utilities::calculate_lines_in_nested_group(lines)?;
         let group = Group::try_from(&lines[0..lines_in_group].to_vec())?;
         Ok((vec![ProtocolObjectItem::Group(group)], lines_in_group))
+    } else if let Some(negated) = group::strip_negation(first_line) {
+        let port_list = ProtocolList::from_str_expanded(negated)?;
+        let port_list = port_list
+            .into_iter()
+            .map(ProtocolObjectItem::Excluded)
+            .collect::<Vec<_>>();
+        Ok((port_list, 1))
     } else {
         let port_list = ProtocolList::from_str_expanded(first_line)?;
         let port_list = port_list
@@ -120,21 +330,40 @@ fn get_object(lines: &[String]) -> Result<(Vec<ProtocolObjectItem>, usize), Port
     }
 }
 
+/// Duplicates are named after the first name in name_order and the result is sorted by protocol,
+/// so the output doesn't depend on the order of the input
 fn unique_l3_items(port_lists: Vec<&ProtocolList>) -> Vec<&ProtocolList> {
-    let unique_items = port_lists
-        .iter()
-        .collect::<std::collections::HashSet<_>>()
-        .into_iter()
-        .copied()
-        .collect();
+    let mut unique_items = port_lists;
+    unique_items.sort_by(|a, b| {
+        l3_key(a)
+            .cmp(&l3_key(b))
+            .then_with(|| explanation::name_order(a.get_name(), b.get_name()))
+    });
+    unique_items.dedup();
 
     unique_items
 }
 
-fn optimize_l4_items(to_optimize: Vec<&ProtocolList>) -> Vec<ProtocolListOptimized> {
+/// Fields compared by the equality of L3 entries
+fn l3_key(port_list: &ProtocolList) -> (Protocol, Option<u8>, Option<u8>) {
+    (
+        port_list.get_protocol(),
+        port_list.get_icmp_type(),
+        port_list.get_icmp_code(),
+    )
+}
+
+fn optimize_l4_items(
+    to_optimize: Vec<&ProtocolList>,
+    steps: &mut Vec<OptimizationStep>,
+) -> Vec<ProtocolListOptimized> {
     let mut to_optimize = to_optimize;
-    to_optimize
-        .sort_by_key(|item| ((item.get_protocol() as u32) << 16) + item.get_ports().0 as u32);
+    // entries with the same start are ordered by the end and the name, so the merged name is stable
+    to_optimize.sort_by(|a, b| {
+        (a.get_protocol(), a.get_ports())
+            .cmp(&(b.get_protocol(), b.get_ports()))
+            .then_with(|| explanation::name_order(a.get_name(), b.get_name()))
+    });
 
     let mut result = vec![];
 
@@ -151,14 +380,18 @@ fn optimize_l4_items(to_optimize: Vec<&ProtocolList>) -> Vec<ProtocolListOptimiz
 
             if next_start as u32 <= curr_end as u32 + 1 {
                 let verb = description::verb(curr_end as u32, next_start as u32, next_end as u32);
-                let new_name = format!(
-                    "{} {verb} {}",
-                    optimized_items.get_name(),
-                    next_item.get_name()
-                );
+                let merged = optimized_items.get_name().to_string();
+                let new_name = format!("{merged} {verb} {}", next_item.get_name());
 
                 optimized_items.append(next_item);
                 optimized_items.set_name(new_name);
+
+                steps.push(OptimizationStep {
+                    merged,
+                    verb,
+                    next: next_item.get_name().to_string(),
+                    capacity: 1,
+                });
             } else {
                 result.push(optimized_items);
                 optimized_items = ProtocolListOptimized::from(next_item);
@@ -186,6 +419,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn optimize_all_protocols_shadows_others() {
+        let lines = vec![
+            "Destination Ports     : HTTP (protocol 6, port 80)".to_string(),
+            "ANY (protocol ip)".to_string(),
+            "IGMP (protocol 2)".to_string(),
+        ];
+        let protocol_object = ProtocolObject::try_from(&lines).unwrap();
+        let optimized = protocol_object.optimize();
+
+        assert_eq!(optimized.len(), 1);
+        assert!(optimized[0].is_all_protocols());
+        assert_eq!(optimized[0].to_string(), "ANY (protocol ip)");
+    }
+
     #[test]
     fn test_empty_input() {
         let lines: Vec<String> = vec![];
@@ -213,6 +461,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_group_with_tab_indented_ports() {
+        let lines = vec![
+            "\tDestination Ports     : HTTP-HTTPS_1 (group)".to_string(),
+            "\t\t\tHTTP (protocol 6, port 80)".to_string(),
+            "\t\t\tHTTPS (protocol 6, port 443)".to_string(),
+            "\t\tTCP-8080 (protocol 6, port 8080)".to_string(),
+        ];
+        let port_object = ProtocolObject::try_from(&lines).unwrap();
+        assert_eq!(port_object.items.len(), 2);
+        match &port_object.items[0] {
+            ProtocolObjectItem::Group(group) => {
+                assert_eq!(group.port_lists.len(), 2);
+            }
+            _ => panic!("Expected Group"),
+        }
+    }
+
     #[test]
     fn test_group_with_ports() {
         let lines = vec![
@@ -272,6 +538,190 @@ mod tests {
         }
     }
 
+    fn ports(lines: &[&str]) -> ProtocolObject {
+        let lines = lines.iter().map(|line| line.to_string()).collect();
+        ProtocolObject::try_from(&lines).unwrap()
+    }
+
+    #[test]
+    fn test_disjoint_from() {
+        let tcp_80_90 = ports(&["Destination Ports     : protocol 6, port 80-90"]);
+        let tcp_100_110 = ports(&["Destination Ports     : protocol 6, port 100-110"]);
+        let tcp_85_95 = ports(&["Destination Ports     : protocol 6, port 85-95"]);
+        let udp_80_90 = ports(&["Destination Ports     : protocol 17, port 80-90"]);
+
+        assert!(tcp_80_90.disjoint_from(&tcp_100_110));
+        assert!(!tcp_80_90.disjoint_from(&tcp_85_95));
+        assert!(!tcp_85_95.disjoint_from(&tcp_80_90));
+        assert!(tcp_80_90.disjoint_from(&udp_80_90));
+    }
+
+    #[test]
+    fn test_disjoint_from_l3() {
+        let igmp = ports(&["Destination Ports     : IGMP (protocol 2)"]);
+        let any = ports(&["Destination Ports     : ANY (protocol ip)"]);
+        let echo = ports(&["Destination Ports     : Echo (protocol 1, type 8)"]);
+        let reply = ports(&["Destination Ports     : Reply (protocol 1, type 0)"]);
+        let icmp = ports(&["Destination Ports     : ICMP (protocol 1)"]);
+        let tcp = ports(&[
+            "Destination Ports     : TCP-8080 (protocol 6, port 8080)",
+            "IGMP (protocol 2)",
+        ]);
+
+        assert!(!igmp.disjoint_from(&tcp));
+        assert!(!any.disjoint_from(&echo));
+        assert!(echo.disjoint_from(&reply));
+        assert!(!echo.disjoint_from(&icmp));
+        assert!(echo.disjoint_from(&igmp));
+    }
+
+    #[test]
+    fn test_negated_port_parsed_as_exclusion() {
+        let port_object = ports(&[
+            "Destination Ports     : WEB (group)",
+            "  HTTP (protocol 6, port 80)",
+            "  HTTPS (protocol 6, port 443) negate",
+            "SSH (protocol 6, port 22) negated",
+            "Negate (protocol 6, port 8080)",
+            "Negate-Tunnel (protocol 47)",
+        ]);
+
+        let exclusions: Vec<String> = port_object
+            .items
+            .iter()
+            .flat_map(|item| item.collect_exclusions())
+            .map(|port_list| port_list.to_string())
+            .collect();
+        assert_eq!(
+            exclusions,
+            vec!["HTTPS (protocol 6, port 443)", "SSH (protocol 6, port 22)"]
+        );
+        let positive: Vec<&ProtocolList> = port_object
+            .items
+            .iter()
+            .flat_map(|item| item.collect_objects())
+            .collect();
+        assert_eq!(positive.len(), 3);
+    }
+
+    #[test]
+    fn test_negated_port_reduces_factor() {
+        let port_object = ports(&[
+            "Destination Ports     : HTTP (protocol 6, port 80)",
+            "HTTPS (protocol 6, port 443)",
+            "HTTPS (protocol 6, port 443) negate",
+        ]);
+        assert_eq!(port_object.capacity(), 1);
+
+        let explanation = port_object.explain();
+        assert_eq!(explanation.capacity, 2);
+        assert_eq!(explanation.optimized_capacity, 1);
+        assert_eq!(explanation.steps.len(), 1);
+        assert_eq!(explanation.steps[0].verb, DescriptionType::Excludes);
+        assert_eq!(explanation.steps[0].capacity, 0);
+    }
+
+    #[test]
+    fn test_negated_port_splits_range() {
+        let port_object = ports(&[
+            "Destination Ports     : HIGH (protocol 6, port 1024-2048)",
+            "ALT (protocol 6, port 1500-1600) negate",
+            "Echo (protocol 1, type 8)",
+            "ICMP (protocol 1) negate",
+        ]);
+        let optimized: Vec<String> = port_object
+            .optimize()
+            .iter()
+            .map(|item| item.to_string())
+            .collect();
+        assert_eq!(
+            optimized,
+            vec![
+                "HIGH EXCLUDES ALT (protocol 6, port 1024-1499)",
+                "HIGH EXCLUDES ALT (protocol 6, port 1601-2048)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_negated_only_matches_all_protocols() {
+        let port_object = ports(&["Destination Ports     : HTTP (protocol 6, port 80) negate"]);
+        let optimized = port_object.optimize();
+        assert_eq!(optimized.len(), 1);
+        assert!(optimized[0].is_all_protocols());
+        assert!(port_object.has_unapplied_exclusions());
+    }
+
+    #[test]
+    fn test_unapplied_exclusions() {
+        let split = ports(&[
+            "Destination Ports     : HIGH (protocol 6, port 1024-2048)",
+            "ALT (protocol 6, port 1500-1600) negate",
+        ]);
+        assert!(!split.has_unapplied_exclusions());
+
+        let all = ports(&[
+            "Destination Ports     : ANY (protocol ip)",
+            "HTTP (protocol 6, port 80) negate",
+        ]);
+        assert!(all.has_unapplied_exclusions());
+
+        let narrower = ports(&[
+            "Destination Ports     : ICMP (protocol 1)",
+            "Echo (protocol 1, type 8) negate",
+        ]);
+        assert!(narrower.has_unapplied_exclusions());
+    }
+
+    #[test]
+    fn test_nested_group_too_deep() {
+        let lines = vec![
+            "Destination Ports     : Web (group)".to_string(),
+            "  HTTP (protocol 6, port 80)".to_string(),
+            "  TLS (group)".to_string(),
+            "    HTTPS (protocol 6, port 443)".to_string(),
+            "    Mail (group)".to_string(),
+            "      IMAPS (protocol 6, port 993)".to_string(),
+        ];
+        assert_eq!(ProtocolObject::try_from(&lines).unwrap().capacity(), 3);
+
+        utilities::set_max_group_depth(2);
+        let result = ProtocolObject::try_from(&lines);
+        utilities::set_max_group_depth(utilities::DEFAULT_MAX_GROUP_DEPTH);
+
+        assert!(matches!(
+            result,
+            Err(PortObjectError::GroupError(
+                group::GroupError::NestingTooDeep(_, 2)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_source_ports_group_before_inline() {
+        let lines = vec![
+            "    Source Ports          : SvcGrp (group)".to_string(),
+            "                              SSH (protocol 6, port 22)".to_string(),
+            "                            TCP-8080 (protocol 6, port 8080)".to_string(),
+        ];
+        let port_object = ProtocolObject::try_from(&lines).unwrap();
+        assert_eq!(port_object._name, "Source Ports");
+        assert_eq!(port_object.items.len(), 2);
+        match &port_object.items[0] {
+            ProtocolObjectItem::Group(group) => {
+                assert_eq!(group._name, "SvcGrp");
+                assert_eq!(group.port_lists.len(), 1);
+            }
+            _ => panic!("Expected Group"),
+        }
+        match &port_object.items[1] {
+            ProtocolObjectItem::ProtocolList(port_list) => {
+                assert_eq!(port_list.to_string(), "TCP-8080 (protocol 6, port 8080)");
+            }
+            _ => panic!("Expected PortList"),
+        }
+    }
+
     #[test]
     fn test_port_object_capacity_single_port_list() {
         let lines = vec!["Destination Ports     : TCP-8080 (protocol 6, port 8080)".to_string()];
@@ -308,6 +758,31 @@ mod tests {
         assert_eq!(port_object.capacity(), 0); // No ports
     }
 
+    #[test]
+    fn test_port_object_capacity_header_with_blank_lines() {
+        let lines = vec![
+            "Destination Ports     : HTTP-HTTPS_1 (group)".to_string(),
+            "".to_string(),
+            "    ".to_string(),
+        ];
+        let port_object = ProtocolObject::try_from(&lines).unwrap();
+        assert!(port_object.optimize().is_empty());
+        assert_eq!(port_object.capacity(), 0);
+    }
+
+    #[test]
+    fn test_port_object_blank_lines_after_items() {
+        let lines = vec![
+            "Destination Ports     : HTTP (protocol 6, port 80)".to_string(),
+            "HTTPS (protocol 6, port 443)".to_string(),
+            "".to_string(),
+            "    ".to_string(),
+        ];
+        let port_object = ProtocolObject::try_from(&lines).unwrap();
+        assert_eq!(port_object.items.len(), 2);
+        assert_eq!(port_object.capacity(), 2);
+    }
+
     #[test]
     fn test_port_object_capacity_mixed_ports_and_ranges() {
         let lines = vec![
@@ -365,6 +840,68 @@ mod tests {
         assert_eq!(l3_items.len(), 5);
     }
 
+    #[test]
+    fn test_l4_same_start_ordered_by_end() {
+        let entries = [
+            "WIDE (protocol 6, port 8000-8100)",
+            "NARROW (protocol 6, port 8000-8010)",
+        ];
+        let merged = |entries: Vec<&str>| {
+            let lines: Vec<String> =
+                std::iter::once("Destination Ports     : SSH (protocol 6, port 22)")
+                    .chain(entries)
+                    .map(|line| line.to_string())
+                    .collect();
+            let mut steps = vec![];
+            let optimized = ProtocolObject::try_from(&lines)
+                .unwrap()
+                .optimize_with_steps(&mut steps);
+            (optimized[1].get_name().to_string(), steps.len())
+        };
+
+        let forward = merged(entries.to_vec());
+        assert_eq!(forward, merged(entries.iter().rev().copied().collect()));
+        assert_eq!(forward, ("NARROW PARTIALLY OVERLAPS WIDE".to_string(), 1));
+    }
+
+    #[test]
+    fn test_optimized_names_stable_across_orderings() {
+        let entries = [
+            "ldp (protocol 39)",
+            "LDP (protocol 39)",
+            "PIM (protocol 103)",
+            "http (protocol 6, port 80)",
+            "HTTP (protocol 6, port 80)",
+            "WEB (protocol 6, port 80-81)",
+        ];
+        let names = |entries: Vec<&str>| {
+            let lines: Vec<String> = std::iter::once("Destination Ports     : IGMP (protocol 2)")
+                .chain(entries)
+                .map(|line| line.to_string())
+                .collect();
+            let port_object = ProtocolObject::try_from(&lines).unwrap();
+            port_object
+                .optimize()
+                .iter()
+                .map(|p| p.get_name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let forward = names(entries.to_vec());
+        let backward = names(entries.iter().rev().copied().collect());
+
+        assert_eq!(forward, backward);
+        assert_eq!(
+            forward,
+            vec![
+                "IGMP",
+                "LDP",
+                "PIM",
+                "HTTP SHADOWS http PARTIALLY OVERLAPS WEB",
+            ]
+        );
+    }
+
     #[test]
     fn test_port_object_unique_l3_items_duplicates_2() {
         let lines = vec![
@@ -572,6 +1109,19 @@ mod tests {
         assert_eq!(ProtocolObject::try_from(&lines).unwrap().capacity(), 6);
     }
 
+    #[test]
+    fn test_port_object_capacity_ip_protocol_0() {
+        let lines = vec![
+            "Destination Ports     : IP (protocol 0)".to_string(),
+            "protocol 0".to_string(),
+            "IGMP (protocol 2)".to_string(),
+        ];
+        let port_object = ProtocolObject::try_from(&lines).unwrap();
+        // both "protocol 0" entries are one L3 item, it doesn't shadow IGMP as "protocol ip" does
+        assert_eq!(port_object.capacity(), 2);
+        assert_eq!(port_object.explain().capacity, 3);
+    }
+
     #[test]
     fn test_port_object_capacity_l3_items_duplicate() {
         let lines = vec![
@@ -792,8 +1342,17 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let mut steps = vec![];
+        let optimized = optimize_l4_items(port_lists, &mut steps);
         assert_eq!(optimized.len(), 1);
+        // the shadowed range doesn't extend the merged one
+        assert_eq!(optimized[0].get_ports(), (80, 82));
+        assert_eq!(optimized[0].get_name(), "HTTP SHADOWS HTTP2");
+
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].verb, description::DescriptionType::Shadows);
+        assert_eq!(steps[0].merged, "HTTP");
+        assert_eq!(steps[0].next, "HTTP2");
     }
 
     #[test]
@@ -812,8 +1371,13 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let mut steps = vec![];
+        let optimized = optimize_l4_items(port_lists, &mut steps);
         assert_eq!(optimized.len(), 2);
+        assert!(steps
+            .iter()
+            .all(|step| step.verb == description::DescriptionType::Shadows));
+        assert_eq!(steps.len(), 2);
     }
 
     #[test]
@@ -832,8 +1396,15 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let mut steps = vec![];
+        let optimized = optimize_l4_items(port_lists, &mut steps);
         assert_eq!(optimized.len(), 2);
+        assert_eq!(optimized[0].get_ports(), (0, 65535));
+        // "any port" starts at 0, so it is merged first and shadows the narrower range
+        assert_eq!(optimized[0].get_name(), "TCP SHADOWS HTTP2");
+        assert!(steps
+            .iter()
+            .all(|step| step.verb == description::DescriptionType::Shadows));
     }
 
     #[test]
@@ -850,7 +1421,7 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let optimized = optimize_l4_items(port_lists, &mut vec![]);
         assert_eq!(optimized.len(), 1);
     }
 
@@ -870,7 +1441,7 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let optimized = optimize_l4_items(port_lists, &mut vec![]);
         assert_eq!(optimized.len(), 2);
     }
 
@@ -888,7 +1459,7 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let optimized = optimize_l4_items(port_lists, &mut vec![]);
         assert_eq!(optimized.len(), 1);
     }
 
@@ -907,7 +1478,7 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let optimized = optimize_l4_items(port_lists, &mut vec![]);
         assert_eq!(optimized.len(), 1);
     }
 
@@ -929,7 +1500,7 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let optimized = optimize_l4_items(port_lists, &mut vec![]);
         assert_eq!(optimized.len(), 2);
     }
 
@@ -943,7 +1514,7 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let optimized = optimize_l4_items(port_lists, &mut vec![]);
         assert_eq!(optimized.len(), 0);
     }
 
@@ -964,7 +1535,7 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let optimized = optimize_l4_items(port_lists, &mut vec![]);
         assert_eq!(optimized.len(), 3);
     }
 
@@ -987,7 +1558,7 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let optimized = optimize_l4_items(port_lists, &mut vec![]);
         assert_eq!(optimized.len(), 5);
     }
 
@@ -1012,7 +1583,7 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let optimized = optimize_l4_items(port_lists, &mut vec![]);
         assert_eq!(optimized.len(), 5);
     }
 
@@ -1038,7 +1609,7 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let optimized = optimize_l4_items(port_lists, &mut vec![]);
         assert_eq!(optimized.len(), 5);
     }
 
@@ -1057,7 +1628,7 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let optimized = optimize_l4_items(port_lists, &mut vec![]);
         assert_eq!(optimized.len(), 1);
     }
 
@@ -1077,7 +1648,7 @@ mod tests {
             .flat_map(|item| item.collect_objects())
             .collect();
 
-        let optimized = optimize_l4_items(port_lists);
+        let optimized = optimize_l4_items(port_lists, &mut vec![]);
         assert_eq!(optimized.len(), 2);
     }
 
@@ -1119,4 +1690,57 @@ mod tests {
         dbg!(&port_object);
         assert_eq!(port_object.capacity(), 4);
     }
+
+    #[test]
+    fn test_merge_tcp_udp() {
+        let lines = vec![
+            "Destination Ports     : WEB (protocol 6, port 80-82)".to_string(),
+            "WEB-UDP (protocol 17, port 80-82)".to_string(),
+            "DNS (protocol 17, port 53)".to_string(),
+            "ICMP (protocol 1)".to_string(),
+        ];
+        let protocol_object = ProtocolObject::try_from(&lines).unwrap();
+
+        assert_eq!(protocol_object.optimize().len(), 4);
+
+        let merged = protocol_object.optimize_with(OptimizeOptions {
+            merge_tcp_udp: true,
+        });
+        assert_eq!(
+            protocol_list_optimized::to_text(&merged),
+            "ICMP (protocol 1)\n\
+             WEB AND WEB-UDP (protocol any, port 80-82)\n\
+             DNS (protocol 17, port 53)"
+        );
+        assert!(merged[1].is_tcp_and_udp());
+
+        // the combined entry overlaps entries of either protocol
+        let udp_81 = ProtocolList::from_str("UDP-81 (protocol 17, port 81)").unwrap();
+        let udp_81 = ProtocolListOptimized::from(&udp_81);
+        assert!(merged[1].overlaps(&udp_81));
+        assert!(udp_81.overlaps(&merged[1]));
+        assert!(!merged[2].overlaps(&merged[1]));
+    }
+
+    #[test]
+    fn test_nested_group() {
+        let lines = vec![
+            "Destination Ports     : Web (group)".to_string(),
+            "                          HTTP (protocol 6, port 80)".to_string(),
+            "                          TLS (group)".to_string(),
+            "                            HTTPS (protocol 6, port 443)".to_string(),
+            "                            DNS-TLS (protocol 6, port 853)".to_string(),
+            "                        SSH (protocol 6, port 22)".to_string(),
+        ];
+        let protocol_object = ProtocolObject::try_from(&lines).unwrap();
+
+        assert_eq!(protocol_object.items.len(), 2);
+        assert_eq!(
+            protocol_list_optimized::to_text(&protocol_object.optimize()),
+            "SSH (protocol 6, port 22)\n\
+             HTTP (protocol 6, port 80)\n\
+             HTTPS (protocol 6, port 443)\n\
+             DNS-TLS (protocol 6, port 853)"
+        );
+    }
 }
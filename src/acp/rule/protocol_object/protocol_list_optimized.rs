@@ -1,10 +1,13 @@
+use std::fmt;
+
 use super::group::protocol_list::ProtocolList;
+use super::Protocol;
 
 /// Vector of PortObjectItem returned after optimization  
 /// name - description of all operations performed on items  
 /// items - the list of PortList objects  
 /// PortList objects are flattened from the Group objects and normal PortList objects
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProtocolListOptimized {
     name: String,
     items: Vec<ProtocolList>,
@@ -22,6 +25,25 @@ impl ProtocolListOptimized {
         self.items.push(port_list.clone());
     }
 
+    /// The entry restricted to another port range, for example the ports left after an exclusion.
+    /// Exclusions are applied before TCP and UDP entries are combined, so one item is enough
+    pub fn with_ports(&self, start: u16, end: u16) -> Self {
+        ProtocolListOptimized {
+            name: self.name.clone(),
+            items: self
+                .items
+                .iter()
+                .take(1)
+                .map(|port_list| port_list.with_ports(start, end))
+                .collect(),
+        }
+    }
+
+    /// Takes over all items of the other optimized entry
+    pub fn absorb(&mut self, other: ProtocolListOptimized) {
+        self.items.extend(other.items);
+    }
+
     pub fn set_name(&mut self, name: String) {
         self.name = name;
     }
@@ -30,7 +52,7 @@ impl ProtocolListOptimized {
         &self.name
     }
 
-    pub fn get_protocol(&self) -> u8 {
+    pub fn get_protocol(&self) -> Protocol {
         self.items
             .first()
             .map(|port_list| port_list.get_protocol())
@@ -42,6 +64,34 @@ impl ProtocolListOptimized {
             )
     }
 
+    /// ICMP type of the item (ICMP items are never merged, so the first item is used)
+    pub fn get_icmp_type(&self) -> Option<u8> {
+        self.items
+            .first()
+            .and_then(|port_list| port_list.get_icmp_type())
+    }
+
+    /// ICMP code of the item (ICMP items are never merged, so the first item is used)
+    pub fn get_icmp_code(&self) -> Option<u8> {
+        self.items
+            .first()
+            .and_then(|port_list| port_list.get_icmp_code())
+    }
+
+    /// TCP and UDP entries with the same port range combined into one (see OptimizeOptions),
+    /// get_protocol returns the protocol of the first item
+    pub fn is_tcp_and_udp(&self) -> bool {
+        let protocol = self.get_protocol();
+        self.items.iter().any(|p| p.get_protocol() != protocol)
+    }
+
+    /// "protocol ip" / "protocol all" entry, never merged with other entries
+    pub fn is_all_protocols(&self) -> bool {
+        self.items
+            .first()
+            .is_some_and(|port_list| port_list.is_all_protocols())
+    }
+
     pub fn get_ports(&self) -> (u16, u16) {
         let start = self
             .items
@@ -57,6 +107,70 @@ impl ProtocolListOptimized {
 
         (start.unwrap_or(0), end.unwrap_or(0))
     }
+
+    /// Both items match some common traffic: "protocol ip" overlaps everything,
+    /// otherwise the protocol must be the same (either of a combined TCP and UDP item)
+    /// and port ranges (TCP/UDP) must intersect,
+    /// ICMP type and code must be equal or unset on one of the items
+    pub fn overlaps(&self, other: &ProtocolListOptimized) -> bool {
+        if self.is_all_protocols() || other.is_all_protocols() {
+            return true;
+        }
+
+        let same_or_any = |a: Option<u8>, b: Option<u8>| a.is_none() || b.is_none() || a == b;
+        let (start, end) = self.get_ports();
+        let (other_start, other_end) = other.get_ports();
+
+        let same_protocol = self.items.iter().any(|p| {
+            other
+                .items
+                .iter()
+                .any(|o| p.get_protocol() == o.get_protocol())
+        });
+
+        same_protocol
+            && start <= other_end
+            && other_start <= end
+            && same_or_any(self.get_icmp_type(), other.get_icmp_type())
+            && same_or_any(self.get_icmp_code(), other.get_icmp_code())
+    }
+}
+
+/// FTD-style rendering of the optimized item  
+/// L4 items are rendered with the merged port range, for example:  
+/// HTTP ADJOINS HTTP-ALT (protocol 6, port 80-81)  
+/// Combined TCP and UDP entries are rendered as "protocol any"
+impl fmt::Display for ProtocolListOptimized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let first = self.items.first().ok_or(fmt::Error)?;
+        if !first.is_l4() {
+            return write!(f, "{}", first);
+        }
+
+        let protocol = match self.is_tcp_and_udp() {
+            true => "any".to_string(),
+            false => self.get_protocol().to_string(),
+        };
+        let (start, end) = self.get_ports();
+        if start == end {
+            write!(f, "{} (protocol {}, port {})", self.name, protocol, start)
+        } else {
+            write!(
+                f,
+                "{} (protocol {}, port {}-{})",
+                self.name, protocol, start, end
+            )
+        }
+    }
+}
+
+/// Render the whole optimized set, one item per line
+pub fn to_text(optimized: &[ProtocolListOptimized]) -> String {
+    optimized
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
@@ -178,4 +292,38 @@ mod tests {
         assert_eq!(start, 443);
         assert_eq!(end, 8443);
     }
+
+    #[test]
+    fn display_single_port() {
+        let protocol_list = ProtocolList::from_str("HTTP (protocol 6, port 80)").unwrap();
+        let optimized = ProtocolListOptimized::from(&protocol_list);
+
+        assert_eq!(optimized.to_string(), "HTTP (protocol 6, port 80)");
+    }
+
+    #[test]
+    fn display_l3() {
+        let protocol_list = ProtocolList::from_str("IGMP (protocol 2)").unwrap();
+        let optimized = ProtocolListOptimized::from(&protocol_list);
+
+        assert_eq!(optimized.to_string(), "IGMP (protocol 2)");
+    }
+
+    #[test]
+    fn to_text_adjacent_ports() {
+        let lines = vec![
+            "Destination Ports     : TCP-80 (protocol 6, port 80-80)".to_string(),
+            "TCP-81 (protocol 6, port 81-81)".to_string(),
+            "TCP-82 (protocol 6, port 82-82)".to_string(),
+            "DNS (protocol 17, port 53)".to_string(),
+        ];
+        let protocol_object = super::super::ProtocolObject::try_from(&lines).unwrap();
+        let optimized = protocol_object.optimize();
+
+        assert_eq!(optimized.len(), 2);
+        assert_eq!(
+            to_text(&optimized),
+            "TCP-80 ADJOINS TCP-81 ADJOINS TCP-82 (protocol 6, port 80-82)\nDNS (protocol 17, port 53)"
+        );
+    }
 }
@@ -1,10 +1,11 @@
 use super::group::protocol_list::ProtocolList;
 use super::group::Group;
 
-/// PortObjectItem is either a PortList or a Group
-#[derive(Debug)]
+/// PortObjectItem is either a PortList, a negated PortList or a Group
+#[derive(Debug, Clone)]
 pub enum ProtocolObjectItem {
     ProtocolList(ProtocolList),
+    Excluded(ProtocolList),
     Group(Group),
 }
 
@@ -13,9 +14,19 @@ impl ProtocolObjectItem {
     pub fn collect_objects(&self) -> Vec<&ProtocolList> {
         let protocol_lists: Vec<&ProtocolList> = match self {
             ProtocolObjectItem::ProtocolList(port_list) => vec![port_list],
+            ProtocolObjectItem::Excluded(_) => vec![],
             ProtocolObjectItem::Group(group) => group.port_lists.iter().collect(),
         };
 
         protocol_lists
     }
+
+    /// Builds a flattened list of negated PortList objects from Groups and PortLists
+    pub fn collect_exclusions(&self) -> Vec<&ProtocolList> {
+        match self {
+            ProtocolObjectItem::ProtocolList(_) => vec![],
+            ProtocolObjectItem::Excluded(port_list) => vec![port_list],
+            ProtocolObjectItem::Group(group) => group.negated_lists.iter().collect(),
+        }
+    }
 }
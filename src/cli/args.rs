@@ -1,13 +1,64 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
+use crate::acp::rule::{asa, DEFAULT_MAX_GROUP_DEPTH};
+
 #[derive(Parser, Debug)]
 #[clap(version, about, author)]
 pub struct AppArgs {
-    /// Output of "show access-control-config"
+    /// Output of "show access-control-config", or a directory of *.txt outputs (one per device)
+    /// for "get acp capacity"
     #[arg(short, long, required = true)]
     pub file: PathBuf,
 
+    /// Input file is gzip-compressed (detected automatically for *.gz files)
+    #[arg(long)]
+    pub gzip: bool,
+
+    /// Layout of the input file
+    #[arg(long, value_enum, default_value_t = InputFormat::AcpConfig)]
+    pub input_format: InputFormat,
+
+    /// Give up resolving a hostname after <ms> milliseconds
+    #[arg(long, value_name = "ms")]
+    pub dns_timeout: Option<u64>,
+
+    /// Don't resolve hostnames, they are counted with capacity 0
+    #[arg(long, overrides_with = "dns")]
+    pub no_dns: bool,
+
+    /// Resolve hostnames even if the config file sets no_dns
+    #[arg(long, overrides_with = "no_dns")]
+    pub dns: bool,
+
+    /// Color the text report: auto - only if the output is a terminal
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Infer the protocol of well-known names written without "(protocol N)", e.g. "ICMP Echo"
+    #[arg(long)]
+    pub lenient_protocols: bool,
+
+    /// Deepest nesting of network and port groups, deeper groups fail to parse
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_GROUP_DEPTH)]
+    pub max_depth: usize,
+
+    /// Parse and analyze only the first <N> rules of the file
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Skip rules that fail to parse, with a warning for each, instead of stopping
+    #[arg(long)]
+    pub continue_on_error: bool,
+
+    /// Stop at the first rule that fails to parse (the default)
+    #[arg(long, conflicts_with = "continue_on_error")]
+    pub fail_on_parse_error: bool,
+
+    /// Config file with default options [default: ftd-acl.toml in the current directory, if present]
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
     #[clap(subcommand)]
     /// Command to run
     pub subcommand: Verb,
@@ -39,10 +90,16 @@ pub enum Entity {
 /// Analyze a rule from "show access-control-config"
 pub enum Rule {
     /// Analyze a rule capacity and optimization capacity
-    Capacity(RuleName),
+    Capacity(RuleCapacity),
 
     /// Get optimization report for a rule
-    Analysis(RuleName),
+    Analysis(RuleAnalysis),
+
+    /// Check if a host ip address is matched by a rule source or destination networks
+    Matches(RuleMatches),
+
+    /// Explain step by step how a rule networks and ports are optimized
+    Explain(RuleName),
 }
 
 #[derive(Args, Debug)]
@@ -52,6 +109,87 @@ pub struct RuleName {
     pub name: String,
 }
 
+#[derive(Args, Debug)]
+/// Rule name and options of the optimization report
+pub struct RuleAnalysis {
+    /// Rule name to analyze
+    pub name: String,
+
+    /// Show how the protocol factor is derived from source and destination ports
+    #[arg(long)]
+    pub explain_factor: bool,
+
+    /// Report TCP and UDP ports with the same range as one "protocol any" entry
+    /// (FTD doesn't merge them, the capacity is unchanged)
+    #[arg(long)]
+    pub merge_tcp_udp: bool,
+}
+
+#[derive(Args, Debug)]
+/// Rule name and capacity threshold
+pub struct RuleCapacity {
+    /// Rule name to analyze
+    pub name: String,
+
+    #[command(flatten)]
+    pub threshold: ThresholdArgs,
+
+    /// Print only the raw capacity, skip the optimization
+    #[arg(long)]
+    pub no_optimize: bool,
+}
+
+#[derive(Args, Debug, Default, Clone, Copy)]
+/// Capacity budget for a single rule as set on the command line,
+/// merged with the config file into Threshold by Config::threshold
+pub struct ThresholdArgs {
+    /// Print a warning for every rule with capacity above this value
+    #[arg(long, value_name = "N")]
+    pub warn_above: Option<u64>,
+
+    /// Exit with an error if any rule capacity is above --warn-above
+    #[arg(long, overrides_with = "no_fail_above")]
+    pub fail_above: bool,
+
+    /// Only warn about rules above --warn-above, even if the config file sets fail_above
+    #[arg(long, overrides_with = "fail_above")]
+    pub no_fail_above: bool,
+}
+
+impl ThresholdArgs {
+    /// --fail-above or --no-fail-above, None if neither is set
+    pub fn fail_above(&self) -> Option<bool> {
+        switch(self.fail_above, self.no_fail_above)
+    }
+}
+
+/// Capacity budget for a single rule
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Threshold {
+    pub warn_above: Option<u64>,
+    pub fail_above: bool,
+}
+
+/// Value of a pair of flags turning an option on and off (the last one given wins),
+/// None if neither is set, so the config file decides
+pub fn switch(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+#[derive(Args, Debug)]
+/// Rule name and host ip address to check
+pub struct RuleMatches {
+    /// Rule name to analyze
+    pub name: String,
+
+    /// Host ip address (for example: 10.11.12.13)
+    pub ip: String,
+}
+
 #[derive(Subcommand, Debug)]
 /// Get info about top-k rules from "show access-control-config"
 pub enum TopK {
@@ -64,11 +202,19 @@ pub enum TopK {
 
 #[derive(Args, Debug)]
 /// Get top-k rules by capacity
-pub struct TopKByCapacity {}
+pub struct TopKByCapacity {
+    /// Number of rules to print [default: 5]
+    #[arg(short, value_name = "K")]
+    pub k: Option<usize>,
+}
 
 #[derive(Args, Debug)]
 /// Get top-k rules by optimization (ratio of a current capacity to an optimized capacity)
-pub struct TopKByOptimization {}
+pub struct TopKByOptimization {
+    /// Number of rules to print [default: 5]
+    #[arg(short, value_name = "K")]
+    pub k: Option<usize>,
+}
 
 #[derive(Subcommand, Debug)]
 /// Analyze the whole access policy from "show access-control-config"
@@ -78,10 +224,127 @@ pub enum Acp {
 
     /// Get capacity optimization only for each rule in the access policy
     Capacity(AcpCapacity),
+
+    /// Compare the access policy with another "show access-control-config" output
+    Diff(AcpDiff),
+
+    /// List unique prefixes, ranges and hosts used in the access policy with the number of rules using them
+    Objects(AcpObjects),
+
+    /// Export optimized rules as Cisco ASA access-list lines (TCP/UDP, hosts and subnets only)
+    Asa(AcpAsa),
 }
 
 #[derive(Args, Debug)]
-pub struct AcpAnalysis {}
+pub struct AcpAnalysis {
+    /// Output format of the report [default: text]
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Print only the number of rules per capacity bucket
+    #[arg(long, conflicts_with = "format")]
+    pub histogram: bool,
+
+    /// Print only count, min, max, mean and median of rule capacities
+    #[arg(long, conflicts_with_all = ["format", "histogram"])]
+    pub stats: bool,
+
+    /// Print only how long each rule took to parse and optimize, and the total
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "format",
+            "histogram",
+            "stats",
+            "output",
+            "warn_noncanonical",
+            "detect_overlaps",
+            "broad",
+        ]
+    )]
+    pub time: bool,
+
+    /// Warn about prefixes with host bits set (e.g. 10.1.2.3/24)
+    #[arg(long, conflicts_with_all = ["histogram", "stats"])]
+    pub warn_noncanonical: bool,
+
+    /// Report overlapping network entries and exit with an error if any are found
+    #[arg(long, conflicts_with_all = ["histogram", "stats"])]
+    pub detect_overlaps: bool,
+
+    /// Warn about overly broad rules matching any source, destination or protocol
+    #[arg(long, conflicts_with_all = ["histogram", "stats"])]
+    pub broad: bool,
+
+    /// Color the names of rules with capacity above this value red in the text report
+    #[arg(long, value_name = "N", conflicts_with_all = ["histogram", "stats", "time"])]
+    pub warn_above: Option<u64>,
+
+    /// Write the report to <FILE> instead of stdout
+    #[arg(short, long, value_name = "FILE", conflicts_with_all = ["histogram", "stats"])]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct AcpCapacity {
+    #[command(flatten)]
+    pub threshold: ThresholdArgs,
+
+    /// Print only the raw capacity, skip the optimization
+    #[arg(long)]
+    pub no_optimize: bool,
+}
 
 #[derive(Args, Debug)]
-pub struct AcpCapacity {}
+pub struct AcpObjects {}
+
+#[derive(Args, Debug)]
+pub struct AcpAsa {
+    /// Name of the access-list
+    #[arg(long, value_name = "NAME", default_value = asa::DEFAULT_ACL_NAME)]
+    pub acl_name: String,
+
+    /// Write the access-list to <FILE> instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct AcpDiff {
+    /// Policy to compare with (--file is the old policy, this one is the new one)
+    pub other: PathBuf,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Layout of the input file, selects the banner and rule header markers
+pub enum InputFormat {
+    /// Output of "show access-control-config"
+    #[default]
+    AcpConfig,
+    /// Output of "show access-list" (stub: only rule names are recognized)
+    AccessList,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// When to color the text output
+pub enum ColorChoice {
+    /// Color if the output is a terminal
+    #[default]
+    Auto,
+    /// Always color, even if the output is piped
+    Always,
+    /// Never color
+    Never,
+}
+
+#[derive(ValueEnum, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+/// Output format of the analysis report
+pub enum OutputFormat {
+    /// Human readable text
+    Text,
+    /// Single JSON document describing the whole access policy
+    Json,
+    /// One line per rule with a header line
+    Csv,
+}
@@ -0,0 +1,226 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::args::{OutputFormat, Threshold, ThresholdArgs};
+
+/// Configuration file looked up in the current directory if --config is not set
+pub const DEFAULT_CONFIG_FILE: &str = "ftd-acl.toml";
+
+/// Number of rules printed by top-k if neither the flag nor the config set it
+pub const DEFAULT_TOP_K: usize = 5;
+
+/// Defaults for the command line options, a flag set on the command line wins.
+///
+/// Example:
+/// format = "json"
+/// top_k = 10
+/// warn_above = 1000
+/// fail_above = true
+/// dns_timeout = 500
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub format: Option<OutputFormat>,
+    pub top_k: Option<usize>,
+    pub warn_above: Option<u64>,
+    pub fail_above: Option<bool>,
+    pub no_dns: Option<bool>,
+    pub dns_timeout: Option<u64>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Fail to read config file {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("Fail to parse config file {0}: {1}")]
+    Parse(PathBuf, toml::de::Error),
+    #[error("Invalid options: {0}")]
+    Conflict(String),
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+
+        toml::from_str(&content).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))
+    }
+
+    /// Config from the --config file, otherwise from ftd-acl.toml in the current directory
+    /// if it exists, otherwise the empty config
+    pub fn find(path: Option<&Path>) -> Result<Self, ConfigError> {
+        match path {
+            Some(path) => Config::load(path),
+            None if Path::new(DEFAULT_CONFIG_FILE).is_file() => {
+                Config::load(Path::new(DEFAULT_CONFIG_FILE))
+            }
+            None => Ok(Config::default()),
+        }
+    }
+
+    pub fn format(&self, format: Option<OutputFormat>) -> OutputFormat {
+        format.or(self.format).unwrap_or(OutputFormat::Text)
+    }
+
+    pub fn top_k(&self, k: Option<usize>) -> usize {
+        k.or(self.top_k).unwrap_or(DEFAULT_TOP_K)
+    }
+
+    pub fn warn_above(&self, warn_above: Option<u64>) -> Option<u64> {
+        warn_above.or(self.warn_above)
+    }
+
+    /// Options are checked after the merge, so either of them may come from the config file:
+    /// fail_above needs warn_above
+    pub fn threshold(&self, threshold: ThresholdArgs) -> Result<Threshold, ConfigError> {
+        let threshold = Threshold {
+            warn_above: self.warn_above(threshold.warn_above),
+            fail_above: threshold
+                .fail_above()
+                .or(self.fail_above)
+                .unwrap_or_default(),
+        };
+
+        if threshold.fail_above && threshold.warn_above.is_none() {
+            return Err(ConfigError::Conflict(
+                "fail_above requires warn_above".to_string(),
+            ));
+        }
+        Ok(threshold)
+    }
+
+    /// no_dns (None if neither --no-dns nor --dns is set) and the resolution timeout,
+    /// no_dns conflicts with dns_timeout after the merge
+    pub fn dns(
+        &self,
+        no_dns: Option<bool>,
+        timeout_ms: Option<u64>,
+    ) -> Result<(bool, Option<u64>), ConfigError> {
+        let no_dns = no_dns.or(self.no_dns).unwrap_or_default();
+        let timeout_ms = timeout_ms.or(self.dns_timeout);
+
+        if no_dns && timeout_ms.is_some() {
+            return Err(ConfigError::Conflict(
+                "no_dns conflicts with dns_timeout".to_string(),
+            ));
+        }
+        Ok((no_dns, timeout_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_from(content: &str) -> (tempfile::TempDir, Result<Config, ConfigError>) {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(DEFAULT_CONFIG_FILE);
+        std::fs::write(&file, content).unwrap();
+        let config = Config::find(Some(&file));
+        (dir, config)
+    }
+
+    #[test]
+    fn test_top_k_from_config() {
+        let (_dir, config) = config_from("top_k = 10\n");
+        let config = config.unwrap();
+
+        assert_eq!(config.top_k(None), 10);
+        assert_eq!(config.top_k(Some(3)), 3);
+        assert_eq!(Config::default().top_k(None), DEFAULT_TOP_K);
+    }
+
+    #[test]
+    fn test_config_values() {
+        let (_dir, config) = config_from(
+            r#"
+format = "csv"
+warn_above = 1000
+fail_above = true
+no_dns = true
+dns_timeout = 500
+"#,
+        );
+        let config = config.unwrap();
+
+        assert_eq!(config.format(None), OutputFormat::Csv);
+        assert_eq!(config.format(Some(OutputFormat::Json)), OutputFormat::Json);
+
+        let threshold = config.threshold(ThresholdArgs::default()).unwrap();
+        assert_eq!(threshold.warn_above, Some(1000));
+        assert!(threshold.fail_above);
+        let threshold = config
+            .threshold(ThresholdArgs {
+                warn_above: Some(10),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(threshold.warn_above, Some(10));
+
+        assert!(matches!(
+            config.dns(None, None),
+            Err(ConfigError::Conflict(_))
+        ));
+        assert_eq!(config.dns(Some(false), None).unwrap(), (false, Some(500)));
+    }
+
+    #[test]
+    fn test_command_line_wins() {
+        let (_dir, config) = config_from("warn_above = 5\nfail_above = true\nno_dns = true\n");
+        let config = config.unwrap();
+
+        // --no-fail-above turns off fail_above of the config
+        let threshold = config
+            .threshold(ThresholdArgs {
+                no_fail_above: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(threshold.warn_above, Some(5));
+        assert!(!threshold.fail_above);
+
+        // --fail-above with warn_above from the config
+        let (_dir, warn_only) = config_from("warn_above = 5\n");
+        let threshold = warn_only
+            .unwrap()
+            .threshold(ThresholdArgs {
+                fail_above: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(threshold.fail_above);
+
+        // --fail-above without warn_above anywhere
+        assert!(matches!(
+            Config::default().threshold(ThresholdArgs {
+                fail_above: true,
+                ..Default::default()
+            }),
+            Err(ConfigError::Conflict(_))
+        ));
+
+        // --dns-timeout conflicts with no_dns of the config, unless --dns turns it off
+        assert!(matches!(
+            config.dns(None, Some(100)),
+            Err(ConfigError::Conflict(_))
+        ));
+        assert_eq!(
+            config.dns(Some(false), Some(100)).unwrap(),
+            (false, Some(100))
+        );
+        assert_eq!(config.dns(None, None).unwrap(), (true, None));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let (_dir, config) = config_from("top_kk = 10\n");
+        assert!(matches!(config, Err(ConfigError::Parse(_, _))));
+
+        let (_dir, config) = config_from("format = \"yaml\"\n");
+        assert!(matches!(config, Err(ConfigError::Parse(_, _))));
+
+        let missing = Config::find(Some(Path::new("/nonexistent/ftd-acl.toml")));
+        assert!(matches!(missing, Err(ConfigError::Io(_, _))));
+    }
+}
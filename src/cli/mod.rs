@@ -1,43 +1,232 @@
-use std::path::PathBuf;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::acp::Acp;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressFinish, ProgressStyle};
+
+use crate::acp::rule::network_object::hostname;
+use crate::acp::rule::network_object::IPv4;
+use crate::acp::rule::{OptimizeOptions, Rule};
+use crate::acp::{self, Acp};
 
 pub mod args;
+pub mod config;
+mod report;
+mod sink;
 mod utils;
 
+/// Input file with options describing how to read it
+#[derive(Debug)]
+pub struct Input {
+    pub file: PathBuf,
+    /// Decompress the file with gzip before parsing
+    pub gzip: bool,
+    pub format: args::InputFormat,
+    /// Parse only the first rules of the file
+    pub limit: Option<usize>,
+    /// Skip rules failing to parse instead of failing the whole file
+    pub continue_on_error: bool,
+}
+
+impl Input {
+    pub fn new(file: PathBuf, gzip: bool) -> Self {
+        // Archived exports are usually stored as .gz
+        let gzip = gzip || file.extension().is_some_and(|ext| ext == "gz");
+        Self {
+            file,
+            gzip,
+            format: args::InputFormat::default(),
+            limit: None,
+            continue_on_error: false,
+        }
+    }
+
+    pub fn with_format(mut self, format: args::InputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn with_continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    /// Another file with the same options, gzip is detected by the file extension
+    pub fn with_file(&self, file: PathBuf) -> Self {
+        Input::new(file, false)
+            .with_format(self.format)
+            .with_limit(self.limit)
+            .with_continue_on_error(self.continue_on_error)
+    }
+
+    /// Directory with one file per device
+    pub fn is_dir(&self) -> bool {
+        self.file.is_dir()
+    }
+
+    /// Every *.txt (or *.txt.gz) file of the directory sorted by name,
+    /// or the input itself if it is a file
+    pub fn inputs(&self) -> Result<Vec<Input>, CliError> {
+        if !self.is_dir() {
+            return Ok(vec![self.with_file(self.file.clone())]);
+        }
+
+        let mut files = std::fs::read_dir(&self.file)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        files.retain(|file| {
+            let name = file.to_string_lossy();
+            file.is_file() && (name.ends_with(".txt") || name.ends_with(".txt.gz"))
+        });
+        files.sort();
+
+        Ok(files
+            .into_iter()
+            .map(|file| {
+                Input::new(file, self.gzip)
+                    .with_format(self.format)
+                    .with_limit(self.limit)
+                    .with_continue_on_error(self.continue_on_error)
+            })
+            .collect())
+    }
+}
+
+/// Selects how hostnames in network objects are resolved
+pub fn configure_dns(no_dns: bool, timeout_ms: Option<u64>) {
+    if no_dns {
+        hostname::set_resolver(Box::new(hostname::NoDnsResolver));
+    } else {
+        let timeout = timeout_ms.map(Duration::from_millis);
+        hostname::set_resolver(Box::new(hostname::SystemResolver::new(timeout)));
+    }
+}
+
+/// Selects strict or lenient parsing of the port lists
+pub fn configure_protocols(lenient: bool) {
+    crate::acp::rule::set_lenient_protocols(lenient);
+}
+
+/// Limits the nesting depth of network and port groups
+pub fn configure_groups(max_depth: usize) {
+    crate::acp::rule::set_max_group_depth(max_depth);
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum CliError {
     #[error("IO Error: {0}")]
     Io(#[from] std::io::Error),
     #[error("Fail to parse rule: {0}")]
     Rule(#[from] crate::acp::rule::RuleError),
-    #[error("Can't find access-control-policy or it is empty ({file})")]
-    AcpEmpty { file: String },
     #[error("No rule found with name ({name})")]
     RuleEmpty { name: String },
     #[error("Fail to parse access control policy: {0}")]
     Acp(#[from] crate::acp::AcpError),
+    #[error("Fail to serialize report: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid host ip address ({ip}): {error}")]
+    InvalidIp { ip: String, error: String },
+    #[error("{count} rule(s) with capacity above {threshold}")]
+    CapacityExceeded { count: usize, threshold: u64 },
+    #[error("{count} overlapping network entries found")]
+    OverlapsFound { count: usize },
+    #[error("{path} is a directory, only get acp capacity and get acp analysis --histogram/--stats accept a directory")]
+    Directory { path: String },
 
     #[error("CLI parsing error: {0}")]
     Cli(#[from] utils::FileError),
+    #[error("{0}")]
+    Config(#[from] config::ConfigError),
+}
+
+/// Exit code for input that can't be parsed or doesn't contain what was requested
+pub const EXIT_PARSE_ERROR: u8 = 1;
+/// Exit code for failures reading the input file
+pub const EXIT_IO_ERROR: u8 = 2;
+/// Exit code for rules above the --warn-above capacity with --fail-above set
+pub const EXIT_THRESHOLD_EXCEEDED: u8 = 3;
+/// Exit code for overlapping network entries with --detect-overlaps set
+pub const EXIT_OVERLAPS_FOUND: u8 = 4;
+
+impl CliError {
+    /// Process exit code for the error category
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Io(_)
+            | CliError::Cli(utils::FileError::Io(_))
+            | CliError::Config(config::ConfigError::Io(_, _)) => EXIT_IO_ERROR,
+            CliError::CapacityExceeded { .. } => EXIT_THRESHOLD_EXCEEDED,
+            CliError::OverlapsFound { .. } => EXIT_OVERLAPS_FOUND,
+            _ => EXIT_PARSE_ERROR,
+        }
+    }
 }
 
-fn get_acp(fname: &PathBuf) -> Result<Acp, CliError> {
-    let rule_lines = utils::read_acp_from_file(fname)?;
+fn get_acp(input: &Input) -> Result<Acp, CliError> {
+    get_acp_with_progress(input, &ProgressBar::hidden())
+}
 
-    let acp = Acp::try_from(rule_lines)?;
+/// Parses the policy, advancing the progress bar after every rule
+fn get_acp_with_progress(input: &Input, progress: &ProgressBar) -> Result<Acp, CliError> {
+    let rule_lines = read_rule_lines(input)?;
+    let (acp, errors) =
+        Acp::from_lines_with_progress(rule_lines, input.continue_on_error, |parsed, total| {
+            progress.set_length(total as u64);
+            progress.set_position(parsed as u64);
+        })?;
 
-    if acp.is_empty() {
-        return Err(CliError::AcpEmpty {
-            file: fname.to_string_lossy().to_string(),
+    progress.suspend(|| {
+        for error in errors {
+            eprintln!("Warning: rule skipped: {}", error);
+        }
+    });
+
+    Ok(acp)
+}
+
+fn read_rule_lines(input: &Input) -> Result<Vec<String>, CliError> {
+    if input.is_dir() {
+        return Err(CliError::Directory {
+            path: input.file.to_string_lossy().to_string(),
         });
     }
+    let rule_lines = utils::read_acp_from_file(&input.file, input.gzip, input.format)?;
 
-    Ok(acp)
+    Ok(utils::first_rules(rule_lines, input.limit))
 }
 
-pub fn analyze_rule(fname: &PathBuf, rule_name: &str) -> Result<(), CliError> {
-    let acp = get_acp(fname)?;
+fn no_rules_message(input: &Input) -> String {
+    format!(
+        "No rules found in {} (expected output of \"show access-control-config\")",
+        input.file.to_string_lossy()
+    )
+}
+
+/// Policy with at least one rule, None if the file has no rules
+/// (a message is printed on stderr instead of an empty report, so json/csv output stays valid)
+fn get_non_empty_acp(input: &Input) -> Result<Option<Acp>, CliError> {
+    let acp = get_acp(input)?;
+
+    if acp.is_empty() {
+        eprintln!("{}", no_rules_message(input));
+        return Ok(None);
+    }
+
+    Ok(Some(acp))
+}
+
+pub fn analyze_rule(
+    input: &Input,
+    rule_name: &str,
+    explain_factor: bool,
+    options: OptimizeOptions,
+) -> Result<(), CliError> {
+    let acp = get_acp(input)?;
 
     let rule = acp.rule_by_name(rule_name).ok_or(CliError::RuleEmpty {
         name: rule_name.to_string(),
@@ -46,87 +235,469 @@ pub fn analyze_rule(fname: &PathBuf, rule_name: &str) -> Result<(), CliError> {
     let rule_capacity = rule.capacity();
     let rule_capacity_optimized = rule.optimized_capacity();
 
-    utils::print_rule_analysis(rule.get_name(), rule_capacity, rule_capacity_optimized);
+    utils::print_rule_analysis(rule, rule_capacity, rule_capacity_optimized);
+
+    if explain_factor {
+        println!("\n{}", rule.explain_protocol_factor());
+    }
 
     let (src_networks_opt, dst_networks_opt) = rule.get_optimized_networks();
     utils::print_optimization_report(&src_networks_opt, &dst_networks_opt);
 
+    let (src_ports_opt, dst_ports_opt) = rule.get_optimized_ports_text_with(options);
+    utils::print_ports_report(&src_ports_opt, &dst_ports_opt);
+
     Ok(())
 }
 
-pub fn analyze_rule_capacity(fname: &PathBuf, rule_name: &str) -> Result<(), CliError> {
-    let acp = get_acp(fname)?;
+pub fn explain_rule(input: &Input, rule_name: &str) -> Result<(), CliError> {
+    let acp = get_acp(input)?;
 
     let rule = acp.rule_by_name(rule_name).ok_or(CliError::RuleEmpty {
         name: rule_name.to_string(),
     })?;
 
-    utils::print_rule_analysis(rule.get_name(), rule.capacity(), rule.optimized_capacity());
+    println!(" --- rule name: {}", rule.get_name());
+    for section in rule.explain() {
+        println!("\n{}", section);
+    }
+    println!("\n protocol factor: {}", rule.protocol_factor());
+    println!(" capacity: {}", rule.capacity());
+    println!(" optimized capacity: {}", rule.optimized_capacity());
 
     Ok(())
 }
 
-pub fn analyze_acp_capacity(fname: &PathBuf) -> Result<(), CliError> {
-    let acp = get_acp(fname)?;
-    let mut acp_capacity: u64 = 0;
-    let mut acp_capacity_optimized: u64 = 0;
+/// Returns true if the capacity is above the warning threshold
+fn is_above_threshold(capacity: u64, threshold: &args::Threshold) -> bool {
+    threshold.warn_above.is_some_and(|limit| capacity > limit)
+}
 
-    println!("==== Rules analysis ====");
-    for rule in acp.iter() {
-        let rule_capacity = rule.capacity();
-        let rule_capacity_optimized = rule.optimized_capacity();
-        acp_capacity += rule_capacity;
-        acp_capacity_optimized += rule_capacity_optimized;
+/// Turns the number of rules above the threshold into an error if --fail-above is set
+fn check_threshold(exceeded: usize, threshold: &args::Threshold) -> Result<(), CliError> {
+    match threshold.warn_above {
+        Some(limit) if threshold.fail_above && exceeded > 0 => Err(CliError::CapacityExceeded {
+            count: exceeded,
+            threshold: limit,
+        }),
+        _ => Ok(()),
+    }
+}
 
-        utils::print_rule_analysis(rule.get_name(), rule_capacity, rule_capacity_optimized);
+/// Optimized capacity of the rule, None if the optimization is skipped (--no-optimize)
+fn optimized_capacity(rule: &Rule, optimize: bool) -> Option<u64> {
+    optimize.then(|| rule.optimized_capacity())
+}
+
+/// Prints the rule analysis, or only the raw capacity if the optimization is skipped
+fn print_rule(
+    rule: &Rule,
+    rule_capacity: u64,
+    rule_capacity_optimized: Option<u64>,
+    palette: &sink::Palette,
+) {
+    let out = &mut std::io::stdout();
+    let name = palette.rule_name(rule.get_name(), rule_capacity);
+    let notes = utils::RuleNotes::from(rule);
+    match rule_capacity_optimized {
+        Some(optimized) => utils::write_rule_analysis(out, &name, notes, rule_capacity, optimized),
+        None => utils::write_rule_capacity(out, &name, notes, rule_capacity),
     }
+    .expect("failed printing to stdout");
+}
 
-    println!("\n");
-    println!("==== Access Control Policy ====");
-    println!("# of rules found: {}", acp.len());
-    println!("acp capacity: {}", acp_capacity);
-    println!("acp optimized capacity: {}", acp_capacity_optimized);
-    println!(
-        "acp optimization ratio: {:.2}%",
-        100. - (acp_capacity_optimized as f64 / acp_capacity as f64) * 100.0
+/// Rule names are colored as in the text report, red above --warn-above
+fn capacity_palette(color: args::ColorChoice, threshold: &args::Threshold) -> sink::Palette {
+    sink::Palette {
+        enabled: use_color(color, std::io::stdout().is_terminal()),
+        warn_above: threshold.warn_above,
+    }
+}
+
+pub fn analyze_rule_capacity(
+    input: &Input,
+    rule_name: &str,
+    threshold: args::Threshold,
+    optimize: bool,
+    color: args::ColorChoice,
+) -> Result<(), CliError> {
+    let acp = get_acp(input)?;
+
+    let rule = acp.rule_by_name(rule_name).ok_or(CliError::RuleEmpty {
+        name: rule_name.to_string(),
+    })?;
+
+    let rule_capacity = rule.capacity();
+    let exceeded = is_above_threshold(rule_capacity, &threshold);
+    if exceeded {
+        utils::print_capacity_warning(rule.get_name(), rule_capacity, &threshold);
+    }
+
+    print_rule(
+        rule,
+        rule_capacity,
+        optimized_capacity(rule, optimize),
+        &capacity_palette(color, &threshold),
     );
 
+    check_threshold(exceeded as usize, &threshold)
+}
+
+pub fn analyze_rule_matches(input: &Input, rule_name: &str, ip: &str) -> Result<(), CliError> {
+    let host = ip.trim().parse::<IPv4>().map_err(|e| CliError::InvalidIp {
+        ip: ip.to_string(),
+        error: e.to_string(),
+    })?;
+
+    let acp = get_acp(input)?;
+
+    let rule = acp.rule_by_name(rule_name).ok_or(CliError::RuleEmpty {
+        name: rule_name.to_string(),
+    })?;
+
+    // Missing networks section means "any", so every host is matched
+    let (src_networks_opt, dst_networks_opt) = rule.get_optimized_networks();
+    let src_match = src_networks_opt.map(|n| n.contains(&host));
+    let dst_match = dst_networks_opt.map(|n| n.contains(&host));
+
+    utils::print_rule_matches(rule.get_name(), &host, src_match, dst_match);
+
     Ok(())
 }
 
-pub fn analyze_acp(fname: &PathBuf) -> Result<(), CliError> {
-    let acp = get_acp(fname)?;
-    let mut acp_capacity: u64 = 0;
+/// Capacity of one or several policies
+#[derive(Debug, Default, PartialEq)]
+struct CapacityTotals {
+    rules: usize,
+    capacity: u64,
+    optimized_capacity: u64,
+    /// Rules above the --warn-above capacity
+    exceeded: usize,
+}
+
+impl std::ops::AddAssign for CapacityTotals {
+    fn add_assign(&mut self, other: Self) {
+        self.rules += other.rules;
+        self.capacity += other.capacity;
+        self.optimized_capacity += other.optimized_capacity;
+        self.exceeded += other.exceeded;
+    }
+}
+
+/// Capacity report of a file, or of every file of a directory followed by the totals
+pub fn analyze_acp_capacity(
+    input: &Input,
+    threshold: args::Threshold,
+    optimize: bool,
+    color: args::ColorChoice,
+) -> Result<(), CliError> {
+    let mut totals = CapacityTotals::default();
+    let inputs = input.inputs()?;
+    let palette = capacity_palette(color, &threshold);
+
+    for file in &inputs {
+        if input.is_dir() {
+            println!("==== File: {} ====", file.file.to_string_lossy());
+        }
+        totals += acp_capacity(file, &threshold, optimize, &palette)?;
+    }
+
+    if input.is_dir() {
+        println!("\n");
+        println!("==== All files ====");
+        println!("# of files analyzed: {}", inputs.len());
+        print_capacity_totals(&totals, optimize);
+    }
+
+    check_threshold(totals.exceeded, &threshold)
+}
+
+fn acp_capacity(
+    input: &Input,
+    threshold: &args::Threshold,
+    optimize: bool,
+    palette: &sink::Palette,
+) -> Result<CapacityTotals, CliError> {
+    let Some(acp) = get_non_empty_acp(input)? else {
+        return Ok(CapacityTotals::default());
+    };
     let mut acp_capacity_optimized: u64 = 0;
+    let mut exceeded: usize = 0;
+
+    // shares are relative to the whole policy, so all capacities are needed upfront
+    let capacities = acp.iter().map(|rule| rule.capacity()).collect::<Vec<_>>();
+    let acp_capacity: u64 = capacities.iter().sum();
+    let shares = utils::capacity_shares(&capacities);
 
     println!("==== Rules analysis ====");
-    for rule in acp.iter() {
-        let rule_capacity = rule.capacity();
-        let rule_capacity_optimized = rule.optimized_capacity();
-        acp_capacity += rule_capacity;
-        acp_capacity_optimized += rule_capacity_optimized;
+    for ((rule, rule_capacity), share) in acp.iter().zip(capacities).zip(shares) {
+        let rule_capacity_optimized = optimized_capacity(rule, optimize);
+        acp_capacity_optimized += rule_capacity_optimized.unwrap_or_default();
 
-        utils::print_rule_analysis(rule.get_name(), rule_capacity, rule_capacity_optimized);
+        if is_above_threshold(rule_capacity, threshold) {
+            exceeded += 1;
+            utils::print_capacity_warning(rule.get_name(), rule_capacity, threshold);
+        }
 
-        let (src_networks_opt, dst_networks_opt) = rule.get_optimized_networks();
-        utils::print_optimization_report(&src_networks_opt, &dst_networks_opt);
+        print_rule(rule, rule_capacity, rule_capacity_optimized, palette);
+        utils::print_capacity_share(share);
     }
 
+    let totals = CapacityTotals {
+        rules: acp.len(),
+        capacity: acp_capacity,
+        optimized_capacity: acp_capacity_optimized,
+        exceeded,
+    };
+
     println!("\n");
     println!("==== Access Control Policy ====");
-    println!("# of rules found: {}", acp.len());
-    println!("acp capacity: {}", acp_capacity);
-    println!("acp optimized capacity: {}", acp_capacity_optimized);
-    println!(
-        "acp optimization ratio: {:.2}%",
-        100. - (acp_capacity_optimized as f64 / acp_capacity as f64) * 100.0
-    );
+    print_capacity_totals(&totals, optimize);
+
+    Ok(totals)
+}
+
+fn print_capacity_totals(totals: &CapacityTotals, optimize: bool) {
+    println!("# of rules found: {}", totals.rules);
+    println!("acp capacity: {}", totals.capacity);
+    if optimize {
+        println!("acp optimized capacity: {}", totals.optimized_capacity);
+        println!(
+            "acp optimization ratio: {}",
+            utils::optimization_ratio(totals.capacity, totals.optimized_capacity)
+        );
+    }
+}
+
+/// Checks of the policy reported along with the analysis
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AuditOptions {
+    /// Warn about prefixes with host bits set
+    pub warn_noncanonical: bool,
+    /// Report overlapping network entries, overlaps are an error
+    pub detect_overlaps: bool,
+    /// Warn about rules matching any source, destination or protocol
+    pub broad: bool,
+}
+
+pub fn analyze_acp(
+    input: &Input,
+    format: args::OutputFormat,
+    audit: AuditOptions,
+    color: args::ColorChoice,
+    warn_above: Option<u64>,
+    output: Option<&Path>,
+) -> Result<(), CliError> {
+    let report_to_terminal = output.is_none() && std::io::stdout().is_terminal();
+    // progress goes to stderr and only for the text report (json and csv are usually piped)
+    // written to a file or a pipe: redrawing the bar would overwrite the report on the terminal
+    let progress = progress_bar(show_progress(
+        format,
+        report_to_terminal,
+        std::io::stderr().is_terminal(),
+    ));
+
+    progress.set_message("parsing");
+    let acp = get_acp_with_progress(input, &progress)?;
+    if acp.is_empty() {
+        progress.finish_and_clear();
+        eprintln!("{}", no_rules_message(input));
+        return Ok(());
+    }
+
+    // warnings go to stderr, so json and csv output stays parsable
+    let overlaps = progress.suspend(|| {
+        utils::print_rules_without_matchers(&utils::rules_without_matchers(&acp));
+        if audit.warn_noncanonical {
+            utils::print_noncanonical_warnings(&utils::noncanonical_prefixes(&acp));
+        }
+        if audit.broad {
+            utils::print_broad_rules(&utils::broad_rules(&acp));
+        }
+        // overlaps are reported before the optimization merges them
+        let overlaps = match audit.detect_overlaps {
+            true => utils::network_overlaps(&acp),
+            false => vec![],
+        };
+        utils::print_overlaps(&overlaps);
+        overlaps
+    });
+
+    let palette = sink::Palette {
+        enabled: use_color(color, report_to_terminal),
+        warn_above,
+    };
+    progress.set_message("reporting");
+    progress.set_position(0);
+
+    let mut sink = sink::new(format, output_writer(output)?, palette);
+    report_acp(&acp, sink.as_mut(), &progress)?;
+
+    match overlaps.len() {
+        0 => Ok(()),
+        count => Err(CliError::OverlapsFound { count }),
+    }
+}
+
+/// Report destination: the --output file if set, otherwise stdout
+fn output_writer(output: Option<&Path>) -> Result<Box<dyn Write>, CliError> {
+    Ok(match output {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    })
+}
+
+/// In auto mode the output is colored only if it is a terminal, so pipes get plain text
+fn use_color(color: args::ColorChoice, is_terminal: bool) -> bool {
+    match color {
+        args::ColorChoice::Always => true,
+        args::ColorChoice::Never => false,
+        args::ColorChoice::Auto => is_terminal,
+    }
+}
+
+/// The bar is drawn only for the text report on a terminal stderr, while the report itself
+/// goes to a file or a pipe
+fn show_progress(
+    format: args::OutputFormat,
+    report_to_terminal: bool,
+    stderr_is_terminal: bool,
+) -> bool {
+    format == args::OutputFormat::Text && stderr_is_terminal && !report_to_terminal
+}
+
+/// Number of parsed, then reported rules drawn on stderr, hidden if not enabled
+fn progress_bar(enabled: bool) -> ProgressBar {
+    match enabled {
+        true => progress_bar_to(ProgressDrawTarget::stderr()),
+        false => ProgressBar::hidden(),
+    }
+}
+
+fn progress_bar_to(target: ProgressDrawTarget) -> ProgressBar {
+    let progress =
+        ProgressBar::with_draw_target(None, target).with_finish(ProgressFinish::AndClear);
+    if let Ok(style) = ProgressStyle::with_template("{msg} {bar:40} {pos}/{len} rules") {
+        progress.set_style(style);
+    }
+    progress
+}
+
+/// Feeds every rule of the policy into the sink
+fn report_acp(
+    acp: &Acp,
+    sink: &mut dyn sink::ReportSink,
+    progress: &ProgressBar,
+) -> Result<(), CliError> {
+    for rule in acp.iter() {
+        sink.rule(&report::RuleReport::from(rule))?;
+        progress.inc(1);
+    }
+    if let Some(default_action) = acp.default_action() {
+        sink.default_action(default_action);
+    }
+    // the bar is cleared before the policy summary is printed
+    progress.finish_and_clear();
+    sink.finish()?;
+
+    Ok(())
+}
+
+pub fn analyze_acp_histogram(input: &Input) -> Result<(), CliError> {
+    let Some(capacities) = rule_capacities(input)? else {
+        return Ok(());
+    };
+
+    let histogram = utils::capacity_histogram(capacities.into_iter());
+    utils::print_capacity_histogram(&histogram);
+
+    Ok(())
+}
+
+/// Parses and optimizes the rules one by one, to find the slow ones
+pub fn analyze_acp_timing(input: &Input) -> Result<(), CliError> {
+    let rules = acp::split_rules(read_rule_lines(input)?);
+    if rules.is_empty() {
+        eprintln!("{}", no_rules_message(input));
+        return Ok(());
+    }
+
+    let timings = utils::rule_timings(rules, input.continue_on_error)?;
+    utils::write_rule_timings(&mut std::io::stdout().lock(), &timings)?;
+
+    Ok(())
+}
+
+pub fn analyze_acp_stats(input: &Input) -> Result<(), CliError> {
+    let Some(capacities) = rule_capacities(input)? else {
+        return Ok(());
+    };
+
+    let stats = utils::capacity_stats(capacities.into_iter());
+    utils::print_capacity_stats(&stats);
 
     Ok(())
 }
 
-pub fn analyze_topk_by_capacity(fname: &PathBuf, k: usize) -> Result<(), CliError> {
-    let acp = get_acp(fname)?;
+/// Capacities of the rules of every input file (see Input::inputs), None if there are no rules
+fn rule_capacities(input: &Input) -> Result<Option<Vec<u64>>, CliError> {
+    let mut capacities = vec![];
+    for file in input.inputs()? {
+        if let Some(acp) = get_non_empty_acp(&file)? {
+            capacities.extend(acp.iter().map(|rule| rule.capacity()));
+        }
+    }
+
+    if capacities.is_empty() && input.is_dir() {
+        eprintln!("{}", no_rules_message(input));
+    }
+    Ok((!capacities.is_empty()).then_some(capacities))
+}
+
+pub fn analyze_acp_objects(input: &Input) -> Result<(), CliError> {
+    let Some(acp) = get_non_empty_acp(input)? else {
+        return Ok(());
+    };
+
+    let objects = utils::network_objects_usage(&acp);
+    utils::print_network_objects_usage(&objects);
+
+    Ok(())
+}
+
+/// Optimized rules as Cisco ASA access-list lines
+pub fn analyze_acp_asa(
+    input: &Input,
+    acl_name: &str,
+    output: Option<&Path>,
+) -> Result<(), CliError> {
+    let Some(acp) = get_non_empty_acp(input)? else {
+        return Ok(());
+    };
+
+    let mut out = output_writer(output)?;
+    utils::write_asa_acl(out.as_mut(), &acp, acl_name)?;
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Rules added, removed and changed between the old and the new policy.
+/// Rules are matched by name, a rule is changed if its action or matchers differ
+pub fn analyze_acp_diff(old: &Input, new: &Input) -> Result<(), CliError> {
+    let old_acp = get_acp(old)?;
+    let new_acp = get_acp(new)?;
+
+    let diff = utils::acp_diff(&old_acp, &new_acp);
+    utils::print_acp_diff(&diff);
+
+    Ok(())
+}
+
+pub fn analyze_topk_by_capacity(input: &Input, k: usize) -> Result<(), CliError> {
+    let Some(acp) = get_non_empty_acp(input)? else {
+        return Ok(());
+    };
 
     let mut rules = acp.iter().collect::<Vec<_>>();
 
@@ -138,14 +709,16 @@ pub fn analyze_topk_by_capacity(fname: &PathBuf, k: usize) -> Result<(), CliErro
         let rule_capacity = rule.capacity();
         let rule_capacity_optimized = rule.optimized_capacity();
 
-        utils::print_rule_analysis(rule.get_name(), rule_capacity, rule_capacity_optimized);
+        utils::print_rule_analysis(rule, rule_capacity, rule_capacity_optimized);
     }
 
     Ok(())
 }
 
-pub fn analyze_topk_by_optimization(fname: &PathBuf, k: usize) -> Result<(), CliError> {
-    let acp = get_acp(fname)?;
+pub fn analyze_topk_by_optimization(input: &Input, k: usize) -> Result<(), CliError> {
+    let Some(acp) = get_non_empty_acp(input)? else {
+        return Ok(());
+    };
 
     let mut rules = acp.iter().collect::<Vec<_>>();
 
@@ -157,8 +730,659 @@ pub fn analyze_topk_by_optimization(fname: &PathBuf, k: usize) -> Result<(), Cli
         let rule_capacity = rule.capacity();
         let rule_capacity_optimized = rule.optimized_capacity();
 
-        utils::print_rule_analysis(rule.get_name(), rule_capacity, rule_capacity_optimized);
+        utils::print_rule_analysis(rule, rule_capacity, rule_capacity_optimized);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_RULES: &str = "----------[ Rule: Small ]-----------
+    Source Networks       : 192.168.0.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)
+----------[ Rule: Large ]-----------
+    Source Networks       : 192.168.0.0/24
+                            192.168.2.0/24
+                            192.168.4.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)
+";
+
+    fn input_from(content: &str) -> (tempfile::TempDir, Input) {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("acp.txt");
+        std::fs::write(&file, content).unwrap();
+        (dir, Input::new(file, false))
+    }
+
+    fn audit() -> AuditOptions {
+        AuditOptions {
+            warn_noncanonical: true,
+            detect_overlaps: true,
+            broad: true,
+        }
+    }
+
+    fn threshold(warn_above: u64, fail_above: bool) -> args::Threshold {
+        args::Threshold {
+            warn_above: Some(warn_above),
+            fail_above,
+        }
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let io_error = || std::io::Error::new(std::io::ErrorKind::NotFound, "not found");
+
+        assert_eq!(CliError::Io(io_error()).exit_code(), EXIT_IO_ERROR);
+        assert_eq!(
+            CliError::Cli(utils::FileError::Io(io_error())).exit_code(),
+            EXIT_IO_ERROR
+        );
+        assert_eq!(
+            CliError::CapacityExceeded {
+                count: 1,
+                threshold: 10
+            }
+            .exit_code(),
+            EXIT_THRESHOLD_EXCEEDED
+        );
+        assert_eq!(
+            CliError::RuleEmpty {
+                name: "rule".to_string()
+            }
+            .exit_code(),
+            EXIT_PARSE_ERROR
+        );
+        assert_eq!(
+            CliError::InvalidIp {
+                ip: "10.0.0".to_string(),
+                error: "invalid".to_string()
+            }
+            .exit_code(),
+            EXIT_PARSE_ERROR
+        );
+    }
+
+    #[test]
+    fn test_limit() {
+        let five_rules = (1..=5)
+            .map(|i| {
+                format!(
+                    "----------[ Rule: Rule{i} ]-----------\n    Source Networks       : 10.0.{i}.0/24\n"
+                )
+            })
+            .collect::<String>();
+        let (_dir, input) = input_from(&five_rules);
+
+        let acp = get_acp(&input.with_limit(Some(2))).unwrap();
+        assert_eq!(acp.rule_count(), 2);
+        assert_eq!(acp.rule_by_idx(1).unwrap().get_name(), "Rule2");
+
+        let (_dir, input) = input_from(&five_rules);
+        assert_eq!(get_acp(&input).unwrap().rule_count(), 5);
+    }
+
+    #[test]
+    fn test_optimized_capacity_skipped() {
+        let (_dir, input) = input_from(TWO_RULES);
+        let acp = get_acp(&input).unwrap();
+        let rule = acp.rule_by_name("Large").unwrap();
+
+        assert_eq!(optimized_capacity(rule, false), None);
+        assert_eq!(
+            optimized_capacity(rule, true),
+            Some(rule.optimized_capacity())
+        );
+        assert!(analyze_acp_capacity(
+            &input,
+            args::Threshold::default(),
+            false,
+            args::ColorChoice::Never
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_empty_acp() {
+        for content in ["", "no rules here\nonly unrelated text"] {
+            let (_dir, input) = input_from(content);
+
+            assert!(acp::split_rules(read_rule_lines(&input).unwrap()).is_empty());
+            assert_eq!(get_acp(&input).unwrap().rule_count(), 0);
+            assert!(matches!(get_non_empty_acp(&input), Ok(None)));
+            assert!(matches!(rule_capacities(&input), Ok(None)));
+            assert_eq!(
+                no_rules_message(&input),
+                format!(
+                    "No rules found in {} (expected output of \"show access-control-config\")",
+                    input.file.to_string_lossy()
+                )
+            );
+
+            assert!(analyze_acp(
+                &input,
+                args::OutputFormat::Text,
+                audit(),
+                args::ColorChoice::Auto,
+                None,
+                None
+            )
+            .is_ok());
+            assert!(analyze_acp_capacity(
+                &input,
+                args::Threshold::default(),
+                true,
+                args::ColorChoice::Never
+            )
+            .is_ok());
+            assert!(analyze_acp_stats(&input).is_ok());
+            assert!(analyze_acp_histogram(&input).is_ok());
+            assert!(analyze_acp_timing(&input).is_ok());
+            assert!(analyze_acp_objects(&input).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_exit_code_from_analysis() {
+        let (dir, input) = input_from(
+            "----------[ Rule: Broken ]-----------
+    Source Networks       : 10.0.0.0/33",
+        );
+        let missing = Input::new(dir.path().join("missing.txt"), false);
+
+        let parse_error = analyze_acp_capacity(
+            &input,
+            args::Threshold::default(),
+            true,
+            args::ColorChoice::Never,
+        )
+        .unwrap_err();
+        assert_eq!(parse_error.exit_code(), EXIT_PARSE_ERROR);
+
+        let io_error = analyze_acp_capacity(
+            &missing,
+            args::Threshold::default(),
+            true,
+            args::ColorChoice::Never,
+        )
+        .unwrap_err();
+        assert_eq!(io_error.exit_code(), EXIT_IO_ERROR);
+
+        let (_dir, input) = input_from(TWO_RULES);
+        let threshold_error =
+            analyze_acp_capacity(&input, threshold(0, true), true, args::ColorChoice::Never)
+                .unwrap_err();
+        assert_eq!(threshold_error.exit_code(), EXIT_THRESHOLD_EXCEEDED);
+    }
+
+    #[test]
+    fn test_detect_overlaps() {
+        let (_dir, input) = input_from(
+            "----------[ Rule: Audit ]-----------
+    Source Networks       : NET-1 (192.168.1.0/24)
+                            NET-1-HIGH (192.168.1.128/25)
+    Destination Networks  : 10.0.0.0/24
+                            10.0.1.0/24
+",
+        );
+        let acp = get_acp(&input).unwrap();
+
+        let overlaps = utils::network_overlaps(&acp);
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].0, "Audit");
+        assert_eq!(
+            overlaps[0].1.to_string(),
+            "Source Networks: 192.168.1.0/24 SHADOWS 192.168.1.128/25"
+        );
+
+        let error = analyze_acp(
+            &input,
+            args::OutputFormat::Json,
+            audit(),
+            args::ColorChoice::Never,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(error.exit_code(), EXIT_OVERLAPS_FOUND);
+
+        let no_audit = AuditOptions::default();
+        assert!(analyze_acp(
+            &input,
+            args::OutputFormat::Json,
+            no_audit,
+            args::ColorChoice::Never,
+            None,
+            None
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_broad_rules() {
+        let (_dir, input) = input_from(
+            "----------[ Rule: Permit-Any ]-----------
+    Destination Ports     : ANY (protocol ip)
+----------[ Rule: Web ]-----------
+    Source Networks       : 10.0.0.0/24
+    Destination Networks  : 192.168.0.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)
+----------[ Rule: Any-Source ]-----------
+    Destination Networks  : 192.168.0.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)
+",
+        );
+        let acp = get_acp(&input).unwrap();
+
+        let broad: Vec<(String, String)> = utils::broad_rules(&acp)
+            .into_iter()
+            .map(|(name, report)| (name, report.to_string()))
+            .collect();
+        assert_eq!(
+            broad,
+            vec![
+                (
+                    "Permit-Any".to_string(),
+                    "any source, any destination, any protocol".to_string()
+                ),
+                ("Any-Source".to_string(), "any source".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rules_without_matchers() {
+        let (_dir, input) = input_from(
+            "----------[ Rule: Empty ]-----------
+    Action                : Allow
+----------[ Rule: Web ]-----------
+    Action                : Allow
+    Destination Ports     : HTTPS (protocol 6, port 443)
+----------[ Rule: Apps ]-----------
+    Action                : Block
+    Applications          : Facebook
+",
+        );
+        let acp = get_acp(&input).unwrap();
+
+        assert_eq!(utils::rules_without_matchers(&acp), vec!["Empty"]);
+    }
+
+    #[test]
+    fn test_example_rule_report() {
+        let input = Input::new(PathBuf::from("examples/rule_report"), false);
+        let acp = get_acp(&input).unwrap();
+
+        assert_eq!(acp.rule_count(), 4);
+        assert_eq!(acp.default_action(), Some("Block"));
+    }
+
+    #[test]
+    fn test_continue_on_error() {
+        let (_dir, input) = input_from(
+            "----------[ Rule: Bad ]-----------
+    Source Networks       : 10.0.0.0/33
+----------[ Rule: Web ]-----------
+    Destination Networks  : 10.0.0.0/24
+",
+        );
+
+        assert!(matches!(get_acp(&input), Err(CliError::Acp(_))));
+
+        let input = input.with_continue_on_error(true);
+        let acp = get_acp(&input).unwrap();
+        assert_eq!(acp.rule_count(), 1);
+        assert!(acp.rule_by_name("Web").is_some());
+        assert!(input.with_file(input.file.clone()).continue_on_error);
+    }
+
+    #[test]
+    fn test_use_color() {
+        assert!(use_color(args::ColorChoice::Auto, true));
+        assert!(!use_color(args::ColorChoice::Auto, false));
+        assert!(use_color(args::ColorChoice::Always, false));
+        assert!(!use_color(args::ColorChoice::Never, true));
+    }
+
+    #[test]
+    fn test_noncanonical_prefixes() {
+        let (_dir, input) = input_from(
+            "----------[ Rule: Typo ]-----------
+    Source Networks       : 10.1.2.0/24
+                            10.1.2.3/24
+    Destination Networks  : 10.1.2.3
+",
+        );
+        let acp = get_acp(&input).unwrap();
+
+        assert_eq!(acp[0].capacity(), 2);
+        assert_eq!(
+            utils::noncanonical_prefixes(&acp),
+            vec![("Typo".to_string(), "10.1.2.3/24".to_string())]
+        );
+        assert!(analyze_acp(
+            &input,
+            args::OutputFormat::Json,
+            AuditOptions {
+                warn_noncanonical: true,
+                ..Default::default()
+            },
+            args::ColorChoice::Never,
+            None,
+            None
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_analyze_acp_capacity_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("fw-1.txt"),
+            "----------[ Rule: Web ]-----------
+    Source Networks       : 10.0.0.0/24
+                            10.0.1.0/24
+",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("fw-2.txt"),
+            "----------[ Rule: Dns ]-----------
+    Destination Ports     : DNS (protocol 17, port 53)
+----------[ Rule: Any ]-----------
+",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.md"), "not a policy").unwrap();
+        let input = Input::new(dir.path().to_path_buf(), false);
+
+        let inputs = input.inputs().unwrap();
+        let names: Vec<_> = inputs
+            .iter()
+            .map(|i| i.file.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["fw-1.txt", "fw-2.txt"]);
+
+        let mut totals = CapacityTotals::default();
+        for file in &inputs {
+            totals += acp_capacity(
+                file,
+                &args::Threshold::default(),
+                true,
+                &sink::Palette::default(),
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            totals,
+            CapacityTotals {
+                rules: 3,
+                capacity: 2 + 1 + 1,
+                optimized_capacity: 1 + 1 + 1,
+                exceeded: 0,
+            }
+        );
+
+        assert!(analyze_acp_capacity(
+            &input,
+            args::Threshold::default(),
+            true,
+            args::ColorChoice::Never
+        )
+        .is_ok());
+        let error =
+            analyze_acp_capacity(&input, threshold(1, true), true, args::ColorChoice::Never)
+                .unwrap_err();
+        assert_eq!(error.exit_code(), EXIT_THRESHOLD_EXCEEDED);
+
+        assert_eq!(rule_capacities(&input).unwrap(), Some(vec![2, 1, 1]));
+        assert!(analyze_acp_histogram(&input).is_ok());
+        assert!(analyze_acp_stats(&input).is_ok());
+
+        let error = analyze_acp(
+            &input,
+            args::OutputFormat::Text,
+            AuditOptions::default(),
+            args::ColorChoice::Never,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(error, CliError::Directory { .. }));
+        assert!(error.to_string().contains("is a directory"));
+    }
+
+    #[test]
+    fn test_optimization_ratio() {
+        assert_eq!(utils::optimization_ratio(0, 0), "N/A");
+        assert_eq!(utils::optimization_ratio(4, 3), "25.00%");
+    }
+
+    #[test]
+    fn test_analyze_acp_objects() {
+        let (_dir, input) = input_from(
+            "----------[ Rule: First ]-----------
+    Source Networks       : 192.168.0.0/24
+                            OBJ-10.0.0.0 (10.0.0.0/8)
+    Destination Networks  : 192.168.0.0/24
+----------[ Rule: Second ]-----------
+    Source Networks       : NET (192.168.0.0/24, 172.16.0.1)
+",
+        );
+
+        let objects = utils::network_objects_usage(&get_acp(&input).unwrap());
+        let objects = objects
+            .iter()
+            .map(|object| (object.name.as_str(), object.rules))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            objects,
+            vec![("10.0.0.0/8", 1), ("172.16.0.1", 1), ("192.168.0.0/24", 2)]
+        );
+        assert!(analyze_acp_objects(&input).is_ok());
+    }
+
+    #[test]
+    fn test_analyze_acp_diff() {
+        let (_old_dir, old) = input_from(TWO_RULES);
+        let (_new_dir, new) = input_from(
+            "----------[ Rule: Small ]-----------
+    Source Networks       : 192.168.0.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)
+----------[ Rule: Large ]-----------
+    Source Networks       : 192.168.0.0/24
+                            192.168.2.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)
+----------[ Rule: Added ]-----------
+    Source Networks       : 10.0.0.0/8
+",
+        );
+
+        let diff = utils::acp_diff(&get_acp(&old).unwrap(), &get_acp(&new).unwrap());
+        assert_eq!(diff.added, vec!["Added"]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed, vec!["Large"]);
+
+        let diff = utils::acp_diff(&get_acp(&new).unwrap(), &get_acp(&old).unwrap());
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.removed, vec!["Added"]);
+        assert_eq!(diff.changed, vec!["Large"]);
+
+        assert!(analyze_acp_diff(&old, &new).is_ok());
+    }
+
+    #[test]
+    fn test_is_above_threshold_boundary() {
+        assert!(!is_above_threshold(3, &threshold(3, false)));
+        assert!(is_above_threshold(4, &threshold(3, false)));
+        assert!(!is_above_threshold(u64::MAX, &args::Threshold::default()));
+    }
+
+    #[test]
+    fn test_check_threshold() {
+        assert!(check_threshold(0, &threshold(3, true)).is_ok());
+        assert!(check_threshold(2, &threshold(3, false)).is_ok());
+        assert!(matches!(
+            check_threshold(2, &threshold(3, true)),
+            Err(CliError::CapacityExceeded {
+                count: 2,
+                threshold: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_analyze_rule_capacity_fail_above() {
+        let (_dir, input) = input_from(TWO_RULES);
+
+        // "Large" capacity is exactly 3
+        assert!(analyze_rule_capacity(
+            &input,
+            "Large",
+            threshold(3, true),
+            true,
+            args::ColorChoice::Never
+        )
+        .is_ok());
+        assert!(matches!(
+            analyze_rule_capacity(
+                &input,
+                "Large",
+                threshold(2, true),
+                true,
+                args::ColorChoice::Never
+            ),
+            Err(CliError::CapacityExceeded { count: 1, .. })
+        ));
+        assert!(analyze_rule_capacity(
+            &input,
+            "Large",
+            threshold(2, false),
+            true,
+            args::ColorChoice::Never
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_analyze_acp_capacity_fail_above() {
+        let (_dir, input) = input_from(TWO_RULES);
+
+        assert!(
+            analyze_acp_capacity(&input, threshold(3, true), true, args::ColorChoice::Never)
+                .is_ok()
+        );
+        assert!(matches!(
+            analyze_acp_capacity(&input, threshold(2, true), true, args::ColorChoice::Never),
+            Err(CliError::CapacityExceeded { count: 1, .. })
+        ));
+        assert!(matches!(
+            analyze_acp_capacity(&input, threshold(0, true), true, args::ColorChoice::Never),
+            Err(CliError::CapacityExceeded { count: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_progress_does_not_touch_report() {
+        let (_dir, input) = input_from(TWO_RULES);
+        let term = RecordingTerm::default();
+        let progress = progress_bar_to(ProgressDrawTarget::term_like(Box::new(term.clone())));
+
+        let report = |progress: &ProgressBar| {
+            let acp = get_acp_with_progress(&input, progress).unwrap();
+            let mut out = Vec::new();
+            let mut sink = sink::new(args::OutputFormat::Text, &mut out, sink::Palette::default());
+            report_acp(&acp, sink.as_mut(), progress).unwrap();
+            drop(sink);
+            String::from_utf8(out).unwrap()
+        };
+        let with_progress = report(&progress);
+
+        assert!(term.0.lock().unwrap().contains("/2 rules"));
+        assert!(!with_progress.contains("/2 rules"));
+        assert_eq!(with_progress, report(&progress_bar(false)));
+        assert_eq!(progress.position(), 2);
+        assert!(progress.is_finished());
+    }
+
+    #[test]
+    fn test_show_progress() {
+        use args::OutputFormat::{Json, Text};
+
+        assert!(show_progress(Text, false, true));
+        // the bar would redraw over the report streaming to the same terminal
+        assert!(!show_progress(Text, true, true));
+        assert!(!show_progress(Text, false, false));
+        assert!(!show_progress(Json, false, true));
+    }
+
+    /// Terminal keeping everything the progress bar draws
+    #[derive(Debug, Clone, Default)]
+    struct RecordingTerm(std::sync::Arc<std::sync::Mutex<String>>);
+
+    impl indicatif::TermLike for RecordingTerm {
+        fn width(&self) -> u16 {
+            80
+        }
+        fn move_cursor_up(&self, _: usize) -> std::io::Result<()> {
+            Ok(())
+        }
+        fn move_cursor_down(&self, _: usize) -> std::io::Result<()> {
+            Ok(())
+        }
+        fn move_cursor_right(&self, _: usize) -> std::io::Result<()> {
+            Ok(())
+        }
+        fn move_cursor_left(&self, _: usize) -> std::io::Result<()> {
+            Ok(())
+        }
+        fn write_line(&self, s: &str) -> std::io::Result<()> {
+            self.write_str(&format!("{s}\n"))
+        }
+        fn write_str(&self, s: &str) -> std::io::Result<()> {
+            self.0.lock().unwrap().push_str(s);
+            Ok(())
+        }
+        fn clear_line(&self) -> std::io::Result<()> {
+            Ok(())
+        }
+        fn flush(&self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_output_file() {
+        let (dir, input) = input_from(TWO_RULES);
+        let output = dir.path().join("report.json");
+
+        analyze_acp(
+            &input,
+            args::OutputFormat::Json,
+            AuditOptions::default(),
+            args::ColorChoice::Always,
+            None,
+            Some(&output),
+        )
+        .unwrap();
+
+        let acp = get_acp(&input).unwrap();
+        let mut stdout = Vec::new();
+        let mut sink = sink::new(
+            args::OutputFormat::Json,
+            &mut stdout,
+            sink::Palette::default(),
+        );
+        report_acp(&acp, sink.as_mut(), &progress_bar(false)).unwrap();
+        drop(sink);
+
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            String::from_utf8(stdout).unwrap()
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+
+use crate::acp::rule::Rule;
+use crate::acp::Acp;
+
+use super::utils;
+
+/// Machine readable report of the whole access control policy.
+/// Decouples output format from internal parsing types.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AcpReport {
+    pub capacity: u64,
+    pub optimized_capacity: u64,
+    pub default_action: Option<String>,
+    pub rules: Vec<RuleReport>,
+}
+
+/// Machine readable report of a single rule.
+/// src_networks/dst_networks - optimized networks in CIDR notation (empty if section is absent)
+/// merges - merged entries per network object (empty if nothing was merged)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RuleReport {
+    pub name: String,
+    pub action: Option<String>,
+    pub description: Option<String>,
+    pub application_filtered: bool,
+    pub time_range: Option<String>,
+    pub capacity: u64,
+    pub optimized_capacity: u64,
+    pub protocol_factor: u64,
+    pub src_networks: Vec<String>,
+    pub dst_networks: Vec<String>,
+    pub merges: Vec<MergeReport>,
+}
+
+/// Entries merged by the optimizer in a single network object
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MergeReport {
+    pub section: String,
+    pub entries: Vec<String>,
+}
+
+impl From<&Rule> for RuleReport {
+    fn from(rule: &Rule) -> Self {
+        let (src_networks_opt, dst_networks_opt) = rule.get_optimized_networks();
+
+        let merges = [&src_networks_opt, &dst_networks_opt]
+            .into_iter()
+            .flatten()
+            .map(|networks| MergeReport {
+                section: networks.name().to_string(),
+                entries: utils::get_optimized_elements_name(networks),
+            })
+            .filter(|merge| !merge.entries.is_empty())
+            .collect();
+
+        RuleReport {
+            name: rule.get_name().to_string(),
+            action: rule.get_action().map(|a| a.to_string()),
+            description: rule.get_description().map(|d| d.to_string()),
+            application_filtered: rule.is_application_filtered(),
+            time_range: rule.get_time_range().map(|t| t.to_string()),
+            capacity: rule.capacity(),
+            optimized_capacity: rule.optimized_capacity(),
+            protocol_factor: rule.protocol_factor(),
+            src_networks: src_networks_opt.map_or(vec![], |n| n.cidrs()),
+            dst_networks: dst_networks_opt.map_or(vec![], |n| n.cidrs()),
+            merges,
+        }
+    }
+}
+
+impl From<&Acp> for AcpReport {
+    fn from(acp: &Acp) -> Self {
+        let mut report = AcpReport::from(acp.iter().map(RuleReport::from).collect::<Vec<_>>());
+        report.default_action = acp.default_action().map(|a| a.to_string());
+        report
+    }
+}
+
+impl From<Vec<RuleReport>> for AcpReport {
+    fn from(rules: Vec<RuleReport>) -> Self {
+        AcpReport {
+            capacity: rules.iter().map(|r| r.capacity).sum(),
+            optimized_capacity: rules.iter().map(|r| r.optimized_capacity).sum(),
+            default_action: None,
+            rules,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_rules_acp() -> Acp {
+        let acp = "----------[ Rule: Custom_rule1 | FM-15046 ]-----------
+    Action                : Allow
+    Description           : Branch to servers
+    Source Networks       :   192.168.0.0/24
+                              192.168.1.0/24
+    Destination Networks  : IPv4-RFC1918 (10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16)
+    Destination Ports     : HTTPS (protocol 6, port 443)
+    Logging Configuration
+----------[ Rule: Custom_rule2 | FM-15046 ]-----------
+    Source Networks       : 10.11.12.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)
+                            HTTP (protocol 6, port 80)
+    Logging Configuration";
+        let lines: Vec<String> = acp.lines().map(|s| s.to_string()).collect();
+        Acp::try_from(lines).unwrap()
+    }
+
+    #[test]
+    fn test_acp_report_json_round_trip() {
+        let report = AcpReport::from(&two_rules_acp());
+        let json = serde_json::to_string_pretty(&report).unwrap();
+
+        let report: AcpReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(report.rules.len(), 2);
+        assert_eq!(report.capacity, 2 * 3 + 2);
+        assert_eq!(report.optimized_capacity, 3 + 2);
+
+        let rule1 = &report.rules[0];
+        assert_eq!(rule1.name, "Custom_rule1 | FM-15046");
+        assert_eq!(rule1.action.as_deref(), Some("Allow"));
+        assert_eq!(rule1.description.as_deref(), Some("Branch to servers"));
+        assert_eq!(rule1.protocol_factor, 1);
+        assert_eq!(rule1.src_networks, vec!["192.168.0.0/23"]);
+        assert_eq!(rule1.dst_networks.len(), 3);
+        assert_eq!(rule1.merges.len(), 1);
+        assert_eq!(rule1.merges[0].entries.len(), 1);
+
+        let rule2 = &report.rules[1];
+        assert_eq!(rule2.action, None);
+        assert_eq!(rule2.description, None);
+        assert_eq!(rule2.protocol_factor, 2);
+        assert_eq!(rule2.src_networks, vec!["10.11.12.0/24"]);
+        assert!(rule2.dst_networks.is_empty());
+    }
+}
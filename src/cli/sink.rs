@@ -0,0 +1,464 @@
+use std::io::Write;
+
+use owo_colors::OwoColorize;
+
+use super::args::OutputFormat;
+use super::report::{AcpReport, RuleReport};
+use super::utils;
+
+/// Output backend of the analysis: receives rule reports one by one,
+/// finish() writes whatever has to follow the last rule (totals, JSON document)
+pub trait ReportSink {
+    fn rule(&mut self, report: &RuleReport) -> std::io::Result<()>;
+    /// Default action of the policy, reported by finish() (CSV has no place for it)
+    fn default_action(&mut self, _action: &str) {}
+    fn finish(&mut self) -> std::io::Result<()>;
+}
+
+/// Creates a sink for the requested output format, the palette is used by the text sink only
+pub fn new<'a>(
+    format: OutputFormat,
+    out: impl Write + 'a,
+    palette: Palette,
+) -> Box<dyn ReportSink + 'a> {
+    match format {
+        OutputFormat::Text => Box::new(TextSink::new(out).with_palette(palette)),
+        OutputFormat::Json => Box::new(JsonSink::new(out)),
+        OutputFormat::Csv => Box::new(CsvSink::new(out)),
+    }
+}
+
+/// Human readable report, the same layout as the rest of the text output
+/// palette - rule names are colored by capacity (see Palette)
+pub struct TextSink<W: Write> {
+    out: W,
+    palette: Palette,
+    rules: usize,
+    capacity: u64,
+    optimized_capacity: u64,
+    default_action: Option<String>,
+}
+
+/// Rules up to this capacity are colored green
+const SMALL_RULE_CAPACITY: u64 = 10;
+/// Rules above this capacity are colored red if no --warn-above threshold is set
+const LARGE_RULE_CAPACITY: u64 = 1000;
+
+/// Colors of rule names by capacity: red above the --warn-above threshold
+/// (LARGE_RULE_CAPACITY if it is not set), green up to SMALL_RULE_CAPACITY
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Palette {
+    pub enabled: bool,
+    pub warn_above: Option<u64>,
+}
+
+impl Palette {
+    pub fn rule_name(&self, name: &str, capacity: u64) -> String {
+        let red_above = self.warn_above.unwrap_or(LARGE_RULE_CAPACITY);
+        match capacity {
+            _ if !self.enabled => name.to_string(),
+            capacity if capacity > red_above => name.red().to_string(),
+            capacity if capacity <= SMALL_RULE_CAPACITY => name.green().to_string(),
+            _ => name.to_string(),
+        }
+    }
+}
+
+impl<W: Write> TextSink<W> {
+    pub fn new(out: W) -> Self {
+        TextSink {
+            out,
+            palette: Palette::default(),
+            rules: 0,
+            capacity: 0,
+            optimized_capacity: 0,
+            default_action: None,
+        }
+    }
+
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+}
+
+impl<W: Write> ReportSink for TextSink<W> {
+    fn rule(&mut self, report: &RuleReport) -> std::io::Result<()> {
+        if self.rules == 0 {
+            writeln!(self.out, "==== Rules analysis ====")?;
+        }
+        self.rules += 1;
+        self.capacity += report.capacity;
+        self.optimized_capacity += report.optimized_capacity;
+
+        let name = self.palette.rule_name(&report.name, report.capacity);
+        utils::write_rule_analysis(
+            &mut self.out,
+            &name,
+            utils::RuleNotes {
+                application_filtered: report.application_filtered,
+                time_range: report.time_range.as_deref(),
+            },
+            report.capacity,
+            report.optimized_capacity,
+        )?;
+
+        for merge in report.merges.iter() {
+            utils::write_merges(&mut self.out, &merge.section, &merge.entries)?;
+        }
+
+        Ok(())
+    }
+
+    fn default_action(&mut self, action: &str) {
+        self.default_action = Some(action.to_string());
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        writeln!(self.out, "\n")?;
+        writeln!(self.out, "==== Access Control Policy ====")?;
+        writeln!(self.out, "# of rules found: {}", self.rules)?;
+        if let Some(default_action) = &self.default_action {
+            writeln!(self.out, "default action: {}", default_action)?;
+        }
+        writeln!(self.out, "acp capacity: {}", self.capacity)?;
+        writeln!(
+            self.out,
+            "acp optimized capacity: {}",
+            self.optimized_capacity
+        )?;
+        writeln!(
+            self.out,
+            "acp optimization ratio: {}",
+            utils::optimization_ratio(self.capacity, self.optimized_capacity)
+        )?;
+        self.out.flush()
+    }
+}
+
+/// Single JSON document, written once all rules are collected
+pub struct JsonSink<W: Write> {
+    out: W,
+    rules: Vec<RuleReport>,
+    default_action: Option<String>,
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn new(out: W) -> Self {
+        JsonSink {
+            out,
+            rules: vec![],
+            default_action: None,
+        }
+    }
+}
+
+impl<W: Write> ReportSink for JsonSink<W> {
+    fn rule(&mut self, report: &RuleReport) -> std::io::Result<()> {
+        self.rules.push(report.clone());
+        Ok(())
+    }
+
+    fn default_action(&mut self, action: &str) {
+        self.default_action = Some(action.to_string());
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        let mut report = AcpReport::from(std::mem::take(&mut self.rules));
+        report.default_action = self.default_action.take();
+        serde_json::to_writer_pretty(&mut self.out, &report)?;
+        writeln!(self.out)?;
+        self.out.flush()
+    }
+}
+
+/// One line per rule, networks are separated by spaces within a column
+pub struct CsvSink<W: Write> {
+    out: W,
+    header_written: bool,
+}
+
+const CSV_HEADER: &str =
+    "name,action,capacity,optimized_capacity,protocol_factor,src_networks,dst_networks";
+
+impl<W: Write> CsvSink<W> {
+    pub fn new(out: W) -> Self {
+        CsvSink {
+            out,
+            header_written: false,
+        }
+    }
+
+    fn write_header(&mut self) -> std::io::Result<()> {
+        if !self.header_written {
+            self.header_written = true;
+            writeln!(self.out, "{}", CSV_HEADER)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> ReportSink for CsvSink<W> {
+    fn rule(&mut self, report: &RuleReport) -> std::io::Result<()> {
+        self.write_header()?;
+
+        writeln!(
+            self.out,
+            "{},{},{},{},{},{},{}",
+            csv_field(&report.name),
+            csv_field(report.action.as_deref().unwrap_or_default()),
+            report.capacity,
+            report.optimized_capacity,
+            report.protocol_factor,
+            csv_field(&report.src_networks.join(" ")),
+            csv_field(&report.dst_networks.join(" ")),
+        )
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        // header only for an empty policy
+        self.write_header()?;
+        self.out.flush()
+    }
+}
+
+/// Quotes the field if it contains a separator, quote, pipe or line break.
+/// Pipes are quoted as well, so rule names like "Rule | FM-15046" stay intact
+/// when the file is imported with "|" as a separator.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '|', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acp::Acp;
+
+    fn rule_reports() -> Vec<RuleReport> {
+        let acp = "----------[ Rule: Custom_rule1 | FM-15046 ]-----------
+    Action                : Allow
+    Source Networks       :   192.168.0.0/24
+                              192.168.1.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)
+    Logging Configuration
+----------[ Rule: Custom_rule2, legacy ]-----------
+    Source Networks       : 10.11.12.0/24
+                            10.11.14.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)
+                            HTTP (protocol 6, port 80)
+    Logging Configuration";
+        let lines: Vec<String> = acp.lines().map(|s| s.to_string()).collect();
+        let acp = Acp::try_from(lines).unwrap();
+        acp.iter().map(RuleReport::from).collect()
+    }
+
+    fn run(format: OutputFormat) -> String {
+        let mut out = vec![];
+        {
+            let mut sink = new(format, &mut out, Palette::default());
+            for report in rule_reports().iter() {
+                sink.rule(report).unwrap();
+            }
+            sink.finish().unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_text_sink() {
+        let text = run(OutputFormat::Text);
+
+        assert!(text.starts_with("==== Rules analysis ====\n"));
+        assert_eq!(text.matches(" --- rule name: ").count(), 2);
+        assert!(text.contains("\t\t 192.168.0.0/24 ADJOINS 192.168.1.0/24"));
+        assert!(text.contains("# of rules found: 2\n"));
+        assert!(text.contains("acp capacity: 6\n"));
+        assert!(text.contains("acp optimized capacity: 5\n"));
+    }
+
+    #[test]
+    fn test_default_action() {
+        let write = |format: OutputFormat| {
+            let mut out = vec![];
+            {
+                let mut sink = new(format, &mut out, Palette::default());
+                sink.rule(&rule_reports()[0]).unwrap();
+                sink.default_action("Block all traffic");
+                sink.finish().unwrap();
+            }
+            String::from_utf8(out).unwrap()
+        };
+
+        assert!(write(OutputFormat::Text).contains("default action: Block all traffic\n"));
+
+        let report: AcpReport = serde_json::from_str(&write(OutputFormat::Json)).unwrap();
+        assert_eq!(report.default_action.as_deref(), Some("Block all traffic"));
+
+        assert_eq!(write(OutputFormat::Csv).lines().count(), 2);
+        assert!(!run(OutputFormat::Text).contains("default action"));
+    }
+
+    #[test]
+    fn test_json_sink() {
+        let json = run(OutputFormat::Json);
+
+        let report: AcpReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(report.rules.len(), 2);
+        assert_eq!(report.capacity, 6);
+        assert_eq!(report.optimized_capacity, 5);
+    }
+
+    #[test]
+    fn test_csv_sink() {
+        let csv = run(OutputFormat::Csv);
+        let lines = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            "\"Custom_rule1 | FM-15046\",Allow,2,1,1,192.168.0.0/23,"
+        );
+        assert_eq!(
+            lines[2],
+            "\"Custom_rule2, legacy\",,4,4,2,10.11.12.0/24 10.11.14.0/24,"
+        );
+    }
+
+    /// Minimal CSV line parser (quoted fields with doubled quotes)
+    fn parse_csv_line(line: &str) -> Vec<String> {
+        let mut fields = vec![String::new()];
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match (c, quoted) {
+                ('"', true) if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    fields.last_mut().unwrap().push('"');
+                }
+                ('"', _) => quoted = !quoted,
+                (',', false) => fields.push(String::new()),
+                (c, _) => fields.last_mut().unwrap().push(c),
+            }
+        }
+
+        fields
+    }
+
+    #[test]
+    fn test_csv_special_characters_round_trip() {
+        let names = [
+            "Custom_rule2 | FM-15046",
+            "Web, legacy",
+            "Say \"hi\"",
+            "All \"of\", them | FM-1",
+        ];
+        let mut reports = rule_reports();
+        reports.truncate(1);
+
+        for name in names {
+            reports[0].name = name.to_string();
+            let mut out = vec![];
+            {
+                let mut sink = CsvSink::new(&mut out);
+                sink.rule(&reports[0]).unwrap();
+                sink.finish().unwrap();
+            }
+            let csv = String::from_utf8(out).unwrap();
+            let row = csv.lines().nth(1).unwrap();
+
+            let fields = parse_csv_line(row);
+            assert_eq!(fields.len(), CSV_HEADER.split(',').count());
+            assert_eq!(fields[0], name);
+        }
+    }
+
+    #[test]
+    fn test_text_and_json_keep_special_characters() {
+        let mut reports = rule_reports();
+        reports.truncate(1);
+        reports[0].name = "All \"of\", them | FM-1".to_string();
+
+        let mut text = vec![];
+        let mut json = vec![];
+        for (format, out) in [
+            (OutputFormat::Text, &mut text),
+            (OutputFormat::Json, &mut json),
+        ] {
+            let mut sink = new(format, out, Palette::default());
+            sink.rule(&reports[0]).unwrap();
+            sink.finish().unwrap();
+        }
+
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains(" --- rule name: All \"of\", them | FM-1\n"));
+
+        let report: AcpReport = serde_json::from_slice(&json).unwrap();
+        assert_eq!(report.rules[0].name, "All \"of\", them | FM-1");
+    }
+
+    #[test]
+    fn test_color() {
+        let mut reports = rule_reports();
+        reports[1].capacity = LARGE_RULE_CAPACITY + 1;
+
+        let write = |format: OutputFormat, enabled: bool| {
+            let mut out = vec![];
+            {
+                let palette = Palette {
+                    enabled,
+                    warn_above: None,
+                };
+                let mut sink = new(format, &mut out, palette);
+                for report in reports.iter() {
+                    sink.rule(report).unwrap();
+                }
+                sink.finish().unwrap();
+            }
+            String::from_utf8(out).unwrap()
+        };
+
+        let colored = write(OutputFormat::Text, true);
+        assert!(colored.contains(&reports[0].name.green().to_string()));
+        assert!(colored.contains(&reports[1].name.red().to_string()));
+
+        assert!(!write(OutputFormat::Text, false).contains('\x1b'));
+        assert!(!write(OutputFormat::Json, true).contains('\x1b'));
+        assert!(!write(OutputFormat::Csv, true).contains('\x1b'));
+    }
+
+    #[test]
+    fn test_palette_threshold() {
+        let palette = Palette {
+            enabled: true,
+            warn_above: Some(5),
+        };
+        assert_eq!(palette.rule_name("rule", 6), "rule".red().to_string());
+        assert_eq!(palette.rule_name("rule", 5), "rule".green().to_string());
+
+        // no threshold: red only above LARGE_RULE_CAPACITY
+        let palette = Palette {
+            enabled: true,
+            warn_above: None,
+        };
+        assert_eq!(palette.rule_name("rule", 11), "rule");
+        assert_eq!(
+            palette.rule_name("rule", LARGE_RULE_CAPACITY + 1),
+            "rule".red().to_string()
+        );
+        assert_eq!(Palette::default().rule_name("rule", 1), "rule");
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a | b"), "\"a | b\"");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -1,6 +1,16 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
+use crate::acp::rule::asa;
+use crate::acp::rule::explanation::Overlap;
 use crate::acp::rule::network_object::network_object_optimized::NetworkObjectOptimized;
+use crate::acp::rule::network_object::IPv4;
+use crate::acp::rule::{BroadnessReport, Rule};
+use crate::acp::Acp;
+
+use super::args::{InputFormat, Threshold};
 
 #[derive(thiserror::Error, Debug)]
 pub enum FileError {
@@ -8,30 +18,23 @@ pub enum FileError {
     Io(#[from] std::io::Error),
     #[error("No rule found with name: {name}")]
     RuleEmpty { name: String },
-    #[error("No access control policy found in file: {file}")]
-    AcpEmpty { file: String },
 }
 
 fn is_filtered(line: &str) -> bool {
     line.contains("Object missing: ") || line.contains("")
 }
 
-/// Checks if a line contains an open parenthesis without a corresponding close parenthesis.
-/// This function is used to identify lines that start a parenthetical block but do not
-/// complete it, which is useful for merging multiline entries.
-fn is_open_parenthesis(line: &str) -> bool {
-    line.contains('(') && !line.contains(')')
-}
-
-/// Checks if a line contains a closing parenthesis `)` without an opening parenthesis `(`.
-/// # Arguments
-/// * `line` - A string slice representing the line to check.
-///
-/// # Returns
-/// * `true` if the line contains a closing parenthesis `)` but no opening parenthesis `(`.
-/// * `false` otherwise.
-fn is_close_parenthesis(line: &str) -> bool {
-    line.contains(')') && !line.contains('(')
+/// Difference between the number of opening and closing parentheses in a line.
+/// Positive value means the line opens a parenthetical block that continues on the next lines,
+/// for example a long prefix list wrapped across several lines:
+///  RFC1918 (10.0.0.0/8, 172.16.0.0/12,
+///  192.168.0.0/16)
+fn parenthesis_balance(line: &str) -> i32 {
+    line.chars().fold(0, |balance, c| match c {
+        '(' => balance + 1,
+        ')' => balance - 1,
+        _ => balance,
+    })
 }
 
 /// Read a file and merge lines that are part of the same entry.
@@ -43,20 +46,17 @@ fn is_close_parenthesis(line: &str) -> bool {
 fn merge_lines_between_parenthesis<'a>(iter: impl Iterator<Item = &'a str>) -> Vec<String> {
     let mut result: Vec<String> = Vec::new();
 
-    let mut in_parenthesis = false;
+    // Join continuation lines until parentheses balance
+    let mut balance = 0;
     for line in iter {
-        if in_parenthesis {
+        if balance > 0 {
             if let Some(last_line) = result.last_mut() {
                 last_line.push_str(line);
             }
-            if is_close_parenthesis(line) {
-                in_parenthesis = false;
-            }
+            balance += parenthesis_balance(line);
             continue;
         }
-        if is_open_parenthesis(line) {
-            in_parenthesis = true;
-        }
+        balance = parenthesis_balance(line);
         // Add the line as a new entry
         result.push(line.to_string());
     }
@@ -64,16 +64,41 @@ fn merge_lines_between_parenthesis<'a>(iter: impl Iterator<Item = &'a str>) -> V
     result
 }
 
-pub fn read_and_merge_lines(fname: &PathBuf) -> Result<Vec<String>, std::io::Error> {
-    let content = std::fs::read_to_string(fname)?;
+/// Read the whole file, transparently decompressing gzip if requested.
+/// Invalid UTF-8 bytes are replaced with U+FFFD, so a stray byte doesn't abort the analysis
+fn read_content(fname: &PathBuf, gzip: bool) -> Result<String, std::io::Error> {
+    let bytes = match gzip {
+        false => std::fs::read(fname)?,
+        true => {
+            let file = std::fs::File::open(fname)?;
+            let mut bytes = vec![];
+            flate2::read::GzDecoder::new(file).read_to_end(&mut bytes)?;
+            bytes
+        }
+    };
+
+    match String::from_utf8(bytes) {
+        Ok(content) => Ok(content),
+        Err(e) => {
+            eprintln!(
+                "Warning: {} is not valid UTF-8, invalid bytes are replaced",
+                fname.to_string_lossy()
+            );
+            Ok(String::from_utf8_lossy(e.as_bytes()).into_owned())
+        }
+    }
+}
+
+pub fn read_and_merge_lines(fname: &PathBuf, gzip: bool) -> Result<Vec<String>, std::io::Error> {
+    let content = read_content(fname, gzip)?;
 
     let result = merge_lines_between_parenthesis(content.lines());
 
     Ok(result)
 }
 
-fn read_file(fname: &PathBuf) -> Result<Vec<String>, std::io::Error> {
-    let content: Vec<_> = read_and_merge_lines(fname)?
+fn read_file(fname: &PathBuf, gzip: bool) -> Result<Vec<String>, std::io::Error> {
+    let content: Vec<_> = read_and_merge_lines(fname, gzip)?
         .into_iter()
         .filter(|line| !is_filtered(line))
         .map(|s| s.to_string())
@@ -82,52 +107,176 @@ fn read_file(fname: &PathBuf) -> Result<Vec<String>, std::io::Error> {
     Ok(content)
 }
 
-pub fn read_acp_from_file(fname: &PathBuf) -> Result<Vec<String>, FileError> {
-    let content = read_file(fname)?;
+pub fn read_acp_from_file(
+    fname: &PathBuf,
+    gzip: bool,
+    format: InputFormat,
+) -> Result<Vec<String>, FileError> {
+    let content = read_file(fname, gzip)?;
 
-    let acp: Vec<_> = content
-        .iter()
-        .skip_while(|line| !line.contains("--[ Rule: "))
-        .take_while(|line| !line.contains("==[ Advanced Settings ]=="))
-        .cloned()
-        .collect();
+    // no rule banners is not an error, the policy is just empty
+    let acp = match format {
+        InputFormat::AcpConfig => acp_config_rules(&content),
+        InputFormat::AccessList => access_list_rules(&content),
+    };
+
+    Ok(acp)
+}
+
+/// Rule name and the time it took to parse the rule and compute its capacity before and after
+/// the optimization. Rules failing to parse stop the timing, unless continue_on_error is set:
+/// then they are skipped with a warning, like in the other reports
+pub(super) fn rule_timings(
+    rules: Vec<Vec<String>>,
+    continue_on_error: bool,
+) -> Result<Vec<(String, Duration)>, crate::acp::rule::RuleError> {
+    let mut timings = vec![];
 
-    if acp.is_empty() {
-        return Err(FileError::AcpEmpty {
-            file: fname.to_string_lossy().to_string(),
-        });
+    for rule_lines in rules {
+        let start = Instant::now();
+        let rule = match Rule::try_from(rule_lines) {
+            Ok(rule) => rule,
+            Err(error) if continue_on_error => {
+                eprintln!("Warning: rule skipped: {}", error);
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+        rule.capacity();
+        rule.optimized_capacity();
+        timings.push((rule.get_name().to_string(), start.elapsed()));
     }
 
-    Ok(acp)
+    Ok(timings)
+}
+
+/// One line per rule and the total:
+/// Web: 0.042 ms
+/// total: 0.042 ms (1 rules)
+pub(super) fn write_rule_timings(
+    out: &mut dyn Write,
+    timings: &[(String, Duration)],
+) -> std::io::Result<()> {
+    for (rule_name, duration) in timings {
+        writeln!(
+            out,
+            "{}: {:.3} ms",
+            rule_name,
+            duration.as_secs_f64() * 1000.0
+        )?;
+    }
+
+    let total: Duration = timings.iter().map(|(_, duration)| *duration).sum();
+    writeln!(
+        out,
+        "total: {:.3} ms ({} rules)",
+        total.as_secs_f64() * 1000.0,
+        timings.len()
+    )
+}
+
+/// Lines of the first `limit` rules, everything if there is no limit
+pub fn first_rules(lines: Vec<String>, limit: Option<usize>) -> Vec<String> {
+    let Some(limit) = limit else {
+        return lines;
+    };
+
+    let mut rules = 0;
+    lines
+        .into_iter()
+        .take_while(|line| {
+            if line.contains("[ Rule: ") {
+                rules += 1;
+            }
+            rules <= limit
+        })
+        .collect()
+}
+
+/// Rules of "show access-control-config" are between the first rule banner and advanced settings.
+/// The default action of the policy header is kept in front of the rules
+fn acp_config_rules(content: &[String]) -> Vec<String> {
+    let default_action = content
+        .iter()
+        .take_while(|line| !line.contains("--[ Rule: "))
+        .filter(|line| {
+            line.split_once(':')
+                .is_some_and(|(title, _)| title.trim() == "Default Action")
+        })
+        .last();
+
+    default_action
+        .into_iter()
+        .chain(
+            content
+                .iter()
+                .skip_while(|line| !line.contains("--[ Rule: "))
+                .take_while(|line| !line.contains("==[ Advanced Settings ]==")),
+        )
+        .cloned()
+        .collect()
+}
+
+/// Stub for "show access-list", rule names are taken from the remarks, for example:
+///  access-list CSM_FW_ACL_ line 10 remark rule-id 268435461: L7 RULE: Allow_Web
+/// is converted to the rule banner of "show access-control-config":
+///  ----------[ Rule: Allow_Web ]-----------
+/// ACE lines are kept in the rule body, but not parsed into sections yet.
+fn access_list_rules(content: &[String]) -> Vec<String> {
+    content
+        .iter()
+        .skip_while(|line| !line.contains("L7 RULE: "))
+        .map(|line| match line.split_once("L7 RULE: ") {
+            Some((_, name)) => format!("----------[ Rule: {} ]-----------", name.trim()),
+            None => line.clone(),
+        })
+        .collect()
 }
 
 pub(super) fn print_optimization_report(
     src_networks_opt: &Option<NetworkObjectOptimized>,
     dst_networks_opt: &Option<NetworkObjectOptimized>,
 ) {
-    if let Some(src_networks) = src_networks_opt {
-        let nets = get_optimized_elements_name(src_networks);
+    for networks in [src_networks_opt, dst_networks_opt].into_iter().flatten() {
+        let nets = get_optimized_elements_name(networks);
+        write_merges(&mut std::io::stdout(), networks.name(), &nets)
+            .expect("failed printing to stdout");
+    }
+}
 
-        if !nets.is_empty() {
-            println!("\n\t --- {} ---", src_networks.name());
-            for net in nets.iter() {
-                println!("\t\t {}", net);
-            }
-        }
+/// Writes merged entries of a network object, nothing if there are no merges
+pub(super) fn write_merges(
+    out: &mut dyn Write,
+    section: &str,
+    entries: &[String],
+) -> std::io::Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "\n\t --- {} ---", section)?;
+    for entry in entries {
+        writeln!(out, "\t\t {}", entry)?;
     }
-    if let Some(dst_networks) = dst_networks_opt {
-        let nets = get_optimized_elements_name(dst_networks);
 
-        if !nets.is_empty() {
-            println!("\n\t --- {} ---", dst_networks.name());
-            for net in nets.iter() {
-                println!("\t\t {}", net);
+    Ok(())
+}
+
+pub(super) fn print_ports_report(src_ports_opt: &Option<String>, dst_ports_opt: &Option<String>) {
+    for (title, ports_opt) in [
+        ("Source Ports", src_ports_opt),
+        ("Destination Ports", dst_ports_opt),
+    ] {
+        if let Some(ports) = ports_opt {
+            println!("\n\t --- {} (optimized) ---", title);
+            for line in ports.lines() {
+                println!("\t\t {}", line);
             }
         }
     }
 }
 
-fn get_optimized_elements_name(network_object: &NetworkObjectOptimized) -> Vec<String> {
+pub(super) fn get_optimized_elements_name(network_object: &NetworkObjectOptimized) -> Vec<String> {
     let result = network_object
         .items()
         .iter()
@@ -139,18 +288,68 @@ fn get_optimized_elements_name(network_object: &NetworkObjectOptimized) -> Vec<S
     result
 }
 
-pub(super) fn print_rule_analysis(
+pub(super) fn print_rule_analysis(rule: &Rule, rule_capacity: u64, rule_capacity_optimized: u64) {
+    write_rule_analysis(
+        &mut std::io::stdout(),
+        rule.get_name(),
+        RuleNotes::from(rule),
+        rule_capacity,
+        rule_capacity_optimized,
+    )
+    .expect("failed printing to stdout");
+}
+
+/// Conditions limiting the traffic matched by the rule, so its effective capacity
+/// may be lower than reported
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct RuleNotes<'a> {
+    pub application_filtered: bool,
+    pub time_range: Option<&'a str>,
+}
+
+impl<'a> From<&'a Rule> for RuleNotes<'a> {
+    fn from(rule: &'a Rule) -> Self {
+        RuleNotes {
+            application_filtered: rule.is_application_filtered(),
+            time_range: rule.get_time_range(),
+        }
+    }
+}
+
+pub(super) fn write_rule_capacity(
+    out: &mut dyn Write,
+    rule_name: &str,
+    notes: RuleNotes,
+    rule_capacity: u64,
+) -> std::io::Result<()> {
+    writeln!(out, " --- rule name: {}", rule_name)?;
+    if notes.application_filtered {
+        writeln!(out, "\t application-filtered (capacity is an upper bound)")?;
+    }
+    if let Some(time_range) = notes.time_range {
+        writeln!(
+            out,
+            "\t time-bounded by {} (active only within the time range)",
+            time_range
+        )?;
+    }
+    writeln!(out, "\t capacity: {}", rule_capacity)
+}
+
+pub(super) fn write_rule_analysis(
+    out: &mut dyn Write,
     rule_name: &str,
+    notes: RuleNotes,
     rule_capacity: u64,
     rule_capacity_optimized: u64,
-) {
-    println!(" --- rule name: {}", rule_name);
-    println!("\t capacity: {}", rule_capacity);
-    println!("\t optimized capacity: {}", rule_capacity_optimized);
-    println!(
+) -> std::io::Result<()> {
+    write_rule_capacity(out, rule_name, notes, rule_capacity)?;
+    writeln!(out, "\t optimized capacity: {}", rule_capacity_optimized)?;
+    writeln!(
+        out,
         "\t # of removed entries: {}",
         rule_capacity - rule_capacity_optimized
-    );
+    )?;
 
     let optimization_ratio = if rule_capacity > 0 {
         (rule_capacity - rule_capacity_optimized) as f64 / rule_capacity as f64 * 100.0
@@ -158,7 +357,340 @@ pub(super) fn print_rule_analysis(
         0.0
     };
 
-    println!("\t optimization ratio: {:.2}%", optimization_ratio);
+    writeln!(out, "\t optimization ratio: {:.2}%", optimization_ratio)
+}
+
+/// Share of the capacity removed by the optimization, N/A for a zero capacity
+pub(super) fn optimization_ratio(capacity: u64, optimized_capacity: u64) -> String {
+    match capacity {
+        0 => "N/A".to_string(),
+        capacity => format!(
+            "{:.2}%",
+            100. - (optimized_capacity as f64 / capacity as f64) * 100.0
+        ),
+    }
+}
+
+/// Share of every capacity in the total, in percent (0 for an empty total)
+pub(super) fn capacity_shares(capacities: &[u64]) -> Vec<f64> {
+    let total: u64 = capacities.iter().sum();
+
+    capacities
+        .iter()
+        .map(|&capacity| match total {
+            0 => 0.0,
+            _ => capacity as f64 / total as f64 * 100.0,
+        })
+        .collect()
+}
+
+pub(super) fn print_capacity_share(share: f64) {
+    println!("\t share of acp capacity: {:.2}%", share);
+}
+
+pub(super) fn print_capacity_warning(rule_name: &str, rule_capacity: u64, threshold: &Threshold) {
+    if let Some(limit) = threshold.warn_above {
+        println!(
+            "WARN: rule ({}) capacity {} is above {}",
+            rule_name, rule_capacity, limit
+        );
+    }
+}
+
+/// Upper bounds (inclusive) and labels of the capacity histogram buckets
+const CAPACITY_BUCKETS: [(u64, &str); 4] = [
+    (10, "1-10"),
+    (100, "11-100"),
+    (1000, "101-1000"),
+    (u64::MAX, "1000+"),
+];
+
+/// Number of rules in every capacity bucket
+pub(super) fn capacity_histogram(
+    capacities: impl Iterator<Item = u64>,
+) -> Vec<(&'static str, usize)> {
+    let mut histogram: Vec<_> = CAPACITY_BUCKETS
+        .iter()
+        .map(|(_, label)| (*label, 0))
+        .collect();
+
+    for capacity in capacities {
+        let idx = CAPACITY_BUCKETS
+            .iter()
+            .position(|(upper, _)| capacity <= *upper)
+            .unwrap_or(CAPACITY_BUCKETS.len() - 1);
+        histogram[idx].1 += 1;
+    }
+
+    histogram
+}
+
+pub(super) fn print_capacity_histogram(histogram: &[(&str, usize)]) {
+    println!("==== Rules by capacity ====");
+    for (label, count) in histogram {
+        println!("\t {:>10}: {}", label, count);
+    }
+}
+
+/// Summary statistics of rule capacities, all zeros for an empty policy
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct CapacityStats {
+    pub count: usize,
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub median: f64,
+}
+
+pub(super) fn capacity_stats(capacities: impl Iterator<Item = u64>) -> CapacityStats {
+    let mut sorted: Vec<u64> = capacities.collect();
+    sorted.sort_unstable();
+
+    let count = sorted.len();
+    if count == 0 {
+        return CapacityStats::default();
+    }
+
+    // even number of rules: mean of the two middle values
+    let median = if count.is_multiple_of(2) {
+        (sorted[count / 2 - 1] + sorted[count / 2]) as f64 / 2.0
+    } else {
+        sorted[count / 2] as f64
+    };
+
+    CapacityStats {
+        count,
+        min: sorted[0],
+        max: sorted[count - 1],
+        mean: sorted.iter().sum::<u64>() as f64 / count as f64,
+        median,
+    }
+}
+
+pub(super) fn print_capacity_stats(stats: &CapacityStats) {
+    println!("==== Rules capacity statistics ====");
+    println!("\t count: {}", stats.count);
+    if stats.count == 0 {
+        for name in ["min", "max", "mean", "median"] {
+            println!("\t {}: N/A", name);
+        }
+        return;
+    }
+    println!("\t min: {}", stats.min);
+    println!("\t max: {}", stats.max);
+    println!("\t mean: {:.2}", stats.mean);
+    println!("\t median: {:.1}", stats.median);
+}
+
+/// Prints if the host is matched by the source and destination networks of a rule.
+/// `None` means the networks section is absent in the rule (any).
+pub(super) fn print_rule_matches(
+    rule_name: &str,
+    host: &IPv4,
+    src_match: Option<bool>,
+    dst_match: Option<bool>,
+) {
+    println!(" --- rule name: {}", rule_name);
+    println!(
+        "\t {} in source networks: {}",
+        host,
+        match_to_str(src_match)
+    );
+    println!(
+        "\t {} in destination networks: {}",
+        host,
+        match_to_str(dst_match)
+    );
+}
+
+fn match_to_str(matched: Option<bool>) -> &'static str {
+    match matched {
+        Some(true) => "yes",
+        Some(false) => "no",
+        None => "yes (any)",
+    }
+}
+
+/// Rule name and prefix for every prefix with host bits set
+pub(super) fn noncanonical_prefixes(acp: &Acp) -> Vec<(String, String)> {
+    acp.iter()
+        .flat_map(|rule| {
+            rule.prefix_list_items()
+                .into_iter()
+                .filter(|item| !item.is_canonical())
+                .map(|item| (rule.get_name().to_string(), item.get_name().to_string()))
+        })
+        .collect()
+}
+
+pub(super) fn print_noncanonical_warnings(prefixes: &[(String, String)]) {
+    for (rule_name, prefix) in prefixes {
+        eprintln!(
+            "Warning: rule {} has prefix {} with host bits set",
+            rule_name, prefix
+        );
+    }
+}
+
+/// Rules matching any source, destination or protocol
+pub(super) fn broad_rules(acp: &Acp) -> Vec<(String, BroadnessReport)> {
+    acp.iter()
+        .map(|rule| (rule.get_name().to_string(), rule.is_overly_broad()))
+        .filter(|(_, report)| report.is_broad())
+        .collect()
+}
+
+pub(super) fn print_broad_rules(rules: &[(String, BroadnessReport)]) {
+    for (rule_name, report) in rules {
+        eprintln!("Warning: rule {} matches {}", rule_name, report);
+    }
+}
+
+/// Names of the rules without networks, ports and applications
+pub(super) fn rules_without_matchers(acp: &Acp) -> Vec<&str> {
+    acp.iter()
+        .filter(|rule| rule.has_no_matchers())
+        .map(|rule| rule.get_name())
+        .collect()
+}
+
+pub(super) fn print_rules_without_matchers(rules: &[&str]) {
+    for rule_name in rules {
+        eprintln!(
+            "Warning: rule {} has no networks, ports or applications",
+            rule_name
+        );
+    }
+}
+
+/// Rule name and overlapping network entries for every rule
+pub(super) fn network_overlaps(acp: &Acp) -> Vec<(String, Overlap)> {
+    acp.iter()
+        .flat_map(|rule| {
+            rule.overlaps()
+                .into_iter()
+                .map(|overlap| (rule.get_name().to_string(), overlap))
+        })
+        .collect()
+}
+
+pub(super) fn print_overlaps(overlaps: &[(String, Overlap)]) {
+    for (rule_name, overlap) in overlaps {
+        eprintln!("Overlap: rule {}: {}", rule_name, overlap);
+    }
+}
+
+/// Prefix, range or host and the number of rules using it
+#[derive(Debug)]
+pub(super) struct ObjectUsage {
+    pub name: String,
+    pub rules: usize,
+}
+
+/// Unique prefixes, ranges and hosts of all rules sorted by start ip.
+/// An item used several times in a rule (e.g. in source and destination) is counted once.
+pub(super) fn network_objects_usage(acp: &Acp) -> Vec<ObjectUsage> {
+    let mut usage: HashMap<&str, (&IPv4, usize)> = HashMap::new();
+
+    for rule in acp.iter() {
+        let items = rule.prefix_list_items();
+        let unique = items
+            .iter()
+            .map(|item| (item.get_name(), item.start_ip()))
+            .collect::<HashMap<_, _>>();
+
+        for (name, start_ip) in unique {
+            usage.entry(name).or_insert((start_ip, 0)).1 += 1;
+        }
+    }
+
+    let mut objects = usage.into_iter().collect::<Vec<_>>();
+    objects.sort_by(|(name1, (ip1, _)), (name2, (ip2, _))| (ip1, name1).cmp(&(ip2, name2)));
+
+    objects
+        .into_iter()
+        .map(|(name, (_, rules))| ObjectUsage {
+            name: name.to_string(),
+            rules,
+        })
+        .collect()
+}
+
+pub(super) fn print_network_objects_usage(objects: &[ObjectUsage]) {
+    println!("==== Network objects ====");
+    for object in objects {
+        println!("\t {} (rules: {})", object.name, object.rules);
+    }
+    println!("# of unique network objects: {}", objects.len());
+}
+
+/// Names of the rules added, removed and changed between two policies
+#[derive(Debug, Default)]
+pub(super) struct AcpDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+/// Rules are matched by name, rules with the same name are compared by action and matchers
+pub(super) fn acp_diff(old: &Acp, new: &Acp) -> AcpDiff {
+    let mut diff = AcpDiff::default();
+
+    for rule in old.iter() {
+        match new.rule_by_name(rule.get_name()) {
+            None => diff.removed.push(rule.get_name().to_string()),
+            Some(new_rule) if rule != new_rule => diff.changed.push(rule.get_name().to_string()),
+            Some(_) => {}
+        }
+    }
+
+    diff.added = new
+        .iter()
+        .filter(|rule| old.rule_by_name(rule.get_name()).is_none())
+        .map(|rule| rule.get_name().to_string())
+        .collect();
+
+    diff
+}
+
+pub(super) fn print_acp_diff(diff: &AcpDiff) {
+    println!("==== Access Control Policy diff ====");
+    for (title, sign, names) in [
+        ("added", '+', &diff.added),
+        ("removed", '-', &diff.removed),
+        ("changed", '~', &diff.changed),
+    ] {
+        println!("rules {}: {}", title, names.len());
+        for name in names {
+            println!("\t {} {}", sign, name);
+        }
+    }
+}
+
+/// Every rule as a remark followed by its ASA access-list lines.
+/// Rules that can't be exported are left as a remark with the reason and a warning on stderr
+pub(super) fn write_asa_acl(out: &mut dyn Write, acp: &Acp, acl_name: &str) -> std::io::Result<()> {
+    for rule in acp.iter() {
+        writeln!(
+            out,
+            "access-list {} remark rule: {}",
+            acl_name,
+            rule.get_name()
+        )?;
+        match asa::to_asa_acl(rule, acl_name) {
+            Ok(lines) => {
+                for line in lines {
+                    writeln!(out, "{}", line)?;
+                }
+            }
+            Err(e) => {
+                eprintln!("Warning: {}", e);
+                writeln!(out, "access-list {} remark not exported: {}", acl_name, e)?;
+            }
+        }
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -229,6 +761,36 @@ Another line"#;
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_merge_lines_prefix_list_wrapped_on_three_lines() {
+        let input = vec![
+            "    Source Networks       : RFC1918 (10.0.0.0/8, 172.16.0.0/12,",
+            " 192.168.0.0/16, 100.64.0.0/10,",
+            " 198.18.0.0/15)",
+            "    Destination Networks  : 10.0.0.0/8",
+        ];
+        let result = merge_lines_between_parenthesis(input.into_iter());
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            result[0],
+            "    Source Networks       : RFC1918 (10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16, 100.64.0.0/10, 198.18.0.0/15)"
+        );
+
+        let network_object =
+            crate::acp::rule::network_object::NetworkObject::try_from(&result[0..1].to_vec())
+                .unwrap();
+        assert_eq!(network_object.capacity(), 5);
+    }
+
+    #[test]
+    fn test_merge_lines_parenthesized_name() {
+        let input = vec!["OBJ (East) (10.0.0.0/8, 172.16.0.", "0/12)", "Another line"];
+        let expected = vec!["OBJ (East) (10.0.0.0/8, 172.16.0.0/12)", "Another line"];
+
+        let result = merge_lines_between_parenthesis(input.into_iter());
+        assert_eq!(result, expected);
+    }
+
     #[test]
     fn test_merge_lines_empty_input() {
         let input: Vec<&str> = vec![];
@@ -246,4 +808,287 @@ Another line"#;
         let result = merge_lines_between_parenthesis(input.into_iter());
         assert_eq!(result, expected);
     }
+
+    const TWO_RULES: &str = "----------[ Rule: Custom_rule1 | FM-15046 ]-----------
+    Source Networks       : 192.168.0.0/24
+                            192.168.1.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)
+----------[ Rule: Custom_rule2 | FM-15046 ]-----------
+    Source Networks       : OBJ-10.18.46.62-69 (10.18.46.62-10.18.
+46.69)
+    Destination Ports     : HTTPS (protocol 6, port 443)
+";
+
+    #[test]
+    fn test_read_acp_from_gzip_file() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("acp.txt");
+        let gzipped = dir.path().join("acp.txt.gz");
+
+        std::fs::write(&plain, TWO_RULES).unwrap();
+        let mut encoder = GzEncoder::new(
+            std::fs::File::create(&gzipped).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(TWO_RULES.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let plain_lines = read_acp_from_file(&plain, false, InputFormat::AcpConfig).unwrap();
+        let gzip_lines = read_acp_from_file(&gzipped, true, InputFormat::AcpConfig).unwrap();
+        assert_eq!(plain_lines, gzip_lines);
+
+        let plain_acp = crate::acp::Acp::try_from(plain_lines).unwrap();
+        let gzip_acp = crate::acp::Acp::try_from(gzip_lines).unwrap();
+        assert_eq!(gzip_acp.len(), 2);
+        for (a, b) in plain_acp.iter().zip(gzip_acp.iter()) {
+            assert_eq!(a.get_name(), b.get_name());
+            assert_eq!(a.capacity(), b.capacity());
+        }
+    }
+
+    #[test]
+    fn test_read_acp_with_invalid_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("acp.txt");
+        let mut content = TWO_RULES.as_bytes().to_vec();
+        // stray Latin-1 byte in the first line
+        content.insert(TWO_RULES.find('\n').unwrap(), 0xE9);
+        std::fs::write(&file, content).unwrap();
+
+        let lines = read_acp_from_file(&file, false, InputFormat::AcpConfig).unwrap();
+        let acp = crate::acp::Acp::try_from(lines).unwrap();
+        assert_eq!(acp.rule_count(), 2);
+    }
+
+    #[test]
+    fn test_read_acp_from_gzip_file_not_compressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("acp.txt");
+        std::fs::write(&plain, TWO_RULES).unwrap();
+
+        assert!(read_acp_from_file(&plain, true, InputFormat::AcpConfig).is_err());
+    }
+
+    #[test]
+    fn test_read_acp_input_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let acp_config = dir.path().join("acp.txt");
+        std::fs::write(&acp_config, TWO_RULES).unwrap();
+        let access_list = dir.path().join("access-list.txt");
+        std::fs::write(
+            &access_list,
+            "access-list cached ACL log flows: total 0\n\
+             access-list CSM_FW_ACL_ line 10 remark rule-id 268435461: L7 RULE: Allow_Web\n\
+             access-list CSM_FW_ACL_ line 11 advanced permit tcp any any eq www rule-id 268435461\n\
+             access-list CSM_FW_ACL_ line 12 remark rule-id 268435462: L7 RULE: Block Telnet\n\
+             access-list CSM_FW_ACL_ line 13 advanced deny tcp any any eq telnet rule-id 268435462\n",
+        )
+        .unwrap();
+
+        let lines = read_acp_from_file(&acp_config, false, InputFormat::AcpConfig).unwrap();
+        let acp = crate::acp::Acp::try_from(lines).unwrap();
+        assert_eq!(acp.len(), 2);
+
+        let lines = read_acp_from_file(&access_list, false, InputFormat::AccessList).unwrap();
+        assert_eq!(lines[0], "----------[ Rule: Allow_Web ]-----------");
+        let acp = crate::acp::Acp::try_from(lines).unwrap();
+        let names = acp.iter().map(|rule| rule.get_name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Allow_Web", "Block Telnet"]);
+
+        // access-list layout has no rule banners of access-control-config
+        assert!(
+            read_acp_from_file(&access_list, false, InputFormat::AcpConfig)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_capacity_histogram() {
+        let capacities = [1, 10, 11, 100, 101, 546, 1000, 1001, 1_000_000];
+        let histogram = capacity_histogram(capacities.into_iter());
+
+        assert_eq!(
+            histogram,
+            vec![("1-10", 2), ("11-100", 2), ("101-1000", 3), ("1000+", 2)]
+        );
+    }
+
+    #[test]
+    fn test_write_rule_capacity_raw_only() {
+        let mut out = vec![];
+        write_rule_capacity(&mut out, "Web", RuleNotes::default(), 42).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text, " --- rule name: Web\n\t capacity: 42\n");
+    }
+
+    #[test]
+    fn test_write_rule_capacity_time_bounded() {
+        let mut out = vec![];
+        let notes = RuleNotes {
+            application_filtered: false,
+            time_range: Some("Business-Hours"),
+        };
+        write_rule_capacity(&mut out, "Web", notes, 42).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text,
+            " --- rule name: Web\n\t time-bounded by Business-Hours (active only within the time range)\n\t capacity: 42\n"
+        );
+    }
+
+    #[test]
+    fn test_capacity_shares() {
+        let shares = capacity_shares(&[50, 30, 20]);
+
+        assert_eq!(shares, vec![50.0, 30.0, 20.0]);
+        assert!((shares.iter().sum::<f64>() - 100.0).abs() < 1e-9);
+
+        let shares = capacity_shares(&[1, 1, 1]);
+        assert!((shares[0] - 100.0 / 3.0).abs() < 1e-9);
+        assert!((shares.iter().sum::<f64>() - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_capacity_shares_empty_total() {
+        assert_eq!(capacity_shares(&[0, 0]), vec![0.0, 0.0]);
+        assert!(capacity_shares(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_capacity_stats_odd_count() {
+        let stats = capacity_stats([7, 1, 100, 3, 4].into_iter());
+
+        assert_eq!(
+            stats,
+            CapacityStats {
+                count: 5,
+                min: 1,
+                max: 100,
+                mean: 23.0,
+                median: 4.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_capacity_stats_even_count() {
+        let stats = capacity_stats([10, 1, 4, 3].into_iter());
+
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.min, 1);
+        assert_eq!(stats.max, 10);
+        assert_eq!(stats.mean, 4.5);
+        assert_eq!(stats.median, 3.5);
+    }
+
+    #[test]
+    fn test_capacity_stats_empty() {
+        let stats = capacity_stats(std::iter::empty());
+
+        assert_eq!(stats, CapacityStats::default());
+    }
+
+    #[test]
+    fn test_capacity_histogram_empty() {
+        let histogram = capacity_histogram(std::iter::empty());
+
+        assert!(histogram.iter().all(|(_, count)| *count == 0));
+        assert_eq!(histogram.len(), 4);
+    }
+
+    #[test]
+    fn test_write_asa_acl() {
+        let acp = Acp::try_from(
+            "----------[ Rule: Web ]-----------
+    Action                : Allow
+    Destination Networks  : 10.1.1.0/24
+    Destination Ports     : HTTPS (protocol 6, port 443)
+----------[ Rule: Ping ]-----------
+    Action                : Allow
+    Destination Ports     : Echo (protocol 1, type 8)
+"
+            .lines()
+            .map(String::from)
+            .collect::<Vec<_>>(),
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        write_asa_acl(&mut out, &acp, "ACL").unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap().lines().collect::<Vec<_>>(),
+            vec![
+                "access-list ACL remark rule: Web",
+                "access-list ACL extended permit tcp any 10.1.1.0 255.255.255.0 eq 443",
+                "access-list ACL remark rule: Ping",
+                "access-list ACL remark not exported: Rule Ping can't be exported to ASA: protocol 1 (Echo (protocol 1, type 8))",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rule_timings() {
+        let rules = crate::acp::split_rules(
+            "----------[ Rule: Web ]-----------
+    Destination Networks  : 10.1.1.0/24
+----------[ Rule: Ranges ]-----------
+    Source Networks       : 10.0.0.1-10.0.255.254
+"
+            .lines()
+            .map(String::from)
+            .collect(),
+        );
+        let timings = rule_timings(rules, false).unwrap();
+
+        let mut out = Vec::new();
+        write_rule_timings(&mut out, &timings).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Web: "));
+        assert!(lines[1].starts_with("Ranges: "));
+        assert!(lines[2].starts_with("total: ") && lines[2].ends_with(" ms (2 rules)"));
+        for line in lines {
+            let (_, time) = line.rsplit_once(": ").unwrap();
+            let millis = time.split_whitespace().next().unwrap();
+            assert!(millis.parse::<f64>().is_ok(), "{}", line);
+            assert!(time.contains(" ms"), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_rule_timings_continue_on_error() {
+        let rules = crate::acp::split_rules(
+            "----------[ Rule: Bad ]-----------
+    Source Networks       : 10.0.0.0/33
+----------[ Rule: Web ]-----------
+    Destination Networks  : 10.1.1.0/24
+"
+            .lines()
+            .map(String::from)
+            .collect(),
+        );
+
+        let error = rule_timings(rules.clone(), false).unwrap_err();
+        assert!(error.to_string().contains("10.0.0.0/33"), "{}", error);
+
+        let timings = rule_timings(rules, true).unwrap();
+        assert_eq!(timings.len(), 1);
+        assert_eq!(timings[0].0, "Web");
+    }
+
+    #[test]
+    fn test_match_to_str() {
+        assert_eq!(match_to_str(Some(true)), "yes");
+        assert_eq!(match_to_str(Some(false)), "no");
+        assert_eq!(match_to_str(None), "yes (any)");
+    }
 }
@@ -0,0 +1,3 @@
+//! Parser and optimizer of the FTD access control policy ("show access-control-config")
+
+pub mod acp;
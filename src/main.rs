@@ -1,11 +1,12 @@
 use clap::Parser;
-use std::path::PathBuf;
+use std::process::ExitCode;
 
-pub mod acp;
+use ftd_acl_optimizer::acp;
 
 mod cli;
 
 use cli::args;
+use cli::config::Config;
 
 #[derive(thiserror::Error, Debug)]
 pub enum AppError {
@@ -13,44 +14,161 @@ pub enum AppError {
     App(#[from] cli::CliError),
 }
 
-fn main() -> Result<(), AppError> {
+impl AppError {
+    /// Exit codes:
+    /// 1 - parse error, 2 - IO error, 3 - rule capacity above the threshold,
+    /// 4 - overlapping network entries found
+    fn exit_code(&self) -> u8 {
+        match self {
+            AppError::App(cli_error) => cli_error.exit_code(),
+        }
+    }
+}
+
+fn main() -> ExitCode {
     let args = args::AppArgs::parse();
-    let file = args.file;
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run(args: args::AppArgs) -> Result<(), AppError> {
+    // options missing on the command line are taken from the config file
+    let config = Config::find(args.config.as_deref()).map_err(cli::CliError::from)?;
+
+    let file = cli::Input::new(args.file, args.gzip)
+        .with_format(args.input_format)
+        .with_limit(args.limit)
+        // fail-fast is the default, --fail-on-parse-error only makes it explicit
+        .with_continue_on_error(args.continue_on_error && !args.fail_on_parse_error);
+    let (no_dns, dns_timeout) = config
+        .dns(args::switch(args.no_dns, args.dns), args.dns_timeout)
+        .map_err(cli::CliError::from)?;
+    cli::configure_dns(no_dns, dns_timeout);
+    cli::configure_protocols(args.lenient_protocols);
+    cli::configure_groups(args.max_depth);
 
     match args.subcommand {
         args::Verb::Get(entity) => match entity {
-            args::Entity::Rule(rule) => parse_rule(&file, rule)?,
-            args::Entity::TopK(topk) => parse_topk(&file, topk)?,
-            args::Entity::Acp(acp) => parse_acp(&file, acp)?,
+            args::Entity::Rule(rule) => parse_rule(&file, rule, args.color, &config)?,
+            args::Entity::TopK(topk) => parse_topk(&file, topk, &config)?,
+            args::Entity::Acp(acp) => parse_acp(&file, acp, args.color, &config)?,
         },
     };
 
     Ok(())
 }
 
-fn parse_rule(file: &PathBuf, action: args::Rule) -> Result<(), AppError> {
+fn parse_rule(
+    file: &cli::Input,
+    action: args::Rule,
+    color: args::ColorChoice,
+    config: &Config,
+) -> Result<(), AppError> {
     match action {
-        args::Rule::Capacity(rule_name) => cli::analyze_rule_capacity(file, &rule_name.name)?,
-        args::Rule::Analysis(rule_name) => cli::analyze_rule(file, &rule_name.name)?,
+        args::Rule::Capacity(capacity) => cli::analyze_rule_capacity(
+            file,
+            &capacity.name,
+            config
+                .threshold(capacity.threshold)
+                .map_err(cli::CliError::from)?,
+            !capacity.no_optimize,
+            color,
+        )?,
+        args::Rule::Analysis(analysis) => cli::analyze_rule(
+            file,
+            &analysis.name,
+            analysis.explain_factor,
+            acp::rule::OptimizeOptions {
+                merge_tcp_udp: analysis.merge_tcp_udp,
+            },
+        )?,
+        args::Rule::Explain(rule_name) => cli::explain_rule(file, &rule_name.name)?,
+        args::Rule::Matches(matches) => {
+            cli::analyze_rule_matches(file, &matches.name, &matches.ip)?
+        }
     };
 
     Ok(())
 }
 
-fn parse_topk(file: &PathBuf, action: args::TopK) -> Result<(), AppError> {
+fn parse_topk(file: &cli::Input, action: args::TopK, config: &Config) -> Result<(), AppError> {
     match action {
-        args::TopK::ByCapacity(_) => cli::analyze_topk_by_capacity(file, 5)?,
-        args::TopK::ByOptimization(_) => cli::analyze_topk_by_optimization(file, 5)?,
+        args::TopK::ByCapacity(topk) => cli::analyze_topk_by_capacity(file, config.top_k(topk.k))?,
+        args::TopK::ByOptimization(topk) => {
+            cli::analyze_topk_by_optimization(file, config.top_k(topk.k))?
+        }
     };
 
     Ok(())
 }
 
-fn parse_acp(file: &PathBuf, action: args::Acp) -> Result<(), AppError> {
+fn parse_acp(
+    file: &cli::Input,
+    action: args::Acp,
+    color: args::ColorChoice,
+    config: &Config,
+) -> Result<(), AppError> {
     match action {
-        args::Acp::Capacity(_) => cli::analyze_acp_capacity(file)?,
-        args::Acp::Analysis(_) => cli::analyze_acp(file)?,
+        args::Acp::Capacity(capacity) => cli::analyze_acp_capacity(
+            file,
+            config
+                .threshold(capacity.threshold)
+                .map_err(cli::CliError::from)?,
+            !capacity.no_optimize,
+            color,
+        )?,
+        args::Acp::Objects(_) => cli::analyze_acp_objects(file)?,
+        args::Acp::Asa(asa) => cli::analyze_acp_asa(file, &asa.acl_name, asa.output.as_deref())?,
+        args::Acp::Diff(diff) => cli::analyze_acp_diff(file, &file.with_file(diff.other))?,
+        args::Acp::Analysis(analysis) if analysis.histogram => cli::analyze_acp_histogram(file)?,
+        args::Acp::Analysis(analysis) if analysis.stats => cli::analyze_acp_stats(file)?,
+        args::Acp::Analysis(analysis) if analysis.time => cli::analyze_acp_timing(file)?,
+        args::Acp::Analysis(analysis) => cli::analyze_acp(
+            file,
+            config.format(analysis.format),
+            cli::AuditOptions {
+                warn_noncanonical: analysis.warn_noncanonical,
+                detect_overlaps: analysis.detect_overlaps,
+                broad: analysis.broad,
+            },
+            color,
+            config.warn_above(analysis.warn_above),
+            analysis.output.as_deref(),
+        )?,
     };
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_error_exit_code() {
+        let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "not found");
+
+        assert_eq!(AppError::from(cli::CliError::Io(io_error)).exit_code(), 2);
+        assert_eq!(
+            AppError::from(cli::CliError::RuleEmpty {
+                name: "rule".to_string()
+            })
+            .exit_code(),
+            1
+        );
+        assert_eq!(
+            AppError::from(cli::CliError::CapacityExceeded {
+                count: 1,
+                threshold: 1
+            })
+            .exit_code(),
+            3
+        );
+    }
+}